@@ -0,0 +1,175 @@
+//! Structured append: a message split over up to 16 qr codes, whose headers
+//! quirc does not read.
+//!
+//! Neither does it read the FNC1 indicator of GS1 codes, which are decoded
+//! here as well.
+//!
+//! Macro PDF417 splits a file over any number of PDF417 codes in the same way.
+
+use quircs::Code;
+use rxing::common::BitMatrix;
+use rxing::qrcode::cpp_port::decoder::Decode;
+
+/// Position of a code in a structured append sequence.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Header {
+    /// Position in the sequence from 0, `None` once the parts are reassembled
+    /// into the message.
+    pub index: Option<u8>,
+    pub count: u8,
+    /// Xor of all bytes of the message, which tells sequences apart.
+    pub parity: u8,
+}
+
+/// Position of a PDF417 code in the Macro PDF417 file it is a segment of.
+#[derive(Clone, PartialEq)]
+pub struct Segment {
+    /// Identifies the file, which tells files apart.
+    pub file_id: String,
+    /// Position in the file from 0, `None` once the segments are reassembled
+    /// into the file.
+    pub index: Option<usize>,
+    /// Number of segments of the file, which is optional.
+    pub count: Option<usize>,
+    /// Whether this is the last segment, which the last one has to tell.
+    pub last: bool,
+}
+
+/// Decode a code that quirc stopped reading at its structured append header
+/// or FNC1 indicator.
+///
+/// Returns the header, if any, and the payload that follows it. The payload
+/// of a GS1 code starts with its `]Q3` symbology identifier.
+pub fn decode(code: &Code) -> Option<(Option<Header>, Vec<u8>)> {
+    let size = code.size as u32;
+    let mut bits = BitMatrix::new(size, size).ok()?;
+    for y in 0..size {
+        for x in 0..size {
+            let i = (y * size + x) as usize;
+            if code.cell_bitmap[i >> 3] >> (i & 7) & 1 != 0 {
+                bits.set(x, y);
+            }
+        }
+    }
+    let result = Decode(&bits).ok()?;
+    let sequence = result.structuredAppend();
+    let gs1 = result.symbologyIdentifier() == "]Q3";
+    if !result.isValid() || (sequence.index < 0 && !gs1) {
+        return None;
+    }
+    let header = match sequence.index {
+        index if index < 0 => None,
+        index => Some(Header {
+            index: Some(index as u8),
+            count: sequence.count as u8,
+            parity: sequence.id.parse().ok()?,
+        }),
+    };
+    let mut payload = match gs1 {
+        true => b"]Q3".to_vec(),
+        false => vec![],
+    };
+    // Text with an ECI has already been converted to utf-8.
+    match result.content().has_eci {
+        true => payload.extend(result.text().into_bytes()),
+        false => payload.extend(result.content().bytes()),
+    }
+    Some((header, payload))
+}
+
+/// The complete sequences among `headers`, each as the positions of its parts
+/// in order.
+///
+/// A part found more than once (say in two photos of the same code) is taken
+/// from its first position.
+pub fn sequences(headers: &[Option<Header>]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<(Header, Vec<Option<usize>>)> = Vec::new();
+    for (position, header) in headers.iter().enumerate() {
+        let Some(Header {
+            index: Some(index),
+            count,
+            parity,
+        }) = *header
+        else {
+            continue;
+        };
+        if index >= count {
+            continue;
+        }
+        let key = Header {
+            index: None,
+            count,
+            parity,
+        };
+        let g = match groups.iter().position(|(k, _)| *k == key) {
+            Some(g) => g,
+            None => {
+                groups.push((key, vec![None; count as usize]));
+                groups.len() - 1
+            }
+        };
+        groups[g].1[index as usize].get_or_insert(position);
+    }
+    groups
+        .into_iter()
+        .filter_map(|(_, parts)| parts.into_iter().collect())
+        .collect()
+}
+
+/// Parity byte of a message, the xor of all its bytes.
+pub fn parity(message: &[u8]) -> u8 {
+    message.iter().fold(0, |p, b| p ^ b)
+}
+
+/// The complete Macro PDF417 files among `segments`, each as the positions
+/// of its segments in order.
+///
+/// A file is complete once its last segment and all segments before it are
+/// found. A segment found more than once is taken from its first position.
+pub fn files(segments: &[Option<&Segment>]) -> Vec<Vec<usize>> {
+    struct File<'a> {
+        id: &'a str,
+        parts: Vec<Option<usize>>,
+        count: Option<usize>,
+    }
+    let mut files: Vec<File> = Vec::new();
+    for (position, segment) in segments.iter().enumerate() {
+        let Some(Segment {
+            file_id,
+            index: Some(index),
+            count,
+            last,
+        }) = segment
+        else {
+            continue;
+        };
+        let f = match files.iter().position(|f| f.id == file_id) {
+            Some(f) => f,
+            None => {
+                files.push(File {
+                    id: file_id,
+                    parts: Vec::new(),
+                    count: None,
+                });
+                files.len() - 1
+            }
+        };
+        let file = &mut files[f];
+        if file.parts.len() <= *index {
+            file.parts.resize(index + 1, None);
+        }
+        file.parts[*index].get_or_insert(position);
+        match (last, count) {
+            (true, _) => file.count = Some(index + 1),
+            (false, Some(count)) => file.count = file.count.or(Some(*count)),
+            _ => {}
+        }
+    }
+    files
+        .into_iter()
+        .filter_map(|mut file| {
+            file.parts.resize(file.count?, None);
+            file.parts.into_iter().collect()
+        })
+        .collect()
+}
@@ -0,0 +1,104 @@
+//! Aztec codes, as found on boarding passes and train tickets, read with
+//! rxing.
+
+use image::{imageops, GrayImage};
+use quircs::{Data, Point};
+use rxing::aztec::decoder;
+use rxing::aztec::detector::Detector;
+use rxing::common::{DetectorRXingResult, HybridBinarizer};
+use rxing::{BinaryBitmap, Luma8LuminanceSource};
+
+use crate::micro::Symbol;
+
+/// Find and decode all aztec codes of a grayscale image, mirrored or not.
+///
+/// rxing only looks for the bullseye nearest to the center of an image, so
+/// the halves and quarters of the image are searched as well. The version
+/// of a symbol is its number of layers, and its corners are those of the
+/// image, clockwise from the top left, whichever way the symbol is rotated.
+pub fn aztecs(image: &GrayImage) -> Vec<Symbol> {
+    let (w, h) = image.dimensions();
+    let (hw, hh) = (w / 2, h / 2);
+    let regions = [
+        (0, 0, w, h),
+        (0, 0, hw, h),
+        (hw, 0, w - hw, h),
+        (0, 0, w, hh),
+        (0, hh, w, h - hh),
+        (0, 0, hw, hh),
+        (hw, 0, w - hw, hh),
+        (0, hh, hw, h - hh),
+        (hw, hh, w - hw, h - hh),
+    ];
+    let mut v: Vec<Symbol> = Vec::new();
+    for (x, y, width, height) in regions {
+        let region = imageops::crop_imm(image, x, y, width, height).to_image();
+        let Some((mut corners, modules, data)) = aztec(&region) else {
+            continue;
+        };
+        corners.iter_mut().for_each(|p| {
+            p.x += x as i32;
+            p.y += y as i32;
+        });
+        // The same code is found again in the regions that contain it.
+        let [a, _, c, _] = corners;
+        let center = Point {
+            x: (a.x + c.x) / 2,
+            y: (a.y + c.y) / 2,
+        };
+        if !v
+            .iter()
+            .any(|(other, _, d)| d.payload == data.payload && contains(other, center))
+        {
+            v.push((corners, modules, data));
+        }
+    }
+    v
+}
+
+/// Whether `p` is within the bounds of `corners`.
+pub fn contains(corners: &[Point; 4], p: Point) -> bool {
+    let xs = corners.iter().map(|c| c.x);
+    let ys = corners.iter().map(|c| c.y);
+    (xs.clone().min().unwrap_or_default()..=xs.max().unwrap_or_default()).contains(&p.x)
+        && (ys.clone().min().unwrap_or_default()..=ys.max().unwrap_or_default()).contains(&p.y)
+}
+
+/// Find and decode the aztec code nearest to the center of a grayscale image.
+fn aztec(image: &GrayImage) -> Option<Symbol> {
+    let source = Luma8LuminanceSource::new(image.to_vec(), image.width(), image.height()).ok()?;
+    let bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    let mut detector = Detector::new(bitmap.get_black_matrix());
+    let (detected, result) = [false, true].into_iter().find_map(|mirrored| {
+        let detected = detector.detect(mirrored).ok()?;
+        let result = decoder::decode(&detected).ok()?;
+        Some((detected, result))
+    })?;
+    // The order of the corners depends on how the bullseye was found, they
+    // are sorted clockwise by their angle around the center, which goes
+    // clockwise as y goes down.
+    let points = detected.getPoints();
+    let (cx, cy) = (
+        points.iter().map(|p| p.x).sum::<f32>() / 4.0,
+        points.iter().map(|p| p.y).sum::<f32>() / 4.0,
+    );
+    let mut corners = [0, 1, 2, 3].map(|i| Point {
+        x: points[i].x.round() as i32,
+        y: points[i].y.round() as i32,
+    });
+    corners.sort_by(|a, b| {
+        let angle = |p: &Point| (p.y as f32 - cy).atan2(p.x as f32 - cx);
+        angle(a).total_cmp(&angle(b))
+    });
+    let top_left = (0..4).min_by_key(|&i| corners[i].x + corners[i].y)?;
+    corners.rotate_left(top_left);
+    Some((
+        corners,
+        (detected.getBits().width(), detected.getBits().height()),
+        Data {
+            version: detected.getNbLayers() as usize,
+            payload: result.getText().as_bytes().to_vec(),
+            ..Default::default()
+        },
+    ))
+}
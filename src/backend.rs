@@ -0,0 +1,173 @@
+//! Engines that locate qr codes in an image and sample their grids.
+//!
+//! Every engine hands its grids over as quirc codes, so that they are decoded
+//! and described the same way no matter which one found them.
+
+use image::GrayImage;
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{FromValue, Spanned};
+use quircs::{Code, Point};
+use rqrr::BitGrid;
+use rxing::common::{BitMatrix, DetectorRXingResult, HybridBinarizer};
+use rxing::qrcode::cpp_port::detector::{FindFinderPatterns, GenerateFinderPatternSets, SampleQR};
+use rxing::{BinaryBitmap, Luma8LuminanceSource};
+
+use crate::decode::decode;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Backend {
+    Quircs,
+    Rqrr,
+    /// The qr code detector of rxing, a port of ZXing, which also finds micro
+    /// qr codes.
+    Rxing,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 3] = [Backend::Quircs, Backend::Rqrr, Backend::Rxing];
+
+    /// Backends to try in order, until one of them finds a code.
+    ///
+    /// `--backends` lists them all, otherwise `--backend` (or quircs) comes
+    /// first and the others follow.
+    pub fn chain_from_call(call: &EvaluatedCall) -> Result<Vec<Self>, LabeledError> {
+        let primary = call.get_flag::<Spanned<String>>("backend")?;
+        let Some(value) = call.get_flag_value("backends") else {
+            let primary = match primary {
+                Some(name) => Backend::parse(&name)?,
+                None => Backend::Quircs,
+            };
+            let others = Backend::ALL.into_iter().filter(|b| *b != primary);
+            return Ok(std::iter::once(primary).chain(others).collect());
+        };
+        let span = value.span().unwrap_or(call.head);
+        if primary.is_some() {
+            return Err(LabeledError {
+                label: "Conflicting flags".into(),
+                msg: "--backend can not be combined with --backends".into(),
+                span: Some(span),
+            });
+        }
+        let chain = value
+            .as_list()
+            .map_err(|_| LabeledError {
+                label: "Invalid backends".into(),
+                msg: "should be a list of backend names".into(),
+                span: Some(span),
+            })?
+            .iter()
+            .map(|v| Backend::parse(&Spanned::<String>::from_value(v)?))
+            .collect::<Result<Vec<_>, _>>()?;
+        match chain.is_empty() {
+            true => Err(LabeledError {
+                label: "Invalid backends".into(),
+                msg: "should list at least one backend".into(),
+                span: Some(span),
+            }),
+            false => Ok(chain),
+        }
+    }
+
+    fn parse(name: &Spanned<String>) -> Result<Self, LabeledError> {
+        Backend::ALL
+            .into_iter()
+            .find(|b| b.name() == name.item)
+            .ok_or_else(|| LabeledError {
+                label: "Unknown backend".into(),
+                msg: format!("{} is not one of: quircs, rqrr, rxing", name.item),
+                span: Some(name.span),
+            })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Quircs => "quircs",
+            Backend::Rqrr => "rqrr",
+            Backend::Rxing => "rxing",
+        }
+    }
+
+    /// Locate all qr codes of `image` and sample their grids.
+    pub fn grids(&self, image: &GrayImage) -> Vec<Result<Code, String>> {
+        let (w, h) = (image.width() as usize, image.height() as usize);
+        match self {
+            Backend::Quircs => quircs::Quirc::default()
+                .identify(w, h, image)
+                .map(|code| code.map_err(|e| e.to_string()))
+                .collect(),
+            Backend::Rqrr => {
+                let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(w, h, |x, y| {
+                    image.get_pixel(x as u32, y as u32).0[0]
+                });
+                prepared
+                    .detect_grids()
+                    .into_iter()
+                    .map(|grid| {
+                        code(
+                            grid.bounds.map(|p| Point { x: p.x, y: p.y }),
+                            grid.grid.size(),
+                            |x, y| grid.grid.bit(y, x),
+                        )
+                    })
+                    .collect()
+            }
+            Backend::Rxing => {
+                let Ok(source) = Luma8LuminanceSource::new(image.to_vec(), w as u32, h as u32)
+                else {
+                    return vec![];
+                };
+                let bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+                let bits = bitmap.get_black_matrix();
+                let mut patterns = FindFinderPatterns(bits, true, 0);
+                // Most sets of finder patterns are not the corners of a code,
+                // so like rxing itself, only grids that decode are kept.
+                GenerateFinderPatternSets(&mut patterns)
+                    .iter()
+                    .filter_map(|set| SampleQR(bits, set).ok())
+                    .filter(|detected| detected.getPoints().len() >= 4)
+                    .filter_map(|detected| {
+                        let points = detected.getPoints();
+                        // rxing orders the corners as rows, quirc clockwise.
+                        let corners = [0, 1, 3, 2].map(|i| Point {
+                            x: points[i].x.round() as i32,
+                            y: points[i].y.round() as i32,
+                        });
+                        let matrix: &BitMatrix = detected.getBits();
+                        code(corners, matrix.width() as usize, |x, y| {
+                            matrix.get(x as u32, y as u32)
+                        })
+                        .ok()
+                    })
+                    .filter(|grid| decode(grid).is_ok())
+                    .map(Ok)
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Build a quirc code of `size` by `size` cells, `dark` telling the color of
+/// the cell at `(x, y)`.
+fn code(
+    corners: [Point; 4],
+    size: usize,
+    dark: impl Fn(usize, usize) -> bool,
+) -> Result<Code, String> {
+    let mut code = Code {
+        corners,
+        size: size as i32,
+        ..Default::default()
+    };
+    if size * size > code.cell_bitmap.len() * 8 {
+        return Err(format!("grid of {} cells is too large", size));
+    }
+    for y in 0..size {
+        for x in 0..size {
+            if dark(x, y) {
+                let i = y * size + x;
+                code.cell_bitmap[i >> 3] |= 1 << (i & 7);
+            }
+        }
+    }
+    Ok(code)
+}
@@ -0,0 +1,666 @@
+//! One dimensional barcodes, rendered as png or svg.
+
+use std::io::Cursor;
+
+use image::{GrayImage, ImageOutputFormat, Luma};
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{Span, Value};
+use rxing::oned::{
+    CodaBarWriter, Code128Writer, Code39Writer, EAN13Writer, EAN8Writer, OneDimensionalCodeWriter,
+    UPCEWriter,
+};
+
+use crate::postal::{self, Bar};
+use crate::{databar, payloads};
+
+/// Light margin on either side of the bars, in modules.
+const QUIET_ZONE: usize = 10;
+
+/// Pixels per module unless `--width` asks for another size.
+const DEFAULT_SCALE: usize = 3;
+
+/// Height of the human readable text below the bars, in modules.
+const TEXT_HEIGHT: usize = 10;
+
+/// 5x7 glyphs of the digits, a row per byte with the leftmost pixel in bit 4.
+#[rustfmt::skip]
+const DIGITS: [[u8; 7]; 10] = [
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e], [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f], [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02], [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e], [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e], [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+];
+
+/// Characters of Code 39 in the order of their values for the mod 43 check.
+const CODE39: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+
+/// Symbologies of `to barcode`.
+#[derive(Clone, Copy)]
+pub enum Symbology {
+    Code128,
+    Code39 { mod43: bool },
+    Codabar,
+    Ean13,
+    Ean8,
+    UpcA,
+    UpcE,
+    DataBar,
+    DataBarExpanded,
+    Postnet,
+    IntelligentMail,
+    RoyalMail,
+    Pharmacode,
+    PharmacodeTwoTrack,
+}
+
+/// The bars of an encoded barcode and its human readable digits.
+pub struct Symbol {
+    /// Modules from left to right, `true` for a bar.
+    bars: Vec<bool>,
+    /// Modules of the guard bars, which extend into the text.
+    guards: Vec<std::ops::Range<usize>>,
+    /// Groups of digits below the bars, each a digit per 7 modules from the
+    /// module it starts at, which is negative left of the bars.
+    text: Vec<(isize, String)>,
+    /// How far each module reaches up and down, for postal barcodes and
+    /// two-track pharmacode whose bars differ in height; empty when all are
+    /// as high.
+    reach: Vec<Bar>,
+}
+
+/// The GS1 mod 10 check digit of `digits`.
+fn check_digit(digits: &str) -> char {
+    let sum: u32 = digits
+        .chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { 3 * d } else { d })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+}
+
+/// Complete `digits` with their check digit, or verify the one given.
+fn with_check_digit(digits: &str, length: usize) -> Result<String, String> {
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err("should only contain digits".into());
+    }
+    match digits.len() {
+        n if n == length - 1 => Ok(format!("{}{}", digits, check_digit(digits))),
+        n if n == length => {
+            let expected = check_digit(&digits[..n - 1]);
+            match digits.ends_with(expected) {
+                true => Ok(digits.to_string()),
+                false => Err(format!("check digit should be {}", expected)),
+            }
+        }
+        n => Err(format!(
+            "should have {} digits, or {} without the check digit, got {}",
+            length,
+            length - 1,
+            n
+        )),
+    }
+}
+
+/// Expand the 7 digits of a UPC-E number, without its check digit, into
+/// the 11 of its UPC-A number.
+fn expand_upce(upce: &str) -> String {
+    let d = upce.as_bytes();
+    let s = |r: std::ops::Range<usize>| &upce[r];
+    match d[6] {
+        b'0'..=b'2' => format!("{}{}0000{}", s(0..3), s(6..7), s(3..6)),
+        b'3' => format!("{}00000{}", s(0..4), s(4..6)),
+        b'4' => format!("{}00000{}", s(0..5), s(5..6)),
+        _ => format!("{}0000{}", s(0..6), s(6..7)),
+    }
+}
+
+/// Zero-suppress the 11 digits of a UPC-A number, without its check digit,
+/// into the 7 of a UPC-E number, if it has the zeros to suppress.
+fn suppress_upca(upca: &str) -> Option<String> {
+    let (system, manufacturer, product) = (&upca[..1], &upca[1..6], &upca[6..]);
+    if system != "0" && system != "1" {
+        return None;
+    }
+    let upce = if manufacturer[3..] == *"00"
+        && manufacturer.as_bytes()[2] <= b'2'
+        && product.starts_with("00")
+    {
+        format!(
+            "{}{}{}",
+            &manufacturer[..2],
+            &product[2..],
+            &manufacturer[2..3]
+        )
+    } else if manufacturer[3..] == *"00" && product.starts_with("000") {
+        format!("{}{}3", &manufacturer[..3], &product[3..])
+    } else if manufacturer.ends_with('0') && product.starts_with("0000") {
+        format!("{}{}4", &manufacturer[..4], &product[4..])
+    } else if product.starts_with("0000") && product.as_bytes()[4] >= b'5' {
+        format!("{}{}", manufacturer, &product[4..])
+    } else {
+        return None;
+    };
+    let upce = format!("{}{}", system, upce);
+    // Some numbers fit the patterns above but expand to another one.
+    (expand_upce(&upce) == upca).then_some(upce)
+}
+
+/// The 8 digits of a UPC-E number with its check digit, from those of a UPC-E
+/// or UPC-A number with or without their check digit.
+fn upce_digits(digits: &str) -> Result<String, String> {
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err("should only contain digits".into());
+    }
+    let upce = match digits.len() {
+        7 | 8 => digits[..7].to_string(),
+        11 | 12 => {
+            let upca = with_check_digit(digits, 12)?;
+            suppress_upca(&upca[..11])
+                .ok_or_else(|| format!("{} cannot be zero-suppressed into upc-e", upca))?
+        }
+        n => {
+            return Err(format!(
+                "should have 8 digits (or 7 without the check digit) of upc-e, or 12 (or 11) of upc-a, got {}",
+                n
+            ))
+        }
+    };
+    if !upce.starts_with(['0', '1']) {
+        return Err("the number system of upc-e should be 0 or 1".into());
+    }
+    let check = check_digit(&expand_upce(&upce));
+    match digits.len() {
+        8 if !digits.ends_with(check) => Err(format!("check digit should be {}", check)),
+        _ => Ok(format!("{}{}", upce, check)),
+    }
+}
+
+/// Spell `text` with the characters of Code 39, as pairs of them for the
+/// characters of Full ASCII Code 39 if it has any.
+fn code39_text(text: &str) -> Result<String, String> {
+    if text.chars().all(|c| CODE39.contains(c)) {
+        return Ok(text.to_string());
+    }
+    let letter = |base: u8, c: u8, from: u8| (base + c - from) as char;
+    text.chars()
+        .map(|c| {
+            let b = u8::try_from(c).ok().filter(u8::is_ascii).ok_or_else(|| {
+                format!(
+                    "{:?} cannot be encoded, code39 only has ascii characters",
+                    c
+                )
+            })?;
+            Ok(match b {
+                0 => "%U".to_string(),
+                1..=26 => format!("${}", letter(b'A', b, 1)),
+                27..=31 => format!("%{}", letter(b'A', b, 27)),
+                b'!'..=b',' => format!("/{}", letter(b'A', b, b'!')),
+                b'/' => "/O".to_string(),
+                b':' => "/Z".to_string(),
+                b';'..=b'?' => format!("%{}", letter(b'F', b, b';')),
+                b'@' => "%V".to_string(),
+                b'['..=b'_' => format!("%{}", letter(b'K', b, b'[')),
+                b'`' => "%W".to_string(),
+                b'a'..=b'z' => format!("+{}", letter(b'A', b, b'a')),
+                b'{'..=127 => format!("%{}", letter(b'P', b, b'{')),
+                _ => c.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Frame `text` with the start and stop characters of Codabar, `A` unless it
+/// starts and ends with its own.
+fn codabar_text(text: &str) -> Result<String, String> {
+    let text = text.to_uppercase();
+    let is_guard = |c: Option<char>| matches!(c, Some('A'..='D'));
+    let data = match (is_guard(text.chars().next()), is_guard(text.chars().last())) {
+        (true, true) if text.len() > 1 => &text[1..text.len() - 1],
+        (false, false) => &text[..],
+        _ => return Err(
+            "should start and end with one of the start/stop characters A to D, or with neither"
+                .into(),
+        ),
+    };
+    if data.is_empty() {
+        return Err("should have characters between the start and stop characters".into());
+    }
+    if let Some(c) = data.chars().find(|c| !"0123456789-$:/.+".contains(*c)) {
+        return Err(format!(
+            "{:?} cannot be encoded, codabar only has digits and -$:/.+",
+            c
+        ));
+    }
+    match text.len() == data.len() {
+        true => Ok(format!("A{}A", text)),
+        false => Ok(text),
+    }
+}
+
+/// Read the value of a pharmacode, which is within `range`.
+fn pharmacode_value(text: &str, range: std::ops::RangeInclusive<u32>) -> Result<u32, String> {
+    match text.trim().parse::<u32>() {
+        Ok(n) if range.contains(&n) => Ok(n),
+        _ => Err(format!(
+            "should be a number from {} to {}",
+            range.start(),
+            range.end()
+        )),
+    }
+}
+
+/// Bars of one-track pharmacode from right to left, a narrow one for odd
+/// digits of its value in bijective base 2 and a wide one for even ones,
+/// as 1 and 3 modules 2 modules apart.
+fn pharmacode(text: &str) -> Result<Vec<bool>, String> {
+    let mut n = pharmacode_value(text, 3..=131070)?;
+    let mut bars = vec![];
+    while n > 0 {
+        let (width, digit) = match n % 2 {
+            1 => (1, 1),
+            _ => (3, 2),
+        };
+        bars.push(width);
+        n = (n - digit) / 2;
+    }
+    Ok(bars
+        .iter()
+        .rev()
+        .enumerate()
+        .flat_map(|(i, width)| {
+            let space = if i == 0 { 0 } else { 2 };
+            std::iter::repeat_n(false, space).chain(std::iter::repeat_n(true, *width))
+        })
+        .collect())
+}
+
+/// Bars of two-track pharmacode, the digits of its value in bijective base
+/// 3 from right to left: 1 a bottom half, 2 a top half and 3 a full bar.
+fn pharmacode_two_track(text: &str) -> Result<Vec<Bar>, String> {
+    let mut n = pharmacode_value(text, 4..=64570080)?;
+    let mut bars = vec![];
+    while n > 0 {
+        let (bar, digit) = match n % 3 {
+            1 => (Bar::Bottom, 1),
+            2 => (Bar::Top, 2),
+            _ => (Bar::Full, 3),
+        };
+        bars.push(bar);
+        n = (n - digit) / 3;
+    }
+    bars.reverse();
+    Ok(bars)
+}
+
+impl Symbology {
+    /// The symbology called `name`, ignoring case and dashes. Code 39 has no
+    /// check character.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().replace('-', "").as_str() {
+            "code128" => Ok(Symbology::Code128),
+            "code39" => Ok(Symbology::Code39 { mod43: false }),
+            "codabar" => Ok(Symbology::Codabar),
+            "ean13" => Ok(Symbology::Ean13),
+            "ean8" => Ok(Symbology::Ean8),
+            "upca" => Ok(Symbology::UpcA),
+            "upce" => Ok(Symbology::UpcE),
+            "databar" => Ok(Symbology::DataBar),
+            "databarexpanded" => Ok(Symbology::DataBarExpanded),
+            "postnet" => Ok(Symbology::Postnet),
+            "intelligentmail" => Ok(Symbology::IntelligentMail),
+            "royalmail" => Ok(Symbology::RoyalMail),
+            "pharmacode" => Ok(Symbology::Pharmacode),
+            "pharmacodetwotrack" => Ok(Symbology::PharmacodeTwoTrack),
+            _ => Err(format!(
+                "{} is not one of: code128, code39, codabar, ean13, ean8, upca, upce, databar, databarexpanded, postnet, intelligentmail, royalmail, pharmacode, pharmacodetwotrack",
+                name
+            )),
+        }
+    }
+
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let symbology = match call.get_flag::<String>("symbology")? {
+            Some(name) => Symbology::from_name(&name).map_err(|msg| LabeledError {
+                label: "Unknown symbology".into(),
+                msg,
+                span: Some(call.head),
+            })?,
+            None => Symbology::Code128,
+        };
+        match symbology {
+            Symbology::Code39 { .. } => Ok(Symbology::Code39 {
+                mod43: call.has_flag("mod43"),
+            }),
+            _ if call.has_flag("mod43") => Err(LabeledError {
+                label: "Invalid flag".into(),
+                msg: "--mod43 only applies to code39".into(),
+                span: Some(call.head),
+            }),
+            _ => Ok(symbology),
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Result<Symbol, String> {
+        let oned = |result: rxing::common::Result<Vec<bool>>| result.map_err(|e| e.to_string());
+        Ok(match self {
+            Symbology::Code128 => Symbol {
+                bars: oned(Code128Writer.encode_oned(text))?,
+                guards: vec![],
+                text: vec![],
+                reach: vec![],
+            },
+            Symbology::Code39 { mod43 } => {
+                let mut text = code39_text(text)?;
+                if *mod43 {
+                    let sum: usize = text.chars().filter_map(|c| CODE39.find(c)).sum();
+                    text.extend(CODE39[sum % 43..].chars().next());
+                }
+                Symbol {
+                    bars: oned(Code39Writer.encode_oned(&text))?,
+                    guards: vec![],
+                    text: vec![],
+                    reach: vec![],
+                }
+            }
+            Symbology::Codabar => Symbol {
+                bars: oned(CodaBarWriter.encode_oned(&codabar_text(text)?))?,
+                guards: vec![],
+                text: vec![],
+                reach: vec![],
+            },
+            // The first digit is told by the parities of the left half.
+            Symbology::Ean13 => {
+                let digits = with_check_digit(text, 13)?;
+                Symbol {
+                    bars: oned(EAN13Writer.encode_oned(&digits))?,
+                    guards: vec![0..3, 45..50, 92..95],
+                    text: vec![
+                        (-7, digits[..1].to_string()),
+                        (3, digits[1..7].to_string()),
+                        (50, digits[7..].to_string()),
+                    ],
+                    reach: vec![],
+                }
+            }
+            Symbology::Ean8 => {
+                let digits = with_check_digit(text, 8)?;
+                Symbol {
+                    bars: oned(EAN8Writer.encode_oned(&digits))?,
+                    guards: vec![0..3, 31..36, 64..67],
+                    text: vec![(3, digits[..4].to_string()), (36, digits[4..].to_string())],
+                    reach: vec![],
+                }
+            }
+            // UPC-A is EAN-13 starting with 0, its number system and check digit
+            // have bars as long as the guards.
+            Symbology::UpcA => {
+                let digits = with_check_digit(text, 12)?;
+                Symbol {
+                    bars: oned(EAN13Writer.encode_oned(&format!("0{}", digits)))?,
+                    guards: vec![0..10, 45..50, 85..95],
+                    text: vec![
+                        (-7, digits[..1].to_string()),
+                        (10, digits[1..6].to_string()),
+                        (50, digits[6..11].to_string()),
+                        (96, digits[11..].to_string()),
+                    ],
+                    reach: vec![],
+                }
+            }
+            Symbology::UpcE => {
+                let digits = upce_digits(text)?;
+                Symbol {
+                    bars: oned(UPCEWriter.encode_oned(&digits))?,
+                    guards: vec![0..3, 45..51],
+                    text: vec![
+                        (-7, digits[..1].to_string()),
+                        (3, digits[1..7].to_string()),
+                        (52, digits[7..].to_string()),
+                    ],
+                    reach: vec![],
+                }
+            }
+            // Only a GTIN fits, the check digit is left out of the bars.
+            Symbology::DataBar => {
+                let digits = with_check_digit(text.strip_prefix("(01)").unwrap_or(text), 14)?;
+                Symbol {
+                    bars: databar::omnidirectional(&digits[..13]),
+                    guards: vec![],
+                    text: vec![],
+                    reach: vec![],
+                }
+            }
+            Symbology::DataBarExpanded => {
+                let element_string = payloads::gs1::element_string(text).ok_or_else(|| {
+                    "should be a GS1 element string of known AIs, e.g. (01)09501101530003(3103)000250"
+                        .to_string()
+                })?;
+                Symbol {
+                    bars: databar::expanded(&element_string)?,
+                    guards: vec![],
+                    text: vec![],
+                    reach: vec![],
+                }
+            }
+            Symbology::Postnet => Symbol::height_modulated(postal::postnet(text)?),
+            Symbology::IntelligentMail => Symbol::height_modulated(postal::intelligent_mail(text)?),
+            Symbology::RoyalMail => Symbol::height_modulated(postal::royal_mail(text)?),
+            Symbology::Pharmacode => Symbol {
+                bars: pharmacode(text)?,
+                guards: vec![],
+                text: vec![],
+                reach: vec![],
+            },
+            Symbology::PharmacodeTwoTrack => Symbol::height_modulated(pharmacode_two_track(text)?),
+        })
+    }
+}
+
+impl Symbol {
+    /// A barcode of `bars` differing in height, with a space after each but
+    /// the last.
+    fn height_modulated(bars: Vec<Bar>) -> Self {
+        let reach: Vec<Bar> = bars.iter().flat_map(|bar| [*bar, *bar]).collect();
+        Symbol {
+            bars: (0..reach.len() - 1).map(|i| i % 2 == 0).collect(),
+            guards: vec![],
+            text: vec![],
+            reach,
+        }
+    }
+
+    /// Rows of pixels covered by the bar of `module`, if it is one, of bars
+    /// `bar` pixels high and guard bars `guard` pixels high.
+    fn rows(&self, module: usize, bar: usize, guard: usize) -> std::ops::Range<usize> {
+        match (self.reach.get(module), self.is_guard(module)) {
+            (Some(b), _) => b.rows(bar),
+            (None, true) => 0..guard,
+            (None, false) => 0..bar,
+        }
+    }
+
+    /// Width of the symbol with its quiet zones, in modules.
+    fn modules(&self) -> usize {
+        self.bars.len() + 2 * QUIET_ZONE
+    }
+
+    /// Height of the bars and of the guard bars, and of the whole image.
+    fn heights(&self, height: usize, scale: usize) -> (usize, usize, usize) {
+        match self.text.is_empty() {
+            true => (height, height, height),
+            false => (
+                height,
+                height + TEXT_HEIGHT / 2 * scale,
+                height + TEXT_HEIGHT * scale,
+            ),
+        }
+    }
+
+    fn is_guard(&self, module: usize) -> bool {
+        self.guards.iter().any(|g| g.contains(&module))
+    }
+
+    /// The digits of the text, with the module their 7 module cell starts
+    /// at counting the quiet zone.
+    fn digits(&self) -> impl Iterator<Item = (usize, char)> + '_ {
+        self.text.iter().flat_map(|(start, digits)| {
+            digits
+                .chars()
+                .enumerate()
+                .map(move |(i, c)| ((QUIET_ZONE as isize + start + 7 * i as isize) as usize, c))
+        })
+    }
+}
+
+/// Render the bars `height` pixels high with `scale` pixels per module.
+pub fn png(symbol: &Symbol, scale: usize, height: usize) -> Result<Vec<u8>, String> {
+    let (bar, guard, total) = symbol.heights(height, scale);
+    let mut image = GrayImage::from_fn((symbol.modules() * scale) as u32, total as u32, |x, y| {
+        let module = (x as usize / scale).wrapping_sub(QUIET_ZONE);
+        match symbol.bars.get(module) {
+            Some(true) if symbol.rows(module, bar, guard).contains(&(y as usize)) => Luma([0]),
+            _ => Luma([255]),
+        }
+    });
+    // Glyph pixels are a module wide, centered in the 7 modules of a digit.
+    for (cell, c) in symbol.digits() {
+        let Some(glyph) = c.to_digit(10).map(|d| DIGITS[d as usize]) else {
+            continue;
+        };
+        for (row, bits) in glyph.iter().enumerate() {
+            for col in 0..5 {
+                if bits >> (4 - col) & 1 == 0 {
+                    continue;
+                }
+                let (x0, y0) = ((cell + 1 + col) * scale, bar + (2 + row) * scale);
+                for y in y0..y0 + scale {
+                    for x in x0..x0 + scale {
+                        image.put_pixel(x as u32, y as u32, Luma([0]));
+                    }
+                }
+            }
+        }
+    }
+    let mut buf = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
+
+/// Render the bars as an svg document, one rectangle per bar.
+pub fn svg(symbol: &Symbol, scale: usize, height: usize) -> String {
+    let (bar, guard, total) = symbol.heights(height, scale);
+    let width = symbol.modules() * scale;
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" shape-rendering=\"crispEdges\">\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#fff\"/>",
+        w = width,
+        h = total
+    );
+    let bars = &symbol.bars;
+    let mut x = 0;
+    while x < bars.len() {
+        let rows = symbol.rows(x, bar, guard);
+        let run = bars[x..]
+            .iter()
+            .enumerate()
+            .take_while(|(i, b)| **b == bars[x] && symbol.rows(x + i, bar, guard) == rows)
+            .count();
+        if bars[x] {
+            out.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>",
+                (x + QUIET_ZONE) * scale,
+                rows.start,
+                run * scale,
+                rows.len()
+            ));
+        }
+        x += run;
+    }
+    for (cell, c) in symbol.digits() {
+        out.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"{}\" text-anchor=\"middle\">{}</text>",
+            cell * scale + 7 * scale / 2,
+            bar + 9 * scale,
+            9 * scale,
+            c
+        ));
+    }
+    out.push_str("</svg>");
+    out
+}
+
+/// Render the text input of `to barcode`.
+pub fn to_barcode(
+    call: &EvaluatedCall,
+    input: &Value,
+    input_span: Option<Span>,
+) -> Result<Value, LabeledError> {
+    let text = match input {
+        Value::String { val, .. } => val.clone(),
+        Value::Int { val, .. } => val.to_string(),
+        v => {
+            return Err(LabeledError {
+                label: "Unsupported input".into(),
+                msg: format!("expected a string, got {}", v.get_type()),
+                span: input_span,
+            })
+        }
+    };
+    let symbol = Symbology::from_call(call)?
+        .encode(&text)
+        .map_err(|e| LabeledError {
+            label: "failed to generate barcode".into(),
+            msg: e,
+            span: input_span,
+        })?;
+    let modules = symbol.modules();
+    let scale = match call.get_flag::<usize>("width")? {
+        Some(width) if width >= modules && width < u32::MAX as usize => width / modules,
+        Some(_) => {
+            return Err(LabeledError {
+                label: "Invalid width".into(),
+                msg: format!(
+                    "this barcode is {} modules wide, the width should be between {} and {}",
+                    modules,
+                    modules,
+                    u32::MAX
+                ),
+                span: Some(call.head),
+            })
+        }
+        None => DEFAULT_SCALE,
+    };
+    let height = match call.get_flag::<usize>("height")? {
+        Some(height) if height > 0 && height < u32::MAX as usize => height,
+        Some(_) => {
+            return Err(LabeledError {
+                label: "Invalid height".into(),
+                msg: format!("height should be between 1 and {}", u32::MAX),
+                span: Some(call.head),
+            })
+        }
+        None => 100,
+    };
+    match call.has_flag("svg") {
+        true => Ok(Value::String {
+            val: svg(&symbol, scale, height),
+            span: call.head,
+        }),
+        false => match png(&symbol, scale, height) {
+            Ok(val) => Ok(Value::Binary {
+                val,
+                span: call.head,
+            }),
+            Err(msg) => Err(LabeledError {
+                label: "failed to generate png".into(),
+                msg,
+                span: Some(call.head),
+            }),
+        },
+    }
+}
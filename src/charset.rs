@@ -0,0 +1,218 @@
+//! Conversion of decoded payloads to text.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use encoding_rs::Encoding;
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{Span, Spanned, Value};
+use quircs::{Data, Eci};
+
+use crate::{payloads, serialize};
+
+/// How payload bytes are turned into strings.
+pub struct Charset {
+    /// Never convert, always return binary.
+    raw: bool,
+    /// Encoding of the payload, overriding the one declared by the symbol.
+    encoding: Option<&'static Encoding>,
+    /// Try legacy encodings on payloads that are not utf-8.
+    guess: bool,
+    /// Text that payloads which are not text are returned as, instead of binary.
+    bytes_as: Option<BytesAs>,
+    /// Parse text in a well-known payload format, such as wifi, into records.
+    parse: bool,
+    /// Hide secrets, such as passwords, of parsed payloads.
+    redact: bool,
+    /// Parse text that looks like json, nuon or a url into structured values.
+    auto: bool,
+    /// Decode EU Digital COVID Certificates into their claims.
+    dcc: bool,
+}
+
+/// Text representations of binary payloads, from `--bytes-as`.
+#[derive(Clone, Copy)]
+enum BytesAs {
+    Hex,
+    Base64,
+}
+
+impl Charset {
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let encoding = match call.get_flag::<String>("encoding")? {
+            Some(label) => Some(Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+                LabeledError {
+                    label: "Unknown encoding".into(),
+                    msg: format!(
+                        "{} is not a known encoding, try e.g. utf-8, shift_jis, euc-kr, gbk, latin1",
+                        label
+                    ),
+                    span: Some(call.head),
+                }
+            })?),
+            None => None,
+        };
+        let bytes_as = match call.get_flag::<Spanned<String>>("bytes-as")? {
+            Some(name) => Some(match name.item.as_str() {
+                "hex" => BytesAs::Hex,
+                "base64" => BytesAs::Base64,
+                _ => {
+                    return Err(LabeledError {
+                        label: "Unknown representation".into(),
+                        msg: format!("{} is not one of: hex, base64", name.item),
+                        span: Some(name.span),
+                    })
+                }
+            }),
+            None => None,
+        };
+        Ok(Charset {
+            raw: call.has_flag("raw"),
+            encoding,
+            guess: call.has_flag("guess-encoding"),
+            bytes_as,
+            parse: call.has_flag("parse"),
+            redact: call.has_flag("redact"),
+            auto: call.has_flag("auto"),
+            dcc: call.has_flag("dcc"),
+        })
+    }
+
+    /// Decode the payload of `data` to a string, `None` if it is not valid text.
+    ///
+    /// Without `--encoding` the ECI designator of the symbol is honored,
+    /// falling back to utf-8 and, with `--guess-encoding`, legacy encodings.
+    pub fn decode(&self, data: &Data) -> Option<String> {
+        if self.raw {
+            return None;
+        }
+        match self.encoding.or_else(|| data.eci.and_then(eci_encoding)) {
+            Some(encoding) => decode_strict(encoding, &data.payload),
+            None => match std::str::from_utf8(&data.payload) {
+                Ok(s) => Some(s.into()),
+                Err(_) if self.guess => guess(&data.payload),
+                Err(_) => None,
+            },
+        }
+    }
+
+    pub fn to_value(&self, data: &Data, span: Span) -> Value {
+        match self.decode(data) {
+            Some(val) => self.text(val, span),
+            None => self.binary(data.payload.clone(), span),
+        }
+    }
+
+    /// A payload that is text, parsed with `--dcc`, `--parse` and `--auto` if
+    /// it is structured.
+    pub fn text(&self, val: String, span: Span) -> Value {
+        let parsed = self
+            .dcc
+            .then(|| payloads::dcc::parse(&val, span))
+            .flatten()
+            .or_else(|| self.parse.then(|| payloads::parse(&val, span)).flatten())
+            .map(|mut record| {
+                if self.redact {
+                    payloads::redact(&mut record);
+                }
+                record
+            })
+            .or_else(|| self.auto.then(|| serialize::sniff(&val, span)).flatten());
+        match parsed {
+            Some(value) => value,
+            None => Value::String { val, span },
+        }
+    }
+
+    /// A payload that is not text, as binary or the text `--bytes-as` asks for.
+    pub fn binary(&self, bytes: Vec<u8>, span: Span) -> Value {
+        match self.bytes_as {
+            None => Value::Binary { val: bytes, span },
+            Some(BytesAs::Hex) => Value::String {
+                val: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+                span,
+            },
+            Some(BytesAs::Base64) => Value::String {
+                val: STANDARD.encode(bytes),
+                span,
+            },
+        }
+    }
+}
+
+fn decode_strict(encoding: &'static Encoding, bytes: &[u8]) -> Option<String> {
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(String::from)
+}
+
+/// Decode `bytes` with the most plausible of shift-jis, gbk and latin1.
+///
+/// Every candidate that decodes without error is scored by the share of
+/// characters that are rare in that encoding, e.g. halfwidth katakana in
+/// shift-jis or characters outside of GB2312 in gbk; the lowest share wins.
+fn guess(bytes: &[u8]) -> Option<String> {
+    use encoding_rs::{GBK, SHIFT_JIS, WINDOWS_1252};
+    let rare_sjis = |c: &[u8]| match c {
+        [b] => (0xa1..=0xdf).contains(b),
+        [lead, _] => *lead > 0x98,
+        _ => false,
+    };
+    let rare_gbk = |c: &[u8]| match c {
+        [lead, trail] => {
+            !(0xb0..=0xf7).contains(lead) && !(0xa1..=0xa9).contains(lead) || *trail < 0xa1
+        }
+        _ => false,
+    };
+    let mut best: Option<(f64, String)> = None;
+    for (encoding, rare) in [
+        (SHIFT_JIS, &rare_sjis as &dyn Fn(&[u8]) -> bool),
+        (GBK, &rare_gbk),
+    ] {
+        if let Some(text) = decode_strict(encoding, bytes) {
+            let mut buf = [0; 4];
+            let count = text
+                .chars()
+                .filter(|c| rare(&encoding.encode(c.encode_utf8(&mut buf)).0))
+                .count();
+            let score = count as f64 / text.chars().count().max(1) as f64;
+            if best.as_ref().is_none_or(|(s, _)| score < *s) {
+                best = Some((score, text));
+            }
+        }
+    }
+    // Accented letters seldom come in runs of three, and C1 controls not at all.
+    let high = |i: usize| bytes.get(i).is_some_and(|b| *b >= 0x80);
+    let count = (0..bytes.len())
+        .filter(|&i| {
+            (0x80..0xa0).contains(&bytes[i]) || high(i) && i > 0 && high(i - 1) && high(i + 1)
+        })
+        .count();
+    let score = count as f64 / bytes.len().max(1) as f64;
+    match best {
+        Some((s, text)) if s <= score => Some(text),
+        _ => decode_strict(WINDOWS_1252, bytes),
+    }
+}
+
+/// The encoding designated by an ECI, if it is supported.
+fn eci_encoding(eci: Eci) -> Option<&'static Encoding> {
+    use encoding_rs::*;
+    match eci {
+        // iso-8859-1 and -9 are decoded as their windows supersets, like browsers do.
+        Eci::Iso8859_1 => Some(WINDOWS_1252),
+        Eci::Ibm437 => None,
+        Eci::Iso8859_2 => Some(ISO_8859_2),
+        Eci::Iso8859_3 => Some(ISO_8859_3),
+        Eci::Iso8859_4 => Some(ISO_8859_4),
+        Eci::Iso8859_5 => Some(ISO_8859_5),
+        Eci::Iso8859_6 => Some(ISO_8859_6),
+        Eci::Iso8859_7 => Some(ISO_8859_7),
+        Eci::Iso8859_8 => Some(ISO_8859_8),
+        Eci::Iso8859_9 => Some(WINDOWS_1254),
+        Eci::Windows874 => Some(WINDOWS_874),
+        Eci::Iso8859_13 => Some(ISO_8859_13),
+        Eci::Iso8859_15 => Some(ISO_8859_15),
+        Eci::ShiftJis => Some(SHIFT_JIS),
+        Eci::Utf8 => Some(UTF_8),
+    }
+}
@@ -0,0 +1,85 @@
+//! Reading codes from and copying codes to the system clipboard.
+
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use arboard::{Clipboard, ImageData};
+use image::{ImageOutputFormat, RgbaImage};
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{Span, Value};
+
+use crate::decode;
+
+fn open(span: Span) -> Result<Clipboard, LabeledError> {
+    Clipboard::new().map_err(|e| LabeledError {
+        label: "Unable to access the clipboard".into(),
+        msg: e.to_string(),
+        span: Some(span),
+    })
+}
+
+/// Decode the image on the clipboard like `from qr` decodes its input.
+///
+/// Text on the clipboard is decoded if it is an image as a data uri or base64.
+pub fn paste(call: &EvaluatedCall) -> Result<Value, LabeledError> {
+    let mut clipboard = open(call.head)?;
+    let input = match clipboard.get_image() {
+        Ok(image) => {
+            let rgba =
+                RgbaImage::from_raw(image.width as u32, image.height as u32, image.bytes.into())
+                    .ok_or_else(|| LabeledError {
+                        label: "Unable to read the clipboard".into(),
+                        msg: "image data does not match its size".into(),
+                        span: Some(call.head),
+                    })?;
+            let mut png = Vec::new();
+            rgba.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+                .map_err(|e| LabeledError {
+                    label: "Unable to read the clipboard".into(),
+                    msg: e.to_string(),
+                    span: Some(call.head),
+                })?;
+            Value::Binary {
+                val: png,
+                span: call.head,
+            }
+        }
+        Err(e) => match clipboard.get_text() {
+            Ok(text) => Value::String {
+                val: text,
+                span: call.head,
+            },
+            Err(_) => {
+                return Err(LabeledError {
+                    label: "No image on the clipboard".into(),
+                    msg: e.to_string(),
+                    span: Some(call.head),
+                })
+            }
+        },
+    };
+    decode::from_image(call, &input, Some(call.head), decode::Codes::Qr)
+}
+
+/// Put the png `image` of a rendered code on the clipboard.
+pub fn copy(image: Value, span: Span) -> Result<Value, LabeledError> {
+    let rgba = image::load_from_memory(image.as_binary()?)
+        .map_err(|e| LabeledError {
+            label: "Unable to copy the qr code".into(),
+            msg: e.to_string(),
+            span: Some(span),
+        })?
+        .into_rgba8();
+    open(span)?
+        .set_image(ImageData {
+            width: rgba.width() as usize,
+            height: rgba.height() as usize,
+            bytes: Cow::Owned(rgba.into_raw()),
+        })
+        .map_err(|e| LabeledError {
+            label: "Unable to copy the qr code".into(),
+            msg: e.to_string(),
+            span: Some(span),
+        })?;
+    Ok(Value::Nothing { span })
+}
@@ -0,0 +1,112 @@
+//! Raw codeword access, to measure how much error correction a decode needed.
+//!
+//! The grid traversal mirrors quirc's, which keeps these helpers private.
+
+use quircs::{Code, Data, VERSION_DB};
+use reed_solomon::Decoder;
+
+fn grid_bit(code: &Code, x: i32, y: i32) -> bool {
+    let p = (y * code.size + x) as usize;
+    code.cell_bitmap[p >> 3] >> (p & 7) & 1 != 0
+}
+
+fn mask_bit(mask: i32, i: i32, j: i32) -> bool {
+    match mask {
+        0 => (i + j) % 2 == 0,
+        1 => i % 2 == 0,
+        2 => j % 3 == 0,
+        3 => (i + j) % 3 == 0,
+        4 => (i / 2 + j / 3) % 2 == 0,
+        5 => i * j % 2 + i * j % 3 == 0,
+        6 => (i * j % 2 + i * j % 3) % 2 == 0,
+        7 => (i * j % 3 + (i + j) % 2) % 2 == 0,
+        _ => false,
+    }
+}
+
+/// Whether the module at row `i`, column `j` is a function pattern.
+fn reserved_cell(version: usize, i: i32, j: i32) -> bool {
+    let size = version as i32 * 4 + 17;
+    if (i < 9 || i + 8 >= size) && j < 9 || i < 9 && j + 8 >= size || i == 6 || j == 6 {
+        return true;
+    }
+    if version >= 7 && (i < 6 && j + 11 >= size || i + 11 >= size && j < 6) {
+        return true;
+    }
+    let apat = &VERSION_DB[version].apat;
+    let count = apat.iter().take_while(|p| **p != 0).count() as i32;
+    let near = |x: i32| {
+        apat[..count as usize]
+            .iter()
+            .rposition(|p| (p - x).abs() < 3)
+    };
+    match (near(i), near(j)) {
+        (Some(ai), Some(aj)) => {
+            let (ai, aj, last) = (ai as i32, aj as i32, count - 1);
+            ai > 0 && ai < last || aj > 0 && aj < last || ai == last && aj == last
+        }
+        _ => false,
+    }
+}
+
+/// Read the interleaved codewords in placement order.
+fn read_codewords(code: &Code, data: &Data) -> Vec<u8> {
+    let mut raw = vec![];
+    let mut bits = 0;
+    let mut push = |v: bool| {
+        if bits % 8 == 0 {
+            raw.push(0);
+        }
+        if v {
+            *raw.last_mut().unwrap() |= 0x80 >> (bits % 8);
+        }
+        bits += 1;
+    };
+    let (mut x, mut y, mut dir) = (code.size - 1, code.size - 1, -1);
+    while x > 0 {
+        if x == 6 {
+            x -= 1;
+        }
+        for col in [x, x - 1] {
+            if !reserved_cell(data.version, y, col) {
+                push(grid_bit(code, col, y) ^ mask_bit(data.mask, y, col));
+            }
+        }
+        y += dir;
+        if y < 0 || y >= code.size {
+            dir = -dir;
+            x -= 2;
+            y += dir;
+        }
+    }
+    raw
+}
+
+/// Count the corrected codewords of every error correction block of a
+/// decoded code, as `(corrected, correctable)` pairs.
+pub fn block_errors(code: &Code, data: &Data) -> Option<Vec<(usize, usize)>> {
+    let ver = VERSION_DB.get(data.version)?;
+    let sb = &ver.ecc[data.ecc_level as usize];
+    let raw = read_codewords(code, data);
+
+    let lb_count = (ver.data_bytes - sb.bs * sb.ns) / (sb.bs + 1);
+    let bc = lb_count + sb.ns;
+    let ecc_offset = sb.dw * bc + lb_count;
+    let num_ec = (sb.bs - sb.dw) as usize;
+    let decoder = Decoder::new(num_ec);
+
+    let mut blocks = vec![];
+    for i in 0..bc {
+        let dw = if i < sb.ns { sb.dw } else { sb.dw + 1 };
+        let mut block = Vec::with_capacity(dw as usize + num_ec);
+        for j in 0..dw {
+            block.push(*raw.get((j * bc + i) as usize)?);
+        }
+        for j in 0..num_ec as i32 {
+            block.push(*raw.get((ecc_offset + j * bc + i) as usize)?);
+        }
+        let (_, corrected) = decoder.correct_err_count(&block, None).ok()?;
+        blocks.push((corrected, num_ec / 2));
+    }
+    Some(blocks)
+}
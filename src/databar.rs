@@ -0,0 +1,390 @@
+//! GS1 DataBar Omnidirectional and Expanded for `to barcode`, as found on
+//! fresh food that has no room for an EAN.
+//!
+//! The characters of both are made of 4 bars and 4 spaces whose widths
+//! are enumerated as in ISO/IEC 24724 annex B, rxing only reads them.
+
+/// Group separator, the FNC1 that ends a field of variable length.
+const GS: u8 = 0x1d;
+
+/// Widths of the first four elements of the finder patterns of DataBar
+/// Omnidirectional, the fifth being a single module.
+const FINDERS: [[u8; 4]; 9] = [
+    [3, 8, 2, 1],
+    [3, 5, 5, 1],
+    [3, 3, 7, 1],
+    [3, 1, 9, 1],
+    [2, 7, 4, 1],
+    [2, 5, 6, 1],
+    [2, 3, 8, 1],
+    [1, 5, 7, 1],
+    [1, 3, 9, 1],
+];
+
+/// Finder patterns A to F of DataBar Expanded.
+const EXPANDED_FINDERS: [[u8; 4]; 6] = [
+    [1, 8, 4, 1],
+    [3, 6, 4, 1],
+    [3, 4, 6, 1],
+    [3, 2, 8, 1],
+    [2, 6, 5, 1],
+    [2, 2, 9, 1],
+];
+
+/// Finder patterns of DataBar Expanded symbols of 2 up to 11 pairs of
+/// characters, 0 for A.
+const SEQUENCES: [&[usize]; 10] = [
+    &[0, 0],
+    &[0, 1, 1],
+    &[0, 2, 1, 3],
+    &[0, 4, 1, 3, 2],
+    &[0, 4, 1, 3, 3, 5],
+    &[0, 4, 1, 3, 4, 5, 5],
+    &[0, 0, 1, 1, 2, 2, 3, 3],
+    &[0, 0, 1, 1, 2, 2, 3, 4, 4],
+    &[0, 0, 1, 1, 2, 2, 3, 4, 5, 5],
+    &[0, 0, 1, 1, 2, 3, 3, 4, 4, 5, 5],
+];
+
+/// Checksum weights of the elements of the data characters of DataBar
+/// Expanded, by their position next to the finder patterns.
+#[rustfmt::skip]
+const WEIGHTS: [[u32; 8]; 23] = [
+    [1, 3, 9, 27, 81, 32, 96, 77], [20, 60, 180, 118, 143, 7, 21, 63],
+    [189, 145, 13, 39, 117, 140, 209, 205], [193, 157, 49, 147, 19, 57, 171, 91],
+    [62, 186, 136, 197, 169, 85, 44, 132], [185, 133, 188, 142, 4, 12, 36, 108],
+    [113, 128, 173, 97, 80, 29, 87, 50], [150, 28, 84, 41, 123, 158, 52, 156],
+    [46, 138, 203, 187, 139, 206, 196, 166], [76, 17, 51, 153, 37, 111, 122, 155],
+    [43, 129, 176, 106, 107, 110, 119, 146], [16, 48, 144, 10, 30, 90, 59, 177],
+    [109, 116, 137, 200, 178, 112, 125, 164], [70, 210, 208, 202, 184, 130, 179, 115],
+    [134, 191, 151, 31, 93, 68, 204, 190], [148, 22, 66, 198, 172, 94, 71, 2],
+    [6, 18, 54, 162, 64, 192, 154, 40], [120, 149, 25, 75, 14, 42, 126, 167],
+    [79, 26, 78, 23, 69, 207, 199, 175], [103, 98, 83, 38, 114, 131, 182, 124],
+    [161, 61, 183, 127, 170, 88, 53, 159], [55, 165, 73, 8, 24, 72, 5, 15],
+    [45, 135, 194, 160, 58, 174, 100, 89],
+];
+
+/// Characters of ISO/IEC 646 that take 8 bits, from 232 on.
+const ISO_646: &[u8] = b"!\"%&'()*+,-./:;<=>?_ ";
+
+/// Number of ways to choose `r` of `n`.
+fn combinations(n: i64, r: i64) -> i64 {
+    if r < 0 || r > n {
+        return 0;
+    }
+    (0..r).fold(1, |c, i| c * (n - i) / (i + 1))
+}
+
+/// Widths of the 4 odd or even elements of a character, `n` modules wide
+/// with none wider than `widest`, by their `value` in the order of annex B.
+/// With `narrow`, those without a single module element are left out.
+fn widths(mut value: i64, mut n: i64, widest: i64, narrow: bool) -> [u8; 4] {
+    let mut widths = [0; 4];
+    let mut narrow_mask = 0;
+    for (bar, width) in widths.iter_mut().take(3).enumerate() {
+        let rest = 3 - bar as i64;
+        let mut w = 1;
+        narrow_mask |= 1 << bar;
+        loop {
+            let mut count = combinations(n - w - 1, rest - 1);
+            if narrow && narrow_mask == 0 && n - w - rest >= rest {
+                count -= combinations(n - w - rest - 1, rest - 1);
+            }
+            if rest > 1 {
+                let too_wide: i64 = (widest + 1..=n - w - rest + 1)
+                    .map(|m| combinations(n - w - m - 1, rest - 2))
+                    .sum();
+                count -= too_wide * rest;
+            } else if n - w > widest {
+                count -= 1;
+            }
+            if value < count {
+                break;
+            }
+            value -= count;
+            w += 1;
+            narrow_mask &= !(1 << bar);
+        }
+        n -= w;
+        *width = w as u8;
+    }
+    widths[3] = n as u8;
+    widths
+}
+
+/// Interleave odd and even elements into the 8 of a character.
+fn interleave(odd: [u8; 4], even: [u8; 4]) -> [u8; 8] {
+    [
+        odd[0], even[0], odd[1], even[1], odd[2], even[2], odd[3], even[3],
+    ]
+}
+
+/// Elements of an outside (16 modules) or inside (15 modules) character of
+/// DataBar Omnidirectional.
+fn character(value: u32, outside: bool) -> [u8; 8] {
+    if outside {
+        const GSUM: [u32; 5] = [0, 161, 961, 2015, 2715];
+        const EVEN_TOTAL: [u32; 5] = [1, 10, 34, 70, 126];
+        const ODD_WIDEST: [u8; 5] = [8, 6, 4, 3, 1];
+        let group = GSUM.iter().rposition(|&g| value >= g).unwrap_or(0);
+        let v = value - GSUM[group];
+        let odd_modules = 12 - 2 * group as i64;
+        let widest = ODD_WIDEST[group] as i64;
+        interleave(
+            widths((v / EVEN_TOTAL[group]) as i64, odd_modules, widest, false),
+            widths(
+                (v % EVEN_TOTAL[group]) as i64,
+                16 - odd_modules,
+                9 - widest,
+                true,
+            ),
+        )
+    } else {
+        const GSUM: [u32; 4] = [0, 336, 1036, 1516];
+        const ODD_TOTAL: [u32; 4] = [4, 20, 48, 81];
+        const ODD_WIDEST: [u8; 4] = [2, 4, 6, 8];
+        let group = GSUM.iter().rposition(|&g| value >= g).unwrap_or(0);
+        let v = value - GSUM[group];
+        let even_modules = 10 - 2 * group as i64;
+        let widest = ODD_WIDEST[group] as i64;
+        interleave(
+            widths(
+                (v % ODD_TOTAL[group]) as i64,
+                15 - even_modules,
+                widest,
+                true,
+            ),
+            widths(
+                (v / ODD_TOTAL[group]) as i64,
+                even_modules,
+                9 - widest,
+                false,
+            ),
+        )
+    }
+}
+
+/// Elements of a character of DataBar Expanded, 17 modules wide.
+fn expanded_character(value: u32) -> [u8; 8] {
+    const GSUM: [u32; 5] = [0, 348, 1388, 2948, 3988];
+    const EVEN_TOTAL: [u32; 5] = [4, 20, 52, 104, 204];
+    const ODD_WIDEST: [u8; 5] = [7, 5, 4, 3, 1];
+    let group = GSUM.iter().rposition(|&g| value >= g).unwrap_or(0);
+    let v = value - GSUM[group];
+    let odd_modules = 12 - 2 * group as i64;
+    let widest = ODD_WIDEST[group] as i64;
+    interleave(
+        widths((v / EVEN_TOTAL[group]) as i64, odd_modules, widest, true),
+        widths(
+            (v % EVEN_TOTAL[group]) as i64,
+            17 - odd_modules,
+            9 - widest,
+            false,
+        ),
+    )
+}
+
+/// Modules of the elements, which start with a space.
+fn modules(elements: &[u8]) -> Vec<bool> {
+    elements
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &w)| std::iter::repeat_n(i % 2 == 1, w as usize))
+        .collect()
+}
+
+/// Finder pattern, reversed for the second one of a pair of finders.
+fn finder(pattern: [u8; 4], reversed: bool) -> Vec<u8> {
+    let mut elements = pattern.to_vec();
+    elements.push(1);
+    if reversed {
+        elements.reverse();
+    }
+    elements
+}
+
+/// Modules of a DataBar Omnidirectional symbol of the first 13 digits of a
+/// GTIN, `true` for a bar.
+///
+/// Its characters are read from the outside of the symbol in, so those to
+/// the right of a finder pattern are reversed.
+pub fn omnidirectional(digits: &str) -> Vec<bool> {
+    let value: u64 = digits.parse().unwrap_or_default();
+    let (left, right) = ((value / 4537077) as u32, (value % 4537077) as u32);
+    let values = [left / 1597, left % 1597, right / 1597, right % 1597];
+    let chars = [0, 1, 2, 3].map(|i| character(values[i], i % 2 == 0));
+    // Weights are powers of 9 across the elements, times 3 for the even
+    // ones, and of 4 across the characters.
+    let checksum = chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let weight = |j: usize| 9u32.pow(j as u32 / 2) * [1, 3][j % 2];
+            4u32.pow(i as u32) * (0..8).map(|j| c[j] as u32 * weight(j)).sum::<u32>()
+        })
+        .sum::<u32>()
+        % 79;
+    // Two of the 81 combinations of finder patterns are not used.
+    let mut check = checksum;
+    if check >= 8 {
+        check += 1;
+    }
+    if check >= 72 {
+        check += 1;
+    }
+    let reversed = |c: [u8; 8]| c.into_iter().rev().collect::<Vec<_>>();
+    let mut elements = vec![1, 1];
+    elements.extend(chars[0]);
+    elements.extend(finder(FINDERS[check as usize / 9], false));
+    elements.extend(reversed(chars[1]));
+    elements.extend(chars[3]);
+    elements.extend(finder(FINDERS[check as usize % 9], true));
+    elements.extend(reversed(chars[2]));
+    elements.extend([1, 1]);
+    modules(&elements)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Numeric,
+    Alphanumeric,
+    Iso646,
+}
+
+/// Append the `n` lowest bits of `value`.
+fn push(bits: &mut Vec<bool>, value: u32, n: u32) {
+    bits.extend((0..n).rev().map(|i| value >> i & 1 == 1));
+}
+
+/// Encode an element string in the general purpose data field, switching
+/// between pairs of digits, alphanumerics and ISO/IEC 646 as needed.
+///
+/// Returns the bits and the mode they end in.
+fn general_purpose(data: &[u8]) -> Result<(Vec<bool>, Mode), String> {
+    let numeric = |i: usize| data.get(i).is_some_and(|b| b.is_ascii_digit() || *b == GS);
+    // FNC1 counts as a tenth digit, but two of them do not make a pair.
+    let pair = |i: usize| numeric(i) && numeric(i + 1) && !(data[i] == GS && data[i + 1] == GS);
+    let digit = |b: u8| if b == GS { 10 } else { (b - b'0') as u32 };
+    let mut bits = vec![];
+    let mut mode = Mode::Numeric;
+    let mut i = 0;
+    while i < data.len() {
+        let b = data[i];
+        match mode {
+            Mode::Numeric if pair(i) => {
+                push(&mut bits, 11 * digit(b) + digit(data[i + 1]) + 8, 7);
+                i += 2;
+                continue;
+            }
+            Mode::Numeric => {
+                push(&mut bits, 0, 4);
+                mode = Mode::Alphanumeric;
+                continue;
+            }
+            // FNC1 is always followed by the digits of an AI, and is read
+            // the same by all decoders as part of a pair.
+            _ if pair(i) && (b == GS || pair(i + 2)) => {
+                push(&mut bits, 0, 3);
+                mode = Mode::Numeric;
+                continue;
+            }
+            _ => {}
+        }
+        match (mode, b) {
+            (_, b'0'..=b'9') => push(&mut bits, (b - b'0') as u32 + 5, 5),
+            (_, GS) => push(&mut bits, 15, 5),
+            (Mode::Alphanumeric, b'A'..=b'Z') => push(&mut bits, b as u32 - 33, 6),
+            (Mode::Alphanumeric, b'*') => push(&mut bits, 58, 6),
+            (Mode::Alphanumeric, b',' | b'-' | b'.' | b'/') => {
+                push(&mut bits, (b - b',') as u32 + 59, 6)
+            }
+            (Mode::Alphanumeric, _) => {
+                push(&mut bits, 0b00100, 5);
+                mode = Mode::Iso646;
+                continue;
+            }
+            (_, b'A'..=b'Z') => push(&mut bits, b as u32 - 1, 7),
+            (_, b'a'..=b'z') => push(&mut bits, b as u32 - 7, 7),
+            (_, b) => match ISO_646.iter().position(|c| *c == b) {
+                Some(n) => push(&mut bits, 232 + n as u32, 8),
+                None => {
+                    let c = String::from_utf8_lossy(&data[i..]).chars().next();
+                    return Err(format!(
+                        "{:?} cannot be encoded in a GS1 DataBar",
+                        c.unwrap_or_default()
+                    ));
+                }
+            },
+        }
+        i += 1;
+    }
+    Ok((bits, mode))
+}
+
+/// Modules of a single row DataBar Expanded symbol of a GS1 element string,
+/// `true` for a bar.
+///
+/// Its data characters are 12 bits of the general purpose encoding each,
+/// read from the outside of a pair of characters in like those of DataBar
+/// Omnidirectional, the first pair starting with the check character.
+pub fn expanded(element_string: &str) -> Result<Vec<bool>, String> {
+    let (data, mode) = general_purpose(element_string.as_bytes())?;
+    // A linkage flag, the encoding method and the variable length field
+    // come first, and there are at least 3 data characters.
+    let n = (5 + data.len()).div_ceil(12).max(3);
+    if n > 21 {
+        return Err(format!(
+            "needs {} data characters, a GS1 DataBar Expanded has at most 21",
+            n
+        ));
+    }
+    let symbol_chars = n + 1;
+    let mut bits = vec![
+        false,
+        false,
+        false,
+        symbol_chars % 2 == 1,
+        symbol_chars > 14,
+    ];
+    bits.extend(data);
+    // Pad with the latch from numeric to alphanumeric, then with latches
+    // between alphanumeric and ISO/IEC 646.
+    let padding = match mode {
+        Mode::Numeric => "0000",
+        _ => "",
+    };
+    let padding = padding.chars().chain("00100".chars().cycle());
+    let missing = 12 * n - bits.len();
+    bits.extend(padding.take(missing).map(|c| c == '1'));
+    let values: Vec<u32> = bits
+        .chunks(12)
+        .map(|chunk| chunk.iter().fold(0, |v, &b| v << 1 | b as u32))
+        .collect();
+    let pairs = symbol_chars.div_ceil(2);
+    let sequence = SEQUENCES[pairs - 2];
+    let chars: Vec<[u8; 8]> = values.iter().map(|&v| expanded_character(v)).collect();
+    let checksum: u32 = chars
+        .iter()
+        .enumerate()
+        .map(|(k, c)| {
+            // The first data character is on the right of the first pair.
+            let (pair, right) = (k.div_ceil(2), (k + 1) % 2);
+            let row = 4 * sequence[pair] + 2 * (pair % 2) + right - 1;
+            (0..8).map(|j| c[j] as u32 * WEIGHTS[row][j]).sum::<u32>()
+        })
+        .sum();
+    let check = 211 * (symbol_chars as u32 - 4) + checksum % 211;
+    let chars: Vec<_> = std::iter::once(expanded_character(check))
+        .chain(chars)
+        .collect();
+    let mut elements = vec![1, 1];
+    for (pair, &pattern) in sequence.iter().enumerate() {
+        elements.extend(chars[2 * pair]);
+        elements.extend(finder(EXPANDED_FINDERS[pattern], pair % 2 == 1));
+        if let Some(right) = chars.get(2 * pair + 1) {
+            elements.extend(right.iter().rev());
+        }
+    }
+    elements.extend([1, 1]);
+    Ok(modules(&elements))
+}
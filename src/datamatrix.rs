@@ -0,0 +1,73 @@
+//! Data matrix codes, as found on parcels and electronic parts, read with
+//! rxing.
+
+use image::GrayImage;
+use quircs::{Data, Point};
+use rxing::common::{DetectorRXingResult, HybridBinarizer};
+use rxing::datamatrix::decoder::Decoder;
+use rxing::datamatrix::detector::zxing_cpp_detector;
+use rxing::{BinaryBitmap, Luma8LuminanceSource};
+
+use crate::aztec;
+use crate::micro::Symbol;
+
+/// Find and decode all data matrix codes of a grayscale image.
+///
+/// Edges are traced along lines across the whole image, so a code may be
+/// found once for every line crossing it. Its corners are those of the
+/// image, clockwise from the top left, as for aztec codes.
+pub fn datamatrices(image: &GrayImage) -> Vec<Symbol> {
+    let Ok(source) = Luma8LuminanceSource::new(image.to_vec(), image.width(), image.height())
+    else {
+        return vec![];
+    };
+    let bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    let Ok(detected) = zxing_cpp_detector::detect(bitmap.get_black_matrix(), true, true) else {
+        return vec![];
+    };
+    let decoder = Decoder::new();
+    let mut v: Vec<Symbol> = Vec::new();
+    for symbol in detected {
+        let Ok(result) = decoder.decode(symbol.getBits()) else {
+            continue;
+        };
+        let points = symbol.getPoints();
+        if points.len() < 4 {
+            continue;
+        }
+        let mut corners = [0, 1, 2, 3].map(|i| Point {
+            x: points[i].x.round() as i32,
+            y: points[i].y.round() as i32,
+        });
+        let Some(top_left) = (0..4).min_by_key(|&i| corners[i].x + corners[i].y) else {
+            continue;
+        };
+        corners.rotate_left(top_left);
+        let [a, _, c, _] = corners;
+        let center = Point {
+            x: (a.x + c.x) / 2,
+            y: (a.y + c.y) / 2,
+        };
+        // The payload of a GS1 code starts with its symbology identifier, as
+        // for qr codes.
+        let mut payload = match result.getSymbologyModifier() {
+            2 => b"]d2".to_vec(),
+            _ => vec![],
+        };
+        payload.extend(result.getText().as_bytes());
+        if v.iter()
+            .any(|(other, _, d)| d.payload == payload && aztec::contains(other, center))
+        {
+            continue;
+        }
+        v.push((
+            corners,
+            (symbol.getBits().width(), symbol.getBits().height()),
+            Data {
+                payload,
+                ..Default::default()
+            },
+        ));
+    }
+    v
+}
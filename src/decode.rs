@@ -0,0 +1,1717 @@
+//! Detection and decoding of qr codes and barcodes in images.
+
+use image::{imageops, GrayImage};
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{FromValue, Span, Spanned, Value};
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::append::{self, Header, Segment};
+use crate::aztec;
+use crate::backend::Backend;
+use crate::charset::Charset;
+use crate::codewords::block_errors;
+use crate::datamatrix;
+use crate::linear;
+use crate::load;
+use crate::micro;
+use crate::payloads;
+use crate::pdf417;
+use crate::preprocess::{self, Binarization, Preprocess};
+use quircs::{Code, Data, DecodeError, Point};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+
+/// Transpose the grid of `code`, which turns a mirrored symbol into a readable one.
+fn flip(code: &Code) -> Code {
+    let mut flipped = *code;
+    flipped.cell_bitmap = [0; 3917];
+    let size = code.size as usize;
+    for y in 0..size {
+        for x in 0..size {
+            let from = y * size + x;
+            if code.cell_bitmap[from >> 3] & (1 << (from & 7)) != 0 {
+                let to = x * size + y;
+                flipped.cell_bitmap[to >> 3] |= 1 << (to & 7);
+            }
+        }
+    }
+    flipped
+}
+
+/// Decode an identified code, retrying with the mirrored grid as quirc recommends.
+///
+/// Returns the grid that was successfully decoded along with the data.
+pub(crate) fn decode(code: &Code) -> Result<(Data, Code), DecodeError> {
+    match code.decode() {
+        Ok(data) => Ok((data, *code)),
+        Err(e) => {
+            let flipped = flip(code);
+            flipped.decode().map(|data| (data, flipped)).map_err(|_| e)
+        }
+    }
+}
+
+/// Convert decoded payloads to a string (or binary, if any is not valid text).
+///
+/// Multiple payloads are returned as a list, unless `join` asks for them
+/// concatenated into a single value.
+fn payloads_to_value(payloads: Vec<Data>, join: bool, charset: &Charset, span: Span) -> Value {
+    let strings = payloads
+        .iter()
+        .map(|p| charset.decode(p))
+        .collect::<Option<Vec<String>>>();
+    match (strings, join || payloads.len() == 1) {
+        (Some(strings), true) => charset.text(strings.join("\n"), span),
+        (None, true) => {
+            charset.binary(payloads.into_iter().flat_map(|p| p.payload).collect(), span)
+        }
+        (Some(strings), false) => Value::List {
+            vals: strings
+                .into_iter()
+                .map(|val| charset.text(val, span))
+                .collect(),
+            span,
+        },
+        (None, false) => Value::List {
+            vals: payloads
+                .into_iter()
+                .map(|p| charset.binary(p.payload, span))
+                .collect(),
+            span,
+        },
+    }
+}
+
+/// A decoded symbol and where it was found.
+struct Detection {
+    /// Page or frame the symbol is on, for inputs that have several.
+    position: Option<(&'static str, usize)>,
+    /// `qr`, `mqr`, `rmqr`, `aztec`, `datamatrix`, `pdf417`, or the name of
+    /// a barcode as `to barcode` calls it.
+    symbology: &'static str,
+    /// Name of the backend that found the symbol.
+    engine: &'static str,
+    corners: [Point; 4],
+    /// Width and height of the symbol in modules, which is not known for
+    /// barcodes.
+    modules: Option<(u32, u32)>,
+    /// Corrected and correctable codewords of every error correction block.
+    blocks: Option<Vec<(usize, usize)>>,
+    /// Structured append header of a qr code that is part of a sequence.
+    sequence: Option<Header>,
+    /// Macro PDF417 segment of a pdf417 code that is part of a file.
+    segment: Option<Segment>,
+    /// How often the payload was found on the page, with `--unique`.
+    count: Option<usize>,
+    data: Data,
+}
+
+impl Detection {
+    fn into_record(self, charset: &Charset, checksum: Option<Checksum>, span: Span) -> Value {
+        let (x0, y0, x1, y1) = self.bounds();
+        let quality = self.quality();
+        let eci = match self.data.eci {
+            Some(eci) => Value::String {
+                val: format!("{:?}", eci),
+                span,
+            },
+            None => Value::Nothing { span },
+        };
+        let payload = charset.to_value(&self.data, span);
+        let kind = payloads::kind(charset.decode(&self.data).as_deref());
+        let corners = self
+            .corners
+            .iter()
+            .map(|p| Value::Record {
+                cols: vec!["x".into(), "y".into()],
+                vals: vec![
+                    Value::Int {
+                        val: p.x as i64,
+                        span,
+                    },
+                    Value::Int {
+                        val: p.y as i64,
+                        span,
+                    },
+                ],
+                span,
+            })
+            .collect();
+        let int = |val: i32| Value::Int {
+            val: val as i64,
+            span,
+        };
+        let bbox = Value::Record {
+            cols: vec!["x".into(), "y".into(), "width".into(), "height".into()],
+            vals: vec![int(x0), int(y0), int(x1 - x0), int(y1 - y0)],
+            span,
+        };
+        let mut record = Value::Record {
+            cols: vec![
+                "payload".into(),
+                "type".into(),
+                "symbology".into(),
+                "engine".into(),
+                "version".into(),
+                "ecc_level".into(),
+                "mask".into(),
+                "data_type".into(),
+                "eci".into(),
+                "sequence".into(),
+                "corners".into(),
+                "bbox".into(),
+                "rotation".into(),
+                "module_size".into(),
+                "corrections".into(),
+                "quality".into(),
+            ],
+            vals: vec![
+                payload,
+                Value::String {
+                    val: kind.into(),
+                    span,
+                },
+                Value::String {
+                    val: self.symbology.into(),
+                    span,
+                },
+                Value::String {
+                    val: self.engine.into(),
+                    span,
+                },
+                // Barcodes have neither versions nor error correction levels,
+                // the version of an aztec code is its number of layers.
+                match self.is_qr() || self.symbology == "aztec" {
+                    true => int(self.data.version as i32),
+                    false => Value::Nothing { span },
+                },
+                match self.is_qr() {
+                    true => Value::String {
+                        val: format!("{:?}", self.data.ecc_level),
+                        span,
+                    },
+                    false => Value::Nothing { span },
+                },
+                // Only qr codes report their mask pattern.
+                match self.symbology {
+                    "qr" => int(self.data.mask),
+                    _ => Value::Nothing { span },
+                },
+                match self.data.data_type {
+                    Some(t) => Value::String {
+                        val: t.to_string(),
+                        span,
+                    },
+                    None => Value::Nothing { span },
+                },
+                eci,
+                match (self.sequence, &self.segment) {
+                    (Some(header), _) => Value::Record {
+                        cols: vec!["index".into(), "count".into(), "parity".into()],
+                        vals: vec![
+                            match header.index {
+                                Some(index) => int(index as i32),
+                                None => Value::Nothing { span },
+                            },
+                            int(header.count as i32),
+                            int(header.parity as i32),
+                        ],
+                        span,
+                    },
+                    (None, Some(segment)) => Value::Record {
+                        cols: vec!["index".into(), "count".into(), "file_id".into()],
+                        vals: vec![
+                            match segment.index {
+                                Some(index) => int(index as i32),
+                                None => Value::Nothing { span },
+                            },
+                            match segment.count {
+                                Some(count) => int(count as i32),
+                                None => Value::Nothing { span },
+                            },
+                            Value::String {
+                                val: segment.file_id.clone(),
+                                span,
+                            },
+                        ],
+                        span,
+                    },
+                    (None, None) => Value::Nothing { span },
+                },
+                Value::List {
+                    vals: corners,
+                    span,
+                },
+                bbox,
+                Value::Float {
+                    val: self.rotation(),
+                    span,
+                },
+                match self.module_size() {
+                    Some(val) => Value::Float { val, span },
+                    None => Value::Nothing { span },
+                },
+                match &self.blocks {
+                    Some(blocks) => int(blocks.iter().map(|(c, _)| *c).sum::<usize>() as i32),
+                    None => Value::Nothing { span },
+                },
+                match quality {
+                    Some(val) => Value::Float { val, span },
+                    None => Value::Nothing { span },
+                },
+            ],
+            span,
+        };
+        if let Some(checksum) = checksum {
+            checksum.insert(&mut record, 1, &self.data.payload, span);
+        }
+        if let Value::Record { cols, vals, .. } = &mut record {
+            if let Some((unit, n)) = self.position {
+                cols.insert(0, unit.into());
+                vals.insert(0, int(n as i32));
+            }
+            if let Some(count) = self.count {
+                cols.push("count".into());
+                vals.push(int(count as i32));
+            }
+        }
+        record
+    }
+
+    /// Whether the symbol is one of the qr codes, rather than an aztec code
+    /// or a barcode.
+    fn is_qr(&self) -> bool {
+        matches!(self.symbology, "qr" | "mqr" | "rmqr")
+    }
+
+    /// Share of the error correction capacity left in the most damaged block,
+    /// from 1.0 (nothing corrected) down to 0.0 (at the limit).
+    fn quality(&self) -> Option<f64> {
+        self.blocks
+            .as_ref()?
+            .iter()
+            .try_fold(1.0f64, |q, (c, max)| {
+                (*max > 0).then(|| q.min(1.0 - *c as f64 / *max as f64))
+            })
+    }
+
+    /// Clockwise angle in degrees from upright, of the top edge of the symbol.
+    fn rotation(&self) -> f64 {
+        let [top_left, top_right, ..] = self.corners;
+        let degrees = ((top_right.y - top_left.y) as f64)
+            .atan2((top_right.x - top_left.x) as f64)
+            .to_degrees();
+        ((degrees * 10.0).round() / 10.0).rem_euclid(360.0)
+    }
+
+    /// Average size of a module in pixels, along both axes of the symbol.
+    fn module_size(&self) -> Option<f64> {
+        let (columns, rows) = self.modules?;
+        let length = |a: Point, b: Point| ((a.x - b.x) as f64).hypot((a.y - b.y) as f64);
+        let [a, b, c, d] = self.corners;
+        let width = (length(a, b) + length(d, c)) / 2.0 / columns.max(1) as f64;
+        let height = (length(a, d) + length(b, c)) / 2.0 / rows.max(1) as f64;
+        Some(((width + height) / 2.0 * 100.0).round() / 100.0)
+    }
+
+    /// Axis aligned bounds of the corners as `(left, top, right, bottom)`.
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        let xs = self.corners.iter().map(|p| p.x);
+        let ys = self.corners.iter().map(|p| p.y);
+        (
+            xs.clone().min().unwrap_or_default(),
+            ys.clone().min().unwrap_or_default(),
+            xs.max().unwrap_or_default(),
+            ys.max().unwrap_or_default(),
+        )
+    }
+
+    /// Whether the bounding boxes of two codes overlap.
+    fn overlaps(&self, other: &Detection) -> bool {
+        let (left, top, right, bottom) = self.bounds();
+        let (other_left, other_top, other_right, other_bottom) = other.bounds();
+        left <= other_right && other_left <= right && top <= other_bottom && other_top <= bottom
+    }
+}
+
+/// Order in which the codes of a page are returned, from `--sort-by`.
+#[derive(Clone, Copy)]
+enum SortBy {
+    /// Reading order, rows from top to bottom and left to right in a row.
+    Position,
+    Payload,
+}
+
+impl SortBy {
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        match call.get_flag::<Spanned<String>>("sort-by")? {
+            None => Ok(SortBy::Position),
+            Some(order) => match order.item.as_str() {
+                "position" => Ok(SortBy::Position),
+                "payload" => Ok(SortBy::Payload),
+                _ => Err(LabeledError {
+                    label: "Unknown sort order".into(),
+                    msg: format!("{} is not one of: position, payload", order.item),
+                    span: Some(order.span),
+                }),
+            },
+        }
+    }
+
+    /// Sort the codes of every page, keeping the pages in order.
+    fn sort(&self, detections: &mut [Detection]) {
+        match self {
+            SortBy::Payload => detections
+                .sort_by(|a, b| (a.position, &a.data.payload).cmp(&(b.position, &b.data.payload))),
+            SortBy::Position => {
+                detections.sort_by_key(|d| (d.position, d.bounds().1));
+                // Codes that overlap the first code of a row by more than
+                // half the height of the smaller one are on that row too,
+                // even if they are larger or printed a little lower.
+                let mut start = 0;
+                while start < detections.len() {
+                    let (_, top, _, bottom) = detections[start].bounds();
+                    let end = (start + 1..detections.len())
+                        .find(|&i| {
+                            let (_, y0, _, y1) = detections[i].bounds();
+                            let overlap = bottom.min(y1) - top.max(y0);
+                            detections[i].position != detections[start].position
+                                || overlap * 2 <= (bottom - top).min(y1 - y0)
+                        })
+                        .unwrap_or(detections.len());
+                    detections[start..end].sort_by_key(|d| d.bounds().0);
+                    start = end;
+                }
+            }
+        }
+    }
+}
+
+/// Checksum of every payload added to tables, from `--checksum`.
+#[derive(Clone, Copy)]
+enum Checksum {
+    Sha256,
+    Crc32,
+}
+
+impl Checksum {
+    fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let Some(name) = call.get_flag::<Spanned<String>>("checksum")? else {
+            return Ok(None);
+        };
+        match name.item.as_str() {
+            "sha256" => Ok(Some(Checksum::Sha256)),
+            "crc32" => Ok(Some(Checksum::Crc32)),
+            _ => Err(LabeledError {
+                label: "Unknown checksum".into(),
+                msg: format!("{} is not one of: sha256, crc32", name.item),
+                span: Some(name.span),
+            }),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Checksum::Sha256 => "sha256",
+            Checksum::Crc32 => "crc32",
+        }
+    }
+
+    /// Checksum of the payload bytes in lowercase hex, as `sha256sum` and
+    /// `crc32` print it.
+    fn of(&self, payload: &[u8]) -> String {
+        match self {
+            Checksum::Sha256 => Sha256::digest(payload)
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+            Checksum::Crc32 => format!("{:08x}", crc32fast::hash(payload)),
+        }
+    }
+
+    /// Insert the checksum column of `payload` into `record` at `index`.
+    fn insert(&self, record: &mut Value, index: usize, payload: &[u8], span: Span) {
+        if let Value::Record { cols, vals, .. } = record {
+            cols.insert(index, self.name().into());
+            vals.insert(
+                index,
+                Value::String {
+                    val: self.of(payload),
+                    span,
+                },
+            );
+        }
+    }
+}
+
+/// Keep the first of the codes with the same payload on the same page,
+/// counting how often each was found.
+fn unique(detections: Vec<Detection>) -> Vec<Detection> {
+    let mut kept: Vec<Detection> = Vec::new();
+    for mut d in detections {
+        match kept
+            .iter_mut()
+            .find(|k| k.position == d.position && k.data.payload == d.data.payload)
+        {
+            Some(k) => *k.count.get_or_insert(1) += 1,
+            None => {
+                d.count = Some(1);
+                kept.push(d);
+            }
+        }
+    }
+    kept
+}
+
+/// Join the parts of every complete structured append sequence (and the
+/// segments of every complete Macro PDF417 file) into the first of them, the
+/// others are taken out.
+///
+/// Parts may come from several images, so `detections` spans all of them.
+fn reassemble(
+    detections: &mut [Option<Detection>],
+    ignore_error: bool,
+    span: Option<Span>,
+) -> Result<(), LabeledError> {
+    let headers: Vec<_> = detections
+        .iter()
+        .map(|d| d.as_ref().and_then(|d| d.sequence))
+        .collect();
+    for parts in append::sequences(&headers) {
+        let Some(header) = headers[parts[0]] else {
+            continue;
+        };
+        let message: Vec<u8> = parts
+            .iter()
+            .filter_map(|&p| detections[p].as_ref())
+            .flat_map(|d| d.data.payload.iter().copied())
+            .collect();
+        let parity = append::parity(&message);
+        if parity != header.parity {
+            let msg = format!(
+                "the {} codes of the sequence have parity {:#04x}, expected {:#04x}",
+                header.count, parity, header.parity
+            );
+            if !ignore_error {
+                return Err(LabeledError {
+                    label: "Structured append parity mismatch".into(),
+                    msg,
+                    span,
+                });
+            }
+            eprintln!("Ignore error while reassembling: {}", msg);
+            continue;
+        }
+        for &p in &parts[1..] {
+            detections[p] = None;
+        }
+        if let Some(first) = &mut detections[parts[0]] {
+            first.data.payload = message;
+            first.sequence = Some(Header {
+                index: None,
+                ..header
+            });
+        }
+    }
+    let segments: Vec<_> = detections
+        .iter()
+        .map(|d| d.as_ref().and_then(|d| d.segment.as_ref()))
+        .collect();
+    for parts in append::files(&segments) {
+        let file: Vec<u8> = parts
+            .iter()
+            .filter_map(|&p| detections[p].as_ref())
+            .flat_map(|d| d.data.payload.iter().copied())
+            .collect();
+        for &p in &parts[1..] {
+            detections[p] = None;
+        }
+        if let Some(first) = &mut detections[parts[0]] {
+            first.data.payload = file;
+            if let Some(segment) = &mut first.segment {
+                segment.index = None;
+                segment.count = Some(parts.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Side lengths in pixels of the codes that are kept, from `--min-size` and
+/// `--max-size`.
+#[derive(Clone, Copy)]
+struct Sizes {
+    min: u32,
+    max: u32,
+}
+
+impl Sizes {
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let flag = |name: &str| -> Result<Option<u32>, LabeledError> {
+            match call.get_flag::<i64>(name)? {
+                Some(pixels) if pixels < 0 || pixels > u32::MAX as i64 => Err(LabeledError {
+                    label: "Invalid size".into(),
+                    msg: "should be a positive number of pixels".into(),
+                    span: call.get_flag_value(name).and_then(|v| v.span().ok()),
+                }),
+                pixels => Ok(pixels.map(|p| p as u32)),
+            }
+        };
+        let sizes = Sizes {
+            min: flag("min-size")?.unwrap_or(0),
+            max: flag("max-size")?.unwrap_or(u32::MAX),
+        };
+        if sizes.min > sizes.max {
+            return Err(LabeledError {
+                label: "Invalid size".into(),
+                msg: "--min-size is larger than --max-size".into(),
+                span: call.get_flag_value("min-size").and_then(|v| v.span().ok()),
+            });
+        }
+        Ok(sizes)
+    }
+
+    /// Whether a code with `corners` is neither too small nor too large.
+    fn contains(&self, corners: &[Point; 4]) -> bool {
+        let extent = |axis: fn(&Point) -> i32| {
+            let values = corners.iter().map(axis);
+            values.clone().max().unwrap_or_default() - values.min().unwrap_or_default()
+        };
+        let longest = extent(|p| p.x).max(extent(|p| p.y)) as u32;
+        (self.min..=self.max).contains(&longest)
+    }
+}
+
+/// Kinds of codes a command looks for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Codes {
+    /// Qr, micro qr, rectangular micro qr, aztec, data matrix and pdf417
+    /// codes, for `from qr`.
+    Qr,
+    /// One dimensional barcodes, for `from barcode`.
+    Barcodes,
+    /// Both of them, for `from code`.
+    All,
+}
+
+/// Names of the two dimensional symbologies, as `Detection` calls them.
+const MATRIX_CODES: [&str; 6] = ["qr", "mqr", "rmqr", "aztec", "datamatrix", "pdf417"];
+
+impl Codes {
+    /// Names of all symbologies of these codes.
+    fn symbologies(&self) -> Vec<&'static str> {
+        let barcodes = linear::SYMBOLOGIES.map(|(_, name)| name);
+        match self {
+            Codes::Qr => MATRIX_CODES.to_vec(),
+            Codes::Barcodes => barcodes.to_vec(),
+            Codes::All => MATRIX_CODES.into_iter().chain(barcodes).collect(),
+        }
+    }
+
+    /// Symbologies to look for, those listed by `--only` or all of them.
+    fn only_from_call(&self, call: &EvaluatedCall) -> Result<Vec<&'static str>, LabeledError> {
+        let all = self.symbologies();
+        let Some(value) = call.get_flag_value("only") else {
+            return Ok(all);
+        };
+        let span = value.span().unwrap_or(call.head);
+        let names = value.as_list().map_err(|_| LabeledError {
+            label: "Invalid symbologies".into(),
+            msg: "should be a list of symbology names".into(),
+            span: Some(span),
+        })?;
+        if names.is_empty() {
+            return Err(LabeledError {
+                label: "Invalid symbologies".into(),
+                msg: "should list at least one symbology".into(),
+                span: Some(span),
+            });
+        }
+        let mut only = Vec::new();
+        for name in names {
+            let name = Spanned::<String>::from_value(name)?;
+            let normalized = name.item.to_lowercase().replace(['-', ' '], "");
+            match all.iter().find(|s| **s == normalized) {
+                Some(s) if only.contains(s) => {}
+                Some(s) => only.push(*s),
+                None => {
+                    return Err(LabeledError {
+                        label: "Unknown symbology".into(),
+                        msg: format!("{} is not one of: {}", name.item, all.join(", ")),
+                        span: Some(name.span),
+                    })
+                }
+            }
+        }
+        Ok(only)
+    }
+}
+
+/// Find and decode all barcodes of a grayscale image whose symbology is one
+/// of `symbologies`.
+fn detect_barcodes(image: &GrayImage, symbologies: &[&str]) -> Vec<Detection> {
+    linear::barcodes(image, symbologies)
+        .into_iter()
+        .map(|(symbology, corners, data)| Detection {
+            position: None,
+            symbology,
+            engine: Backend::Rxing.name(),
+            corners,
+            modules: None,
+            blocks: None,
+            sequence: None,
+            segment: None,
+            count: None,
+            data,
+        })
+        .collect()
+}
+
+/// Find and decode the qr codes of a grayscale image with `backend`, if they
+/// are among `symbologies`.
+///
+/// Codes that can not be decoded are skipped instead of failing if `sizes`
+/// would drop them anyway, measured in pixels of `image`.
+fn detect(
+    image: &GrayImage,
+    backend: &Backend,
+    symbologies: &[&str],
+    ignore_error: bool,
+    sizes: Sizes,
+    input_span: Option<Span>,
+) -> Result<Vec<Detection>, LabeledError> {
+    let wants = |symbology: &str| symbologies.contains(&symbology);
+    let mut v = Vec::new();
+    let grids = match wants("qr") {
+        true => backend.grids(image),
+        false => vec![],
+    };
+    for s in grids {
+        match s {
+            Ok(code) => match decode(&code) {
+                Ok((mut data, grid)) => {
+                    // quirc stops reading at a structured append header or
+                    // FNC1 indicator, leaving the payload empty.
+                    let mut sequence = None;
+                    if data.payload.is_empty() {
+                        if let Some((header, payload)) = append::decode(&grid) {
+                            sequence = header;
+                            data.payload = payload;
+                        }
+                    }
+                    v.push(Detection {
+                        position: None,
+                        symbology: "qr",
+                        engine: backend.name(),
+                        corners: code.corners,
+                        modules: Some((grid.size as u32, grid.size as u32)),
+                        blocks: block_errors(&grid, &data),
+                        sequence,
+                        segment: None,
+                        count: None,
+                        data,
+                    })
+                }
+                Err(_) if !sizes.contains(&code.corners) => {}
+                Err(e) => {
+                    if !ignore_error {
+                        return Err(LabeledError {
+                            label: "input contains incorrect data".into(),
+                            msg: format!("identified data can not be decoded: {}", e),
+                            span: input_span,
+                        });
+                    } else {
+                        eprintln!("Ignore error while decoding: {}", e);
+                    }
+                }
+            },
+            Err(e) => {
+                if !ignore_error {
+                    return Err(LabeledError {
+                        label: "input contains incorrect data".into(),
+                        msg: format!("part of data can not be identified: {}", e),
+                        span: input_span,
+                    });
+                } else {
+                    eprintln!("Ignore error while decoding: {}", e);
+                }
+            }
+        }
+    }
+    Ok(v)
+}
+
+/// Find and decode all symbols of a grayscale image other than qr codes,
+/// running only the detectors of `symbologies`. They are read by rxing
+/// whatever the backend of the qr codes.
+fn detect_others(image: &GrayImage, symbologies: &[&str]) -> Vec<Detection> {
+    let wants = |symbology: &str| symbologies.contains(&symbology);
+    let mut v = detect_barcodes(image, symbologies);
+    if wants("mqr") {
+        v.extend(
+            micro::mqr(image)
+                .into_iter()
+                .map(|(corners, modules, data)| Detection {
+                    position: None,
+                    symbology: "mqr",
+                    engine: Backend::Rxing.name(),
+                    corners,
+                    modules: Some(modules),
+                    blocks: None,
+                    sequence: None,
+                    segment: None,
+                    count: None,
+                    data,
+                }),
+        );
+    }
+    if wants("rmqr") {
+        v.extend(
+            micro::rmqr(image)
+                .into_iter()
+                .map(|(corners, modules, data)| Detection {
+                    position: None,
+                    symbology: "rmqr",
+                    engine: Backend::Rxing.name(),
+                    corners,
+                    modules: Some(modules),
+                    blocks: None,
+                    sequence: None,
+                    segment: None,
+                    count: None,
+                    data,
+                }),
+        );
+    }
+    if wants("aztec") {
+        v.extend(
+            aztec::aztecs(image)
+                .into_iter()
+                .map(|(corners, modules, data)| Detection {
+                    position: None,
+                    symbology: "aztec",
+                    engine: Backend::Rxing.name(),
+                    corners,
+                    modules: Some(modules),
+                    blocks: None,
+                    sequence: None,
+                    segment: None,
+                    count: None,
+                    data,
+                }),
+        );
+    }
+    if wants("datamatrix") {
+        v.extend(
+            datamatrix::datamatrices(image)
+                .into_iter()
+                .map(|(corners, modules, data)| Detection {
+                    position: None,
+                    symbology: "datamatrix",
+                    engine: Backend::Rxing.name(),
+                    corners,
+                    modules: Some(modules),
+                    blocks: None,
+                    sequence: None,
+                    segment: None,
+                    count: None,
+                    data,
+                }),
+        );
+    }
+    if wants("pdf417") {
+        v.extend(
+            pdf417::pdf417s(image)
+                .into_iter()
+                .map(|(corners, segment, data)| Detection {
+                    position: None,
+                    symbology: "pdf417",
+                    engine: Backend::Rxing.name(),
+                    corners,
+                    modules: None,
+                    blocks: None,
+                    sequence: None,
+                    segment,
+                    count: None,
+                    data,
+                }),
+        );
+    }
+    v
+}
+
+/// Read the file at `path`, relative to the working directory of the shell.
+///
+/// `~` is expanded like nu expands file paths, which would turn urls into
+/// paths if the argument was one.
+fn read_file(path: &Spanned<String>) -> Result<Vec<u8>, LabeledError> {
+    let pwd = std::env::var("PWD").map(PathBuf::from).unwrap_or_default();
+    let full = nu_path::expand_path_with(&path.item, pwd);
+    std::fs::read(&full).map_err(|e| LabeledError {
+        label: "Unable to read file".into(),
+        msg: format!("{}: {}", full.display(), e),
+        span: Some(path.span),
+    })
+}
+
+/// Images larger than this are not downloaded.
+const MAX_DOWNLOAD: u64 = 64 * 1024 * 1024;
+
+/// Time a download may take, unless `--timeout` says otherwise.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Download the image at `url`, giving up after `timeout`.
+fn fetch(url: &Spanned<String>, timeout: Option<Duration>) -> Result<Vec<u8>, LabeledError> {
+    let error = |msg: String| LabeledError {
+        label: "Unable to download image".into(),
+        msg,
+        span: Some(url.span),
+    };
+    let response = ureq::AgentBuilder::new()
+        .timeout(timeout.unwrap_or(DOWNLOAD_TIMEOUT))
+        .try_proxy_from_env(true)
+        .build()
+        .get(&url.item)
+        .call()
+        .map_err(|e| error(e.to_string()))?;
+    let too_large = || {
+        error(format!(
+            "{} is larger than {} MiB",
+            url.item,
+            MAX_DOWNLOAD >> 20
+        ))
+    };
+    let length = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok());
+    if length.is_some_and(|l| l > MAX_DOWNLOAD) {
+        return Err(too_large());
+    }
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(MAX_DOWNLOAD + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| error(e.to_string()))?;
+    match bytes.len() as u64 > MAX_DOWNLOAD {
+        true => Err(too_large()),
+        false => Ok(bytes),
+    }
+}
+
+/// Angles in degrees that `--try-rotate` retries at, in order.
+const ROTATIONS: [f64; 8] = [90.0, 180.0, 270.0, 15.0, -15.0, 30.0, -30.0, 45.0];
+
+/// Scales that `--try-harder` retries at, in order.
+const SCALES: [f64; 4] = [1.0, 0.5, 2.0, 0.25];
+
+/// Binarizations that `--try-harder` retries with at every scale, `None`
+/// leaving it to quirc.
+const BINARIZATIONS: [Option<Binarization>; 4] = [
+    None,
+    Some(Binarization::Adaptive),
+    Some(Binarization::Threshold(96)),
+    Some(Binarization::Threshold(160)),
+];
+
+/// Images are not upscaled beyond this many pixels on their longest side.
+const MAX_UPSCALED: u32 = 2048;
+
+/// How images are prepared and searched for codes.
+#[derive(Clone)]
+struct Scanner {
+    ignore_error: bool,
+    /// Only look for light-on-dark codes, instead of trying them as a fallback.
+    invert: bool,
+    /// Retry at other angles when nothing is found.
+    try_rotate: bool,
+    /// Retry with other scales and binarizations when nothing is found.
+    try_harder: bool,
+    /// Backends to try in order, until one of them finds a code.
+    backends: Vec<Backend>,
+    codes: Codes,
+    /// Symbologies looked for, all of those of `codes` unless `--only` is
+    /// given.
+    symbologies: Vec<&'static str>,
+    /// Give up on an image after this long.
+    timeout: Option<Duration>,
+    /// When the `timeout` of the image being scanned is up, after which no
+    /// more pages, backends or passes are tried.
+    deadline: Option<Instant>,
+    /// Sizes of the codes that are kept.
+    sizes: Sizes,
+    /// Report codes found more than once on a page only once.
+    unique: bool,
+    sort_by: SortBy,
+    preprocess: Preprocess,
+}
+
+impl Scanner {
+    /// Read the image at `path`, a url or a file.
+    fn read(&self, path: &Spanned<String>) -> Result<Vec<u8>, LabeledError> {
+        match is_url(&path.item) {
+            true => fetch(path, self.timeout),
+            false => read_file(path),
+        }
+    }
+
+    /// Look for all `codes` as a command without flags does.
+    fn new(codes: Codes) -> Self {
+        let symbologies = codes.symbologies();
+        Scanner {
+            ignore_error: false,
+            invert: false,
+            try_rotate: false,
+            try_harder: false,
+            backends: match symbologies.contains(&"qr") {
+                true => Backend::ALL.to_vec(),
+                false => vec![Backend::Rxing],
+            },
+            codes,
+            symbologies,
+            timeout: None,
+            deadline: None,
+            sizes: Sizes {
+                min: 0,
+                max: u32::MAX,
+            },
+            unique: false,
+            sort_by: SortBy::Position,
+            preprocess: Preprocess::default(),
+        }
+    }
+
+    fn from_call(call: &EvaluatedCall, codes: Codes) -> Result<Self, LabeledError> {
+        let symbologies = codes.only_from_call(call)?;
+        Ok(Scanner {
+            ignore_error: call.has_flag("ignore-error"),
+            invert: call.has_flag("invert"),
+            try_rotate: call.has_flag("try-rotate"),
+            try_harder: call.has_flag("try-harder"),
+            // Symbols other than qr codes are only read by rxing.
+            backends: match symbologies.contains(&"qr") {
+                true => Backend::chain_from_call(call)?,
+                false => vec![Backend::Rxing],
+            },
+            codes,
+            symbologies,
+            timeout: match call.get_flag::<i64>("timeout")? {
+                Some(nanos) if nanos <= 0 => {
+                    return Err(LabeledError {
+                        label: "Invalid timeout".into(),
+                        msg: "should be a positive duration".into(),
+                        span: call.get_flag_value("timeout").and_then(|v| v.span().ok()),
+                    })
+                }
+                Some(nanos) => Some(Duration::from_nanos(nanos as u64)),
+                None => None,
+            },
+            deadline: None,
+            sizes: Sizes::from_call(call)?,
+            unique: call.has_flag("unique"),
+            sort_by: SortBy::from_call(call)?,
+            preprocess: Preprocess::from_call(call)?,
+        })
+    }
+
+    /// Load an image and find all codes in it, giving up after `--timeout`.
+    ///
+    /// The backends can not be interrupted, so the time is checked between
+    /// pages, backends and passes, and a pass that is running is finished.
+    /// With `ignore_error` the codes found until then are kept.
+    fn scan(&self, bytes: &[u8], input_span: Option<Span>) -> Result<Vec<Detection>, LabeledError> {
+        let Some(timeout) = self.timeout else {
+            return self.scan_image(bytes, input_span);
+        };
+        let scanner = Scanner {
+            deadline: Some(Instant::now() + timeout),
+            ..self.clone()
+        };
+        let detections = scanner.scan_image(bytes, input_span)?;
+        if !scanner.expired() {
+            Ok(detections)
+        } else if self.ignore_error {
+            eprintln!("Ignore timeout after {:?}", timeout);
+            Ok(detections)
+        } else {
+            Err(LabeledError {
+                label: "Timed out".into(),
+                msg: format!("the image was not searched completely within {:?}", timeout),
+                span: input_span,
+            })
+        }
+    }
+
+    /// Whether the time to scan the image is up.
+    fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Load an image (or the pages of a document, the frames of an animation)
+    /// and find all codes in it.
+    fn scan_image(
+        &self,
+        bytes: &[u8],
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        let pages = load::pages(bytes, input_span)?
+            .into_par_iter()
+            .enumerate()
+            .map(|(n, page)| {
+                // The first page is always searched, the others only in time.
+                if n > 0 && self.expired() {
+                    return Ok(vec![]);
+                }
+                let mut found = self.scan_page(page.image, input_span)?;
+                found.iter_mut().for_each(|d| d.position = page.position);
+                Ok(found)
+            })
+            .collect::<Result<Vec<_>, LabeledError>>()?;
+        let mut detections: Vec<_> = pages.into_iter().flatten().collect();
+        self.sort_by.sort(&mut detections);
+        Ok(match self.unique {
+            true => unique(detections),
+            false => detections,
+        })
+    }
+
+    /// Find all codes of a loaded image.
+    fn scan_page(
+        &self,
+        image: GrayImage,
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        let (mut image, origin) = self.preprocess.apply(image)?;
+        if self.invert {
+            imageops::invert(&mut image);
+        }
+        let mut result = self.attempt(&image, input_span);
+        if self.try_harder && result.as_ref().map_or(true, Vec::is_empty) && !self.expired() {
+            if let Some(detections) = self.try_harder(&image, input_span) {
+                result = Ok(detections);
+            }
+        }
+        let mut detections = result?;
+        if detections.is_empty() && self.try_rotate {
+            for degrees in ROTATIONS {
+                if self.expired() {
+                    break;
+                }
+                let (rotated, rotation) = preprocess::rotate(&image, degrees);
+                detections = self.search(&rotated, input_span)?;
+                if !detections.is_empty() {
+                    for d in &mut detections {
+                        d.corners.iter_mut().for_each(|p| rotation.unmap(p));
+                    }
+                    break;
+                }
+            }
+        }
+        for d in &mut detections {
+            d.corners.iter_mut().for_each(|p| origin.map(p));
+        }
+        detections.retain(|d| self.sizes.contains(&d.corners));
+        Ok(detections)
+    }
+
+    /// Find all codes of a prepared image, rectifying those that are skewed.
+    fn attempt(
+        &self,
+        image: &GrayImage,
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        match self.search(image, input_span) {
+            Ok(detections) if !detections.is_empty() => Ok(detections),
+            result if self.expired() => result,
+            // Finder patterns that quirc could not sample a code from may
+            // belong to one photographed at a steep angle.
+            result => match self.rectified(image, input_span) {
+                rectified if rectified.is_empty() => result,
+                rectified => Ok(rectified),
+            },
+        }
+    }
+
+    /// Retry at every scale and binarization until one of them finds a code.
+    fn try_harder(&self, image: &GrayImage, input_span: Option<Span>) -> Option<Vec<Detection>> {
+        let longest = image.width().max(image.height()) as f64;
+        for factor in SCALES {
+            let size = longest * factor;
+            if (factor > 1.0 && size > MAX_UPSCALED as f64) || size < 32.0 {
+                continue;
+            }
+            let scaled = match factor {
+                1.0 => image.clone(),
+                _ => preprocess::scale(image, factor),
+            };
+            for binarization in BINARIZATIONS {
+                if self.expired() {
+                    return None;
+                }
+                let binarized = match binarization {
+                    // The original image has already been tried.
+                    None if factor == 1.0 => continue,
+                    None => scaled.clone(),
+                    Some(b) => preprocess::binarize(&scaled, b),
+                };
+                let Ok(mut detections) = self.attempt(&binarized, input_span) else {
+                    continue;
+                };
+                if !detections.is_empty() {
+                    for d in &mut detections {
+                        for p in &mut d.corners {
+                            p.x = (p.x as f64 / factor).round() as i32;
+                            p.y = (p.y as f64 / factor).round() as i32;
+                        }
+                    }
+                    return Some(detections);
+                }
+            }
+        }
+        None
+    }
+
+    /// Rectify every qr code located by its finder patterns and decode it.
+    fn rectified(&self, image: &GrayImage, input_span: Option<Span>) -> Vec<Detection> {
+        let mut v: Vec<Detection> = Vec::new();
+        for quad in preprocess::outlines(image) {
+            if self.expired() {
+                break;
+            }
+            let Some((rectified, perspective)) = preprocess::rectify(image, quad) else {
+                continue;
+            };
+            for mut d in self.search(&rectified, input_span).unwrap_or_default() {
+                d.corners.iter_mut().for_each(|p| perspective.unmap(p));
+                // Several sets of finder patterns may outline the same code,
+                // while codes elsewhere in the image may repeat its payload.
+                if v.iter().all(|other| !other.overlaps(&d)) {
+                    v.push(d);
+                }
+            }
+        }
+        v
+    }
+
+    /// Find all codes of a prepared image, the qr codes with the first
+    /// backend that finds any.
+    ///
+    /// If none does, the first error is returned, as a code was seen after all.
+    fn search(
+        &self,
+        image: &GrayImage,
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        let wants_qr = |s: &&str| *s == "qr";
+        // The other symbologies do not depend on the backend, and are looked
+        // for once whichever backend finds the qr codes.
+        let mut detections = match self.symbologies.iter().all(wants_qr) {
+            true => vec![],
+            false => self.search_with(image, None, input_span)?,
+        };
+        if !self.symbologies.iter().any(wants_qr) {
+            return Ok(detections);
+        }
+        let mut error = None;
+        for backend in &self.backends {
+            if self.expired() {
+                return Ok(detections);
+            }
+            match self.search_with(image, Some(backend), input_span) {
+                Ok(qr) if qr.is_empty() => {}
+                Ok(qr) => {
+                    detections.extend(qr);
+                    return Ok(detections);
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match error {
+            Some(e) => Err(e),
+            None => Ok(detections),
+        }
+    }
+
+    /// Find the qr codes of a prepared image with `backend`, or the other
+    /// codes without one, trying light-on-dark codes too.
+    fn search_with(
+        &self,
+        image: &GrayImage,
+        backend: Option<&Backend>,
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        let detections = self.detect_padded(image, backend, input_span)?;
+        if !detections.is_empty() || self.invert || self.expired() {
+            return Ok(detections);
+        }
+        // Light-on-dark codes, e.g. from dark mode screenshots.
+        let mut inverted = image.clone();
+        imageops::invert(&mut inverted);
+        self.detect_padded(&inverted, backend, input_span)
+    }
+
+    /// Find the qr codes of a prepared image with `backend`, or the other
+    /// codes without one, retrying with a quiet zone around it if none is
+    /// found, as screenshots are often cropped right at the edge of the code.
+    fn detect_padded(
+        &self,
+        image: &GrayImage,
+        backend: Option<&Backend>,
+        input_span: Option<Span>,
+    ) -> Result<Vec<Detection>, LabeledError> {
+        let detect = |image: &GrayImage| match backend {
+            Some(backend) => detect(
+                image,
+                backend,
+                &self.symbologies,
+                self.ignore_error,
+                self.sizes,
+                input_span,
+            ),
+            None => Ok(detect_others(image, &self.symbologies)),
+        };
+        let detections = detect(image)?;
+        if !detections.is_empty() || self.expired() {
+            return Ok(detections);
+        }
+        let (padded, margin) = preprocess::pad(image);
+        let mut detections = detect(&padded)?;
+        for d in &mut detections {
+            for p in &mut d.corners {
+                p.x -= margin as i32;
+                p.y -= margin as i32;
+            }
+        }
+        Ok(detections)
+    }
+}
+
+/// Decode a list of image paths or binaries into a table with one row per code.
+///
+/// Each row starts with the source of the code, a `filename` for paths or the
+/// position in the list as `input` for binaries. Images that can not be
+/// decoded are reported in the `error` column instead of failing the whole
+/// batch. Structured append sequences spread over several images are
+/// reassembled into the row of their first part.
+fn scan_list(
+    items: &[Value],
+    scanner: &Scanner,
+    charset: &Charset,
+    checksum: Option<Checksum>,
+    span: Span,
+) -> Result<Value, LabeledError> {
+    // Not streamed: nu-plugin 0.83 has no streaming plugin API, so the engine
+    // collects the whole list before calling us and the rows are returned as
+    // one list. Streaming waits for the port to `PluginCommand`; until then
+    // the items are spread over a thread pool.
+    let scanned = items
+        .par_iter()
+        .enumerate()
+        .map(|(n, item)| scan_item(n, item, scanner, span))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut owners = Vec::new();
+    let mut detections = Vec::new();
+    let mut results = Vec::new();
+    for (n, (source, result)) in scanned.into_iter().enumerate() {
+        let result = result.map(|found| {
+            owners.extend(std::iter::repeat_n(n, found.len()));
+            detections.extend(found.into_iter().map(Some));
+        });
+        results.push((source, result));
+    }
+    reassemble(&mut detections, scanner.ignore_error, Some(span))?;
+    let mut found: Vec<Vec<Option<Detection>>> = results.iter().map(|_| Vec::new()).collect();
+    for (n, d) in owners.into_iter().zip(detections) {
+        found[n].push(d);
+    }
+    let rows = results
+        .into_iter()
+        .zip(found)
+        .flat_map(|((source, result), found)| {
+            let result = result.map(|_| found);
+            item_rows(source, result, scanner.codes, charset, checksum, span)
+        })
+        .collect();
+    Ok(Value::List { vals: rows, span })
+}
+
+/// Source column of a list item, its name and value.
+type Source = (&'static str, Value);
+
+/// Find the codes of the `n`th item of a list input.
+///
+/// Errors of the item itself are returned along with its source, only input
+/// that is not an image or a path fails.
+fn scan_item(
+    n: usize,
+    item: &Value,
+    scanner: &Scanner,
+    span: Span,
+) -> Result<(Source, Result<Vec<Detection>, LabeledError>), LabeledError> {
+    let input = (
+        "input",
+        Value::Int {
+            val: n as i64,
+            span,
+        },
+    );
+    // Text is a path, unless it is the image itself.
+    let text = match item {
+        Value::String {
+            val,
+            span: item_span,
+        } => load::from_text(val, *item_span).map(|decoded| (decoded, *item_span)),
+        _ => None,
+    };
+    Ok(match (item, text) {
+        (
+            Value::Binary {
+                val,
+                span: item_span,
+            },
+            _,
+        ) => (input, scanner.scan(val, Some(*item_span))),
+        (_, Some((decoded, item_span))) => (
+            input,
+            decoded.and_then(|bytes| scanner.scan(&bytes, Some(item_span))),
+        ),
+        _ => {
+            let path = Spanned::<String>::from_value(item)?;
+            let result = scanner
+                .read(&path)
+                .and_then(|bytes| scanner.scan(&bytes, Some(path.span)));
+            (
+                (
+                    if is_url(&path.item) {
+                        "url"
+                    } else {
+                        "filename"
+                    },
+                    Value::String {
+                        val: path.item,
+                        span,
+                    },
+                ),
+                result,
+            )
+        }
+    })
+}
+
+/// The rows of the table for the codes found in a list item.
+///
+/// Codes taken out by `reassemble` are `None`, they were part of a sequence
+/// reported with an earlier code.
+fn item_rows(
+    source: Source,
+    result: Result<Vec<Option<Detection>>, LabeledError>,
+    codes: Codes,
+    charset: &Charset,
+    checksum: Option<Checksum>,
+    span: Span,
+) -> Vec<Value> {
+    let row = |index: Value, symbology: Option<&str>, payload: Value, error: Option<String>| {
+        let mut row = Value::Record {
+            cols: vec![
+                source.0.into(),
+                "index".into(),
+                "payload".into(),
+                "error".into(),
+            ],
+            vals: vec![
+                source.1.clone(),
+                index,
+                payload,
+                match error {
+                    Some(val) => Value::String { val, span },
+                    None => Value::Nothing { span },
+                },
+            ],
+            span,
+        };
+        // `from code` tells which kind of code each one is.
+        if let (Codes::All, Value::Record { cols, vals, .. }) = (codes, &mut row) {
+            cols.insert(2, "symbology".into());
+            vals.insert(
+                2,
+                match symbology {
+                    Some(val) => Value::String {
+                        val: val.into(),
+                        span,
+                    },
+                    None => Value::Nothing { span },
+                },
+            );
+        }
+        row
+    };
+    // Codes of documents and animations tell their page or frame right after
+    // the source.
+    let with_position = |mut row: Value, position: Option<(&str, usize)>| {
+        if let (Some((unit, n)), Value::Record { cols, vals, .. }) = (position, &mut row) {
+            cols.insert(1, unit.into());
+            vals.insert(
+                1,
+                Value::Int {
+                    val: n as i64,
+                    span,
+                },
+            );
+        }
+        row
+    };
+    match result {
+        Ok(detections) if detections.is_empty() => vec![row(
+            Value::Nothing { span },
+            None,
+            Value::Nothing { span },
+            Some("no code found".into()),
+        )],
+        Ok(detections) => detections
+            .iter()
+            .enumerate()
+            .filter_map(|(i, d)| Some((i, d.as_ref()?)))
+            .map(|(i, d)| {
+                let index = Value::Int {
+                    val: i as i64,
+                    span,
+                };
+                let payload = charset.to_value(&d.data, span);
+                let mut row = row(index, Some(d.symbology), payload, None);
+                if let Some(checksum) = checksum {
+                    let column = if codes == Codes::All { 4 } else { 3 };
+                    checksum.insert(&mut row, column, &d.data.payload, span);
+                }
+                let mut row = with_position(row, d.position);
+                if let (Some(count), Value::Record { cols, vals, .. }) = (d.count, &mut row) {
+                    cols.push("count".into());
+                    vals.push(Value::Int {
+                        val: count as i64,
+                        span,
+                    });
+                }
+                row
+            })
+            .collect(),
+        Err(e) => vec![row(
+            Value::Nothing { span },
+            None,
+            Value::Nothing { span },
+            Some(format!("{}: {}", e.label, e.msg)),
+        )],
+    }
+}
+
+/// Find and decode the `codes` of the input, or of the image at the path
+/// given as argument.
+pub(crate) fn from_image(
+    call: &EvaluatedCall,
+    input: &Value,
+    input_span: Option<Span>,
+    codes: Codes,
+) -> Result<Value, LabeledError> {
+    let scanner = Scanner::from_call(call, codes)?;
+    let charset = Charset::from_call(call)?;
+    let checksum = Checksum::from_call(call)?;
+    if checksum.is_some() && call.has_flag("join") && !call.has_flag("detailed") {
+        return Err(LabeledError {
+            label: "Conflicting flags".into(),
+            msg: "--checksum adds a column for every code, which --join would merge into one value"
+                .into(),
+            span: Some(call.head),
+        });
+    }
+    let file;
+    let (bytes, input_span) = match (call.opt::<Spanned<String>>(0)?, input) {
+        (Some(path), _) => {
+            file = scanner.read(&path)?;
+            (file.as_slice(), Some(path.span))
+        }
+        (None, Value::List { vals, .. }) => {
+            return scan_list(vals, &scanner, &charset, checksum, call.head)
+        }
+        (None, Value::String { val, span }) => match load::from_text(val, *span) {
+            Some(decoded) => {
+                file = decoded?;
+                (file.as_slice(), Some(*span))
+            }
+            None if is_url(val) => {
+                file = scanner.read(&Spanned {
+                    item: val.clone(),
+                    span: *span,
+                })?;
+                (file.as_slice(), Some(*span))
+            }
+            None => {
+                return Err(LabeledError {
+                    label: "Invalid input".into(),
+                    msg: "text should be a url, a data uri or a base64 encoded image, pass paths as an argument"
+                        .into(),
+                    span: Some(*span),
+                })
+            }
+        },
+        (None, _) => (input.as_binary()?, input_span),
+    };
+    let mut detections: Vec<_> = scanner
+        .scan(bytes, input_span)?
+        .into_iter()
+        .map(Some)
+        .collect();
+    reassemble(&mut detections, scanner.ignore_error, input_span)?;
+    let detections: Vec<_> = detections.into_iter().flatten().collect();
+    Ok(if call.has_flag("detailed") {
+        Value::List {
+            vals: detections
+                .into_iter()
+                .map(|d| d.into_record(&charset, checksum, call.head))
+                .collect(),
+            span: call.head,
+        }
+    } else if (codes == Codes::All && !call.has_flag("join")) || checksum.is_some() {
+        // Payloads alone have no room for their checksum.
+        symbols_to_value(detections, &charset, checksum, call.head)
+    } else if detections.iter().any(|d| d.position.is_some()) {
+        pages_to_value(detections, call.has_flag("join"), &charset, call.head)
+    } else {
+        payloads_to_value(
+            detections.into_iter().map(|d| d.data).collect(),
+            call.has_flag("join"),
+            &charset,
+            call.head,
+        )
+    })
+}
+
+/// Convert the codes found by `from code`, or by any command with
+/// `--checksum`, to a table of their `symbology` and `payload`, after the
+/// `page` (or `frame`) of documents and animations.
+fn symbols_to_value(
+    detections: Vec<Detection>,
+    charset: &Charset,
+    checksum: Option<Checksum>,
+    span: Span,
+) -> Value {
+    Value::List {
+        vals: detections
+            .into_iter()
+            .map(|d| {
+                let mut record = Value::Record {
+                    cols: vec!["symbology".into(), "payload".into()],
+                    vals: vec![
+                        Value::String {
+                            val: d.symbology.into(),
+                            span,
+                        },
+                        charset.to_value(&d.data, span),
+                    ],
+                    span,
+                };
+                if let Some(checksum) = checksum {
+                    checksum.insert(&mut record, 2, &d.data.payload, span);
+                }
+                if let Value::Record { cols, vals, .. } = &mut record {
+                    if let Some((unit, n)) = d.position {
+                        cols.insert(0, unit.into());
+                        vals.insert(
+                            0,
+                            Value::Int {
+                                val: n as i64,
+                                span,
+                            },
+                        );
+                    }
+                    if let Some(count) = d.count {
+                        cols.push("count".into());
+                        vals.push(Value::Int {
+                            val: count as i64,
+                            span,
+                        });
+                    }
+                }
+                record
+            })
+            .collect(),
+        span,
+    }
+}
+
+/// Convert the codes found in a document (or animation) to a table of `page`
+/// (or `frame`) and `payload`, with one row for every page that has codes on
+/// it.
+fn pages_to_value(detections: Vec<Detection>, join: bool, charset: &Charset, span: Span) -> Value {
+    let mut pages: Vec<((&str, usize), Vec<Data>)> = Vec::new();
+    for d in detections {
+        let position = d.position.unwrap_or(("page", 0));
+        match pages.last_mut() {
+            Some((last, payloads)) if *last == position => payloads.push(d.data),
+            _ => pages.push((position, vec![d.data])),
+        }
+    }
+    Value::List {
+        vals: pages
+            .into_iter()
+            .map(|((unit, n), payloads)| Value::Record {
+                cols: vec![unit.into(), "payload".into()],
+                vals: vec![
+                    Value::Int {
+                        val: n as i64,
+                        span,
+                    },
+                    payloads_to_value(payloads, join, charset, span),
+                ],
+                span,
+            })
+            .collect(),
+        span,
+    }
+}
+
+/// A code found by [scan].
+pub struct Found {
+    /// `qr`, `mqr`, `rmqr`, `aztec`, `datamatrix`, `pdf417`, or the name of
+    /// a barcode as `to barcode` calls it.
+    pub symbology: &'static str,
+    /// Corners of the code in pixels, clockwise from its top left.
+    pub corners: [(i32, i32); 4],
+    /// `page` or `frame` and its number, for documents and animations.
+    pub position: Option<(&'static str, usize)>,
+    /// The payload, with the parts of a structured append sequence joined.
+    pub payload: Vec<u8>,
+}
+
+/// Find and decode the `codes` of an image, a pdf document or an animation,
+/// as `from qr`, `from barcode` and `from code` do without flags.
+///
+/// Codes are returned in reading order, page by page. Errors are the label
+/// and message `from qr` would show.
+pub fn scan(bytes: &[u8], codes: Codes) -> Result<Vec<Found>, String> {
+    let message = |e: LabeledError| format!("{}: {}", e.label, e.msg);
+    let scanner = Scanner::new(codes);
+    let mut detections: Vec<_> = scanner
+        .scan(bytes, None)
+        .map_err(message)?
+        .into_iter()
+        .map(Some)
+        .collect();
+    reassemble(&mut detections, false, None).map_err(message)?;
+    Ok(detections
+        .into_iter()
+        .flatten()
+        .map(|d| Found {
+            symbology: d.symbology,
+            corners: d.corners.map(|p| (p.x, p.y)),
+            position: d.position,
+            payload: d.data.payload,
+        })
+        .collect())
+}
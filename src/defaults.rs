@@ -0,0 +1,33 @@
+//! Defaults of flags from `NU_QR_*` environment variables.
+//!
+//! Nushell starts the plugin with the environment of the caller, so scripts
+//! and CI jobs can set `$env.NU_QR_WIDTH` once instead of `--width` on every
+//! call.
+
+use std::str::FromStr;
+
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::FromValue;
+
+/// Value of the flag `name` of `call`, or else of the environment variable
+/// named after it, e.g. `NU_QR_WIDTH` for `--width`. Empty variables count
+/// as unset.
+pub fn flag<T: FromValue + FromStr>(
+    call: &EvaluatedCall,
+    name: &str,
+) -> Result<Option<T>, LabeledError> {
+    if let Some(value) = call.get_flag(name)? {
+        return Ok(Some(value));
+    }
+    let var = format!("NU_QR_{}", name.to_uppercase().replace('-', "_"));
+    match std::env::var(&var) {
+        Ok(text) if !text.trim().is_empty() => {
+            text.trim().parse().map(Some).map_err(|_| LabeledError {
+                label: format!("Invalid {}", var),
+                msg: format!("{:?} is not a valid value of --{}", text, name),
+                span: Some(call.head),
+            })
+        }
+        _ => Ok(None),
+    }
+}
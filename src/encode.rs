@@ -0,0 +1,154 @@
+//! Encoding of qr codes, other two dimensional codes and barcodes as images,
+//! without nushell.
+//!
+//! Symbologies are named as `--symbology` of `to qr` and `to barcode` names
+//! them, and errors are the messages these commands show.
+
+pub use fast_qr::convert::Shape;
+use fast_qr::convert::{image::ImageBuilder, Builder};
+
+use crate::barcode;
+use crate::matrix;
+pub use crate::maxicode::Carrier;
+use crate::payloads;
+
+/// Width of images unless [Options::width] or [Options::height] are given.
+const DEFAULT_WIDTH: u32 = 600;
+
+/// How [render] draws a code, the flags of `to qr`.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// `qr`, `datamatrix`, `aztec`, `maxicode` or `hanxin`.
+    pub symbology: String,
+    /// Shape of the modules of qr codes, the others have square modules.
+    pub shape: Shape,
+    /// Pixels to fit the image into, 600 pixels wide if neither is given.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Read the text as a GS1 element string such as
+    /// `(01)09501101530003(17)270101`, for data matrix.
+    pub gs1: bool,
+    /// Structured carrier message of a maxicode.
+    pub carrier: Option<Carrier>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            symbology: "qr".into(),
+            shape: Shape::Square,
+            width: None,
+            height: None,
+            gs1: false,
+            carrier: None,
+        }
+    }
+}
+
+/// Encode `data` as a png of a two dimensional code, as `options` tell.
+///
+/// Qr codes hold any bytes, the others hold only text and are drawn with
+/// whole pixels per square module.
+pub fn render(data: &[u8], options: &Options) -> Result<Vec<u8>, String> {
+    let symbology = matrix::Symbology::from_name(&options.symbology)?;
+    if symbology == matrix::Symbology::Qr {
+        let code = fast_qr::QRBuilder::new(data)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut builder = ImageBuilder::default();
+        builder.shape(options.shape);
+        match (options.width, options.height) {
+            (Some(w), Some(h)) => builder.fit_width(w).fit_height(h),
+            (Some(w), None) => builder.fit_width(w),
+            (None, Some(h)) => builder.fit_height(h),
+            (None, None) => builder.fit_width(DEFAULT_WIDTH),
+        };
+        return builder
+            .to_pixmap(&code)
+            .encode_png()
+            .map_err(|e| e.to_string());
+    }
+    let text = std::str::from_utf8(data)
+        .map_err(|_| "only text can be encoded in this symbology".to_string())?;
+    let text = match options.gs1 {
+        true => payloads::gs1::element_string(text).ok_or_else(|| {
+            "should be a GS1 element string of known AIs, e.g. (01)09501101530003(17)270101"
+                .to_string()
+        })?,
+        false => text.to_string(),
+    };
+    let code = symbology.encode(&text, options.gs1, options.carrier.as_ref())?;
+    let (width, height) = symbology.size(&code);
+    let fit = |size: u32, modules: f64| size as f64 / modules;
+    let pixels = match (options.width, options.height) {
+        (Some(w), Some(h)) => fit(w, width).min(fit(h, height)),
+        (Some(w), None) => fit(w, width),
+        (None, Some(h)) => fit(h, height),
+        (None, None) => fit(DEFAULT_WIDTH, width),
+    };
+    matrix::png(&symbology.image(&code, pixels))
+}
+
+/// Encode `data` as a png qr code of modules of `shape`, `width` pixels wide.
+pub fn qr(data: &[u8], shape: Shape, width: u32) -> Result<Vec<u8>, String> {
+    render(
+        data,
+        &Options {
+            shape,
+            width: Some(width),
+            ..Default::default()
+        },
+    )
+}
+
+/// Encode `data` as a png of a two dimensional code of `symbology`, `qr`,
+/// `datamatrix`, `aztec`, `maxicode` or `hanxin`, about `width` pixels wide.
+///
+/// Qr codes hold any bytes and have square modules, the others hold only
+/// text.
+pub fn matrix(symbology: &str, data: &[u8], width: u32) -> Result<Vec<u8>, String> {
+    render(
+        data,
+        &Options {
+            symbology: symbology.into(),
+            width: Some(width),
+            ..Default::default()
+        },
+    )
+}
+
+/// Encode `text` as a png barcode of `symbology`, e.g. `code128` or `ean13`,
+/// with `scale` pixels per module and bars `height` pixels high.
+pub fn barcode(
+    symbology: &str,
+    text: &str,
+    scale: usize,
+    height: usize,
+) -> Result<Vec<u8>, String> {
+    let symbol = bars(symbology, text, scale, height)?;
+    barcode::png(&symbol, scale, height)
+}
+
+/// Encode `text` as an svg document of a barcode, like [barcode].
+pub fn barcode_svg(
+    symbology: &str,
+    text: &str,
+    scale: usize,
+    height: usize,
+) -> Result<String, String> {
+    let symbol = bars(symbology, text, scale, height)?;
+    Ok(barcode::svg(&symbol, scale, height))
+}
+
+/// The bars of `text`, checking that the barcode has a size.
+fn bars(
+    symbology: &str,
+    text: &str,
+    scale: usize,
+    height: usize,
+) -> Result<barcode::Symbol, String> {
+    if scale == 0 || height == 0 {
+        return Err("scale and height should be at least 1".into());
+    }
+    barcode::Symbology::from_name(symbology)?.encode(text)
+}
@@ -0,0 +1,455 @@
+//! Han Xin codes, the Chinese two dimensional code of GB/T 21049 and
+//! ISO/IEC 20830, encoded here as rxing does not handle them.
+//!
+//! A symbol of version v is 21 + 2v modules square, with a finder pattern in
+//! each corner and, from version 4 on, a staggered grid of alignment lines.
+//! Text is written in GB 18030, in modes for digits, ASCII, the two regions
+//! of common Chinese characters, other two byte characters and four byte
+//! ones. Versions 1 to 10 are generated, up to 41 modules square.
+
+use encoding_rs::GB18030;
+use rxing::common::reedsolomon::{GenericGF, PredefinedGenericGF, ReedSolomonEncoder};
+use rxing::common::BitMatrix;
+
+/// Field of the data codewords, x^8 + x^6 + x^5 + x + 1.
+static FIELD: GenericGF = GenericGF::new(0x163, 256, 1);
+
+/// Error correction level, L2 of L1 to L4, which restores about 15% of the
+/// codewords as the default level of qr codes.
+const LEVEL: u32 = 2;
+
+/// Error correction blocks of level L2 by version, as their count, data
+/// codewords and error correction codewords.
+const BLOCKS: [&[(usize, usize, usize)]; 10] = [
+    &[(1, 17, 8)],
+    &[(1, 25, 12)],
+    &[(1, 34, 16)],
+    &[(1, 38, 16)],
+    &[(1, 49, 20)],
+    &[(1, 58, 26)],
+    &[(1, 70, 30)],
+    &[(1, 40, 18), (1, 41, 18)],
+    &[(2, 48, 20)],
+    &[(1, 52, 22), (1, 57, 24)],
+];
+
+/// Width of the blocks between alignment lines from version 4 on, but for
+/// the last one which is one module less than `ALIGNMENT_LAST`.
+const ALIGNMENT: [u32; 7] = [14, 16, 16, 17, 18, 19, 20];
+const ALIGNMENT_LAST: [u32; 7] = [15, 15, 17, 18, 19, 20, 21];
+
+/// Finder patterns of the top left corner, of the top right and bottom left
+/// ones, and of the bottom right one, as rows of 7 modules from the left.
+const FINDER_TOP_LEFT: [u8; 7] = [0x7f, 0x40, 0x5f, 0x50, 0x57, 0x57, 0x57];
+const FINDER: [u8; 7] = [0x7f, 0x01, 0x7d, 0x05, 0x75, 0x75, 0x75];
+const FINDER_BOTTOM_RIGHT: [u8; 7] = [0x75, 0x75, 0x75, 0x05, 0x7d, 0x01, 0x7f];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Numeric = 1,
+    Text = 2,
+    Binary = 3,
+    Region1 = 4,
+    Region2 = 5,
+    DoubleByte = 6,
+    FourByte = 7,
+}
+
+/// Mode of a character by its GB 18030 bytes.
+fn mode(bytes: &[u8]) -> Mode {
+    match bytes {
+        [b'0'..=b'9'] => Mode::Numeric,
+        // Text mode has no codes for the information separators.
+        [0x1c..=0x1f] => Mode::Binary,
+        [_] => Mode::Text,
+        [0xb0..=0xd7 | 0xa1..=0xa3, 0xa1..=0xfe] | [0xa8, 0xa1..=0xc0] => Mode::Region1,
+        [0xd8..=0xf7, 0xa1..=0xfe] => Mode::Region2,
+        [_, _] => Mode::DoubleByte,
+        _ => Mode::FourByte,
+    }
+}
+
+/// Submode and value of an ASCII character in text mode.
+fn text_value(b: u8) -> (u8, u32) {
+    let (submode, value) = match b {
+        b'0'..=b'9' => (1, b - b'0'),
+        b'A'..=b'Z' => (1, b - b'A' + 10),
+        b'a'..=b'z' => (1, b - b'a' + 36),
+        0..=0x1b => (2, b),
+        b' '..=b'/' => (2, b - b' ' + 28),
+        b':'..=b'@' => (2, b - b':' + 44),
+        b'['..=b'`' => (2, b - b'[' + 51),
+        _ => (2, b - b'{' + 57),
+    };
+    (submode, value as u32)
+}
+
+fn push(bits: &mut Vec<bool>, value: u32, len: u32) {
+    bits.extend((0..len).rev().map(|i| value >> i & 1 == 1));
+}
+
+/// Bits of `text`, in runs of characters of the same mode.
+///
+/// Digits are only written in numeric mode on their own or in runs long
+/// enough to save the bits of leaving and resuming text mode.
+fn message(text: &str) -> Vec<bool> {
+    let mut runs: Vec<(Mode, Vec<Vec<u8>>)> = vec![];
+    for c in text.chars() {
+        let bytes = GB18030.encode(c.encode_utf8(&mut [0; 4])).0.into_owned();
+        match runs.last_mut() {
+            Some((m, chars)) if *m == mode(&bytes) => chars.push(bytes),
+            _ => runs.push((mode(&bytes), vec![bytes])),
+        }
+    }
+    for i in 0..runs.len() {
+        let next_to_text = [i.wrapping_sub(1), i + 1]
+            .iter()
+            .any(|&j| matches!(runs.get(j), Some((Mode::Text, _))));
+        if runs[i].0 == Mode::Numeric && runs[i].1.len() < 12 && next_to_text {
+            runs[i].0 = Mode::Text;
+        }
+    }
+    let mut merged: Vec<(Mode, Vec<Vec<u8>>)> = vec![];
+    for (m, chars) in runs {
+        match merged.last_mut() {
+            Some((last, previous)) if *last == m => previous.extend(chars),
+            _ => merged.push((m, chars)),
+        }
+    }
+    let mut bits = vec![];
+    for (m, chars) in merged {
+        if m != Mode::FourByte {
+            push(&mut bits, m as u32, 4);
+        }
+        match m {
+            Mode::Numeric => {
+                let digits: Vec<u8> = chars.concat();
+                for group in digits.chunks(3) {
+                    let value = group.iter().fold(0, |n, d| n * 10 + (d - b'0') as u32);
+                    push(&mut bits, value, 10);
+                }
+                // The terminator tells how many digits the last group has.
+                push(&mut bits, 1020 + (digits.len() - 1) as u32 % 3 + 1, 10);
+            }
+            Mode::Text => {
+                let mut submode = 1;
+                for b in chars.concat() {
+                    let (sub, value) = text_value(b);
+                    if sub != submode {
+                        push(&mut bits, 62, 6);
+                        submode = sub;
+                    }
+                    push(&mut bits, value, 6);
+                }
+                push(&mut bits, 63, 6);
+            }
+            Mode::Binary => {
+                push(&mut bits, chars.len() as u32, 13);
+                chars
+                    .concat()
+                    .iter()
+                    .for_each(|b| push(&mut bits, *b as u32, 8));
+            }
+            Mode::Region1 | Mode::Region2 => {
+                for c in chars {
+                    let (b1, b2) = (c[0] as u32, c[1] as u32 - 0xa1);
+                    let value = match b1 {
+                        0xa8 => b2 + 0xfca,
+                        0xa1..=0xa3 => 94 * (b1 - 0xa1) + b2 + 0xeb0,
+                        0xb0..=0xd7 => 94 * (b1 - 0xb0) + b2,
+                        _ => 94 * (b1 - 0xd8) + b2,
+                    };
+                    push(&mut bits, value, 12);
+                }
+                push(&mut bits, 0xfff, 12);
+            }
+            Mode::DoubleByte => {
+                for c in chars {
+                    let (b1, b2) = (c[0] as u32, c[1] as u32);
+                    let b2 = b2 - if b2 <= 0x7e { 0x40 } else { 0x41 };
+                    push(&mut bits, 0xbe * (b1 - 0x81) + b2, 15);
+                }
+                push(&mut bits, 0x7fff, 15);
+            }
+            Mode::FourByte => {
+                for c in chars {
+                    let [b1, b2, b3, b4] = [0, 1, 2, 3].map(|i| c[i] as u32);
+                    push(&mut bits, m as u32, 4);
+                    push(
+                        &mut bits,
+                        0x3138 * (b1 - 0x81) + 0x4ec * (b2 - 0x30) + 0xa * (b3 - 0x81) + b4 - 0x30,
+                        21,
+                    );
+                }
+            }
+        }
+    }
+    bits
+}
+
+/// Reed-Solomon error correction codewords of `data`.
+fn error_correction(field: &'static GenericGF, data: &[u8], ec: usize) -> Result<Vec<u8>, String> {
+    let mut codewords: Vec<i32> = data.iter().map(|cw| *cw as i32).collect();
+    codewords.resize(data.len() + ec, 0);
+    ReedSolomonEncoder::new(field)
+        .and_then(|mut encoder| encoder.encode(&mut codewords, ec))
+        .map_err(|e| e.to_string())?;
+    Ok(codewords[data.len()..].iter().map(|cw| *cw as u8).collect())
+}
+
+/// Version and codewords of `text`, every block followed by its error
+/// correction and all spread 13 apart.
+fn codewords(text: &str) -> Result<(usize, Vec<u8>), String> {
+    let mut bits = message(text);
+    let capacity = |v: usize| -> usize { BLOCKS[v].iter().map(|(n, data, _)| n * data).sum() };
+    let len = bits.len().div_ceil(8);
+    let Some(version) = (0..BLOCKS.len()).find(|&v| capacity(v) >= len) else {
+        return Err(format!(
+            "text too long, it takes {} codewords and there is room for {}",
+            len,
+            capacity(BLOCKS.len() - 1)
+        ));
+    };
+    // Zeros after the text read as the end of the data.
+    bits.resize(capacity(version) * 8, false);
+    let data: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |cw, bit| cw << 1 | *bit as u8))
+        .collect();
+    let mut blocks = vec![];
+    let mut rest = &data[..];
+    for &(n, len, ec) in BLOCKS[version] {
+        for _ in 0..n {
+            let (block, next) = rest.split_at(len);
+            blocks.extend(block);
+            blocks.extend(error_correction(&FIELD, block, ec)?);
+            rest = next;
+        }
+    }
+    let codewords = (0..13)
+        .flat_map(|start| blocks.iter().skip(start).step_by(13).copied())
+        .collect();
+    Ok((version + 1, codewords))
+}
+
+/// Modules of the finder, separator, function information and alignment
+/// patterns of a symbol, the latter not filled in yet.
+fn function_patterns(version: usize) -> Vec<Vec<Option<bool>>> {
+    let size = 21 + 2 * version;
+    let mut grid = vec![vec![None; size]; size];
+    let finders = [
+        (0, 0, FINDER_TOP_LEFT),
+        (size - 7, 0, FINDER),
+        (0, size - 7, FINDER),
+        (size - 7, size - 7, FINDER_BOTTOM_RIGHT),
+    ];
+    for (x0, y0, rows) in finders {
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..7 {
+                grid[y0 + y][x0 + x] = Some(row & 0x40 >> x != 0);
+            }
+        }
+    }
+    // Separators, then the function information, around each corner.
+    for (edge, len) in [(7, 8), (8, 9)] {
+        for i in 0..len {
+            for (x, y) in [
+                (i, edge),
+                (edge, i),
+                (size - 1 - i, edge),
+                (size - 1 - edge, i),
+                (i, size - 1 - edge),
+                (edge, size - 1 - i),
+                (size - 1 - i, size - 1 - edge),
+                (size - 1 - edge, size - 1 - i),
+            ] {
+                grid[y][x] = Some(false);
+            }
+        }
+    }
+    if version < 4 {
+        return grid;
+    }
+    let (k, last) = (ALIGNMENT[version - 4], ALIGNMENT_LAST[version - 4] - 1);
+    let size = size as i64;
+    let mut plot = |x: i64, y: i64, dark: bool| {
+        if (0..size).contains(&x) && (0..size).contains(&y) {
+            let module = &mut grid[y as usize][x as usize];
+            module.get_or_insert(dark);
+        }
+    };
+    // Blocks are k modules wide from the top right corner on, and lines run
+    // along the top and right of every other one, with a light line inside.
+    let block = |i: i64| if i == 0 { k as i64 } else { last as i64 };
+    let mut line = |x: i64, y: i64, w: i64, h: i64| {
+        for i in 0..=w {
+            plot(x - i, y, true);
+            plot(x - i - 1, y + 1, false);
+        }
+        for i in 1..h {
+            plot(x, y + i, true);
+            plot(x - 1, y + i + 1, false);
+        }
+    };
+    let (mut y, mut row) = (0, 0);
+    while y < size {
+        let (mut x, mut column) = (size - 1, 0);
+        while x >= 0 {
+            if (row + column) % 2 == 0 && (x, y) != (size - 1, 0) {
+                line(x, y, block(column), block(row));
+            }
+            x -= block(column);
+            column += 1;
+        }
+        y += block(row);
+        row += 1;
+    }
+    // Small crosses where the lines would meet the edges.
+    let mut cross = |x: i64, y: i64| {
+        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+            plot(x + dx, y + dy, (dx, dy) == (0, 0));
+        }
+    };
+    let (mut y, mut row) = (0, 0);
+    while y < size {
+        match row % 2 {
+            0 => cross(0, y),
+            _ => cross(size - 1, y),
+        }
+        y += block(row);
+        row += 1;
+    }
+    let (mut x, mut column) = (size - 1, 0);
+    while x >= 0 {
+        match column % 2 {
+            0 => cross(x, size - 1),
+            _ => cross(x, 0),
+        }
+        x -= block(column);
+        column += 1;
+    }
+    grid
+}
+
+/// Whether the mask flips the module at row `i` and column `j`, both
+/// counted from 1.
+fn masked(mask: usize, i: usize, j: usize) -> bool {
+    match mask {
+        1 => (i + j).is_multiple_of(2),
+        2 => ((i + j) % 3 + j % 3).is_multiple_of(2),
+        3 => (i % j + j % i + i % 3 + j % 3).is_multiple_of(2),
+        _ => false,
+    }
+}
+
+/// Penalty of a symbol, as for qr codes: lines that look like a finder
+/// pattern and runs of modules of one colour.
+fn penalty(modules: &[Vec<bool>]) -> usize {
+    let size = modules.len();
+    let lines = (0..size).flat_map(|i| {
+        [
+            modules[i].clone(),
+            modules.iter().map(|row| row[i]).collect::<Vec<_>>(),
+        ]
+    });
+    let mut penalty = 0;
+    for line in lines {
+        let light = |range: &mut dyn Iterator<Item = usize>| {
+            range
+                .take(3)
+                .all(|i| !line.get(i).copied().unwrap_or(false))
+        };
+        for i in 0..size.saturating_sub(6) {
+            let finder = line[i..i + 7] == [true, false, true, false, true, true, true]
+                || line[i..i + 7] == [true, true, true, false, true, false, true];
+            if finder && (light(&mut (0..i).rev()) || light(&mut (i + 7..))) {
+                penalty += 50;
+            }
+        }
+        for run in line.chunk_by(|a, b| a == b) {
+            if run.len() >= 3 {
+                penalty += 4 * run.len();
+            }
+        }
+    }
+    penalty
+}
+
+/// Encode `text` into the grid of a symbol.
+pub fn encode(text: &str) -> Result<BitMatrix, String> {
+    let (version, codewords) = codewords(text)?;
+    let grid = function_patterns(version);
+    let size = grid.len();
+    let mut bits = codewords
+        .iter()
+        .flat_map(|cw| (0..8).rev().map(move |i| cw >> i & 1 == 1));
+    let data: Vec<Vec<Option<bool>>> = grid
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|module| match module {
+                    Some(_) => None,
+                    None => Some(bits.next().unwrap_or(false)),
+                })
+                .collect()
+        })
+        .collect();
+    let symbol = |mask: usize| -> Vec<Vec<bool>> {
+        (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| match (grid[y][x], data[y][x]) {
+                        (Some(dark), _) => dark,
+                        (None, bit) => bit.unwrap_or(false) ^ masked(mask, y + 1, x + 1),
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+    let mask = (0..4)
+        .min_by_key(|&mask| penalty(&symbol(mask)))
+        .unwrap_or(0);
+    let mut modules = symbol(mask);
+    // The version, level and mask, with their error correction in GF(16),
+    // and alternating modules to fill the rest.
+    let info = (version as u32 + 20) << 4 | (LEVEL - 1) << 2 | mask as u32;
+    let nibbles = [8, 4, 0].map(|shift| (info >> shift & 0xf) as u8);
+    let mut info: Vec<bool> = vec![];
+    let ec = error_correction(PredefinedGenericGF::AztecParam.into(), &nibbles, 4)?;
+    for nibble in nibbles.iter().chain(&ec) {
+        push(&mut info, *nibble as u32, 4);
+    }
+    info.extend((28..34).map(|i| i % 2 == 1));
+    let last = size - 1;
+    // Each corner has half of them, along its row and then its column or
+    // the other way round.
+    for i in 0..8 {
+        for (bit, (x, y)) in [
+            (i, (i, 8)),
+            (i + 17, (last - 8, i)),
+            (i + 17, (8, last - i)),
+            (i, (last - i, last - 8)),
+        ] {
+            modules[y][x] = info[bit];
+        }
+    }
+    for i in 0..9 {
+        for (bit, (x, y)) in [
+            (i + 8, (8, 8 - i)),
+            (i + 25, (last - 8 + i, 8)),
+            (i + 25, (8 - i, last - 8)),
+            (i + 8, (last - 8, last - 8 + i)),
+        ] {
+            modules[y][x] = info[bit];
+        }
+    }
+    let mut matrix = BitMatrix::with_single_dimension(size as u32).map_err(|e| e.to_string())?;
+    for (y, row) in modules.iter().enumerate() {
+        for (x, dark) in row.iter().enumerate() {
+            if *dark {
+                matrix.set(x as u32, y as u32);
+            }
+        }
+    }
+    Ok(matrix)
+}
@@ -0,0 +1,44 @@
+//! Encoding and decoding of qr codes, other two dimensional codes and
+//! barcodes, the logic of the nushell plugin `nu_plugin_qr`.
+//!
+//! - [encode] writes codes as png images, and barcodes also as svg.
+//! - [decode] finds and reads the codes of images, pdf documents and
+//!   animations.
+//! - [payloads] builds well-known payloads such as wifi credentials from
+//!   plain structs, and parses them into nushell values as `--parse` does.
+//! - [plugin] is the plugin itself, which the `nu_plugin_qr` binary serves.
+//!
+//! ```no_run
+//! let png = nu_plugin_qr::encode::matrix("qr", b"https://www.nushell.sh", 300)?;
+//! for found in nu_plugin_qr::decode::scan(&png, nu_plugin_qr::decode::Codes::Qr)? {
+//!     println!("{}: {}", found.symbology, String::from_utf8_lossy(&found.payload));
+//! }
+//! # Ok::<(), String>(())
+//! ```
+
+mod append;
+mod aztec;
+mod backend;
+mod barcode;
+mod charset;
+mod clipboard;
+mod codewords;
+mod databar;
+mod datamatrix;
+pub mod decode;
+mod defaults;
+pub mod encode;
+mod hanxin;
+mod linear;
+mod load;
+mod matrix;
+mod maxicode;
+mod micro;
+pub mod payloads;
+mod pdf417;
+pub mod plugin;
+mod postal;
+mod preprocess;
+mod qr_image;
+mod render;
+mod serialize;
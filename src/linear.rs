@@ -0,0 +1,198 @@
+//! One dimensional barcodes, as written by `to barcode`, read with rxing.
+
+use std::collections::HashSet;
+
+use image::GrayImage;
+use quircs::{Data, Point};
+use rxing::common::{BitMatrix, HybridBinarizer};
+use rxing::multi::{GenericMultipleBarcodeReader, MultipleBarcodeReader};
+use rxing::oned::MultiFormatOneDReader;
+use rxing::{
+    BarcodeFormat, BinaryBitmap, DecodeHints, Luma8LuminanceSource, RXingResult,
+    RXingResultMetadataType, RXingResultMetadataValue,
+};
+
+use crate::aztec;
+use crate::payloads::gs1;
+
+/// Symbologies that are looked for, and their names as `to barcode` calls them.
+pub const SYMBOLOGIES: [(BarcodeFormat, &str); 10] = [
+    (BarcodeFormat::CODE_128, "code128"),
+    (BarcodeFormat::CODE_39, "code39"),
+    (BarcodeFormat::CODABAR, "codabar"),
+    (BarcodeFormat::EAN_13, "ean13"),
+    (BarcodeFormat::EAN_8, "ean8"),
+    (BarcodeFormat::UPC_A, "upca"),
+    (BarcodeFormat::UPC_E, "upce"),
+    (BarcodeFormat::ITF, "itf"),
+    (BarcodeFormat::RSS_14, "databar"),
+    (BarcodeFormat::RSS_EXPANDED, "databarexpanded"),
+];
+
+/// Name, corners and data of a decoded barcode.
+pub type Barcode = (&'static str, [Point; 4], Data);
+
+/// Find and decode all barcodes of a grayscale image whose symbology is one
+/// of `names`.
+///
+/// Barcodes are read along a row of pixels, from the middle of the start
+/// pattern to the middle of the stop pattern, which are the left and right
+/// edges of the corners. Their top and bottom are where the bars end.
+pub fn barcodes(image: &GrayImage, names: &[&str]) -> Vec<Barcode> {
+    let formats: HashSet<_> = SYMBOLOGIES
+        .into_iter()
+        .filter(|(_, name)| names.contains(name))
+        .map(|(format, _)| format)
+        .collect();
+    if formats.is_empty() {
+        return vec![];
+    }
+    let Ok(source) = Luma8LuminanceSource::new(image.to_vec(), image.width(), image.height())
+    else {
+        return vec![];
+    };
+    let hints = DecodeHints {
+        PossibleFormats: Some(formats),
+        ..Default::default()
+    };
+    let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    let mut reader = GenericMultipleBarcodeReader::new(MultiFormatOneDReader::new(&hints));
+    let results = reader
+        .decode_multiple_with_hints(&mut bitmap, &hints)
+        .unwrap_or_default();
+    let bits = bitmap.get_black_matrix();
+    let mut v: Vec<Barcode> = Vec::new();
+    // DataBar may be read both ways, as rxing also tries each row reversed.
+    for (name, corners, data) in results.iter().filter_map(|result| barcode(result, bits)) {
+        let [a, _, c, _] = corners;
+        let center = Point {
+            x: (a.x + c.x) / 2,
+            y: (a.y + c.y) / 2,
+        };
+        if !v
+            .iter()
+            .any(|(_, other, d)| d.payload == data.payload && aztec::contains(other, center))
+        {
+            v.push((name, corners, data));
+        }
+    }
+    v
+}
+
+/// First and last row of the bars crossing the row `y` from `x0` to `x1`,
+/// as far as at most a tenth of the pixels differ from that row.
+fn rows(bits: &BitMatrix, y: u32, x0: u32, x1: u32) -> (u32, u32) {
+    let differs = |other: u32| {
+        (x0..=x1)
+            .filter(|&x| bits.get(x, y) != bits.get(x, other))
+            .count()
+    };
+    let same = |other: u32| differs(other) * 10 <= (x1 - x0 + 1) as usize;
+    let mut top = y;
+    while top > 0 && same(top - 1) {
+        top -= 1;
+    }
+    let mut bottom = y;
+    while bottom + 1 < bits.height() && same(bottom + 1) {
+        bottom += 1;
+    }
+    (top, bottom)
+}
+
+/// Last dark pixel of the row `y` going from `from` towards `to`, before a
+/// light gap more than `gap` times as wide as the narrowest bar.
+fn end_of_bars(bits: &BitMatrix, y: u32, from: u32, to: u32, gap: u32) -> u32 {
+    let xs: Box<dyn Iterator<Item = u32>> = match from <= to {
+        true => Box::new(from..=to),
+        false => Box::new((to..=from).rev()),
+    };
+    let (mut end, mut narrowest, mut run, mut dark) = (from, u32::MAX, 0, bits.get(from, y));
+    for x in xs {
+        let d = bits.get(x, y);
+        if d != dark {
+            match dark {
+                true => narrowest = narrowest.min(run),
+                false if run > narrowest.saturating_mul(gap) => break,
+                false => {}
+            }
+            (dark, run) = (d, 0);
+        }
+        run += 1;
+        if d {
+            end = x;
+        }
+    }
+    end
+}
+
+fn barcode(result: &RXingResult, bits: &BitMatrix) -> Option<Barcode> {
+    let (_, name) = SYMBOLOGIES
+        .iter()
+        .find(|(format, _)| format == result.getBarcodeFormat())?;
+    // DataBar has no start and stop patterns, rxing gives the edges of a
+    // finder pattern first, which are in the order it was read in. Those it
+    // gives for the other finder pattern are not reliable once reversed.
+    let (left, right, databar) = match result.getPoints() {
+        [left, right] => (left, right, false),
+        [left, right, _, _] => (left, right, true),
+        _ => return None,
+    };
+    let y = left.y as u32;
+    let [mut left, mut right] = [left, right].map(|p| (p.x.round() as u32).min(bits.width() - 1));
+    // rxing reports the end of a codabar barcode too far off, its bars end
+    // before the quiet zone.
+    if *name == "codabar" {
+        right = end_of_bars(bits, y, left, right, 5);
+    }
+    // Spaces of DataBar are up to 8 modules wide.
+    if databar {
+        let (first, last) = match left <= right {
+            true => (0, bits.width() - 1),
+            false => (bits.width() - 1, 0),
+        };
+        (left, right) = (
+            end_of_bars(bits, y, left, first, 9),
+            end_of_bars(bits, y, right, last, 9),
+        );
+    }
+    let (top, bottom) = rows(bits, y, left.min(right), left.max(right));
+    // A barcode read from right to left is upside down, its top is at the
+    // bottom of the image.
+    let (top, bottom) = match left <= right {
+        true => (top as i32, bottom as i32),
+        false => (bottom as i32, top as i32),
+    };
+    let [left, right] = [left as i32, right as i32];
+    let corner = |x: i32, y: i32| Point { x, y };
+    // The payload of a GS1-128 barcode starts with its symbology identifier,
+    // as for GS1 qr codes. DataBar is always GS1, rxing reads the GTIN of
+    // DataBar Omnidirectional without its AI, and the element string of
+    // DataBar Expanded in its human readable form.
+    let text = result.getText();
+    let payload = match *name {
+        "databar" => format!("]e001{}", text),
+        "databarexpanded" => format!("]e0{}", gs1::element_string(text).unwrap_or(text.into())),
+        _ => match result
+            .getRXingResultMetadata()
+            .get(&RXingResultMetadataType::SYMBOLOGY_IDENTIFIER)
+        {
+            Some(RXingResultMetadataValue::SymbologyIdentifier(id)) if id == "]C1" => {
+                format!("]C1{}", text)
+            }
+            _ => text.into(),
+        },
+    };
+    Some((
+        name,
+        [
+            corner(left, top),
+            corner(right, top),
+            corner(right, bottom),
+            corner(left, bottom),
+        ],
+        Data {
+            payload: payload.into_bytes(),
+            ..Default::default()
+        },
+    ))
+}
@@ -0,0 +1,333 @@
+//! Turning input bytes into grayscale images to search for codes.
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::Engine;
+use hayro::hayro_interpret::InterpreterSettings;
+use hayro::hayro_syntax::Pdf;
+use hayro::vello_cpu::color::palette::css::WHITE;
+use hayro::{PixmapSettings, RenderCache, RenderSettings};
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, GrayImage, ImageFormat};
+#[cfg(feature = "heic")]
+use libheif_rs::{ColorSpace, HeifContext, HeifError, LibHeif, RgbChroma};
+use nu_plugin::LabeledError;
+use nu_protocol::Span;
+use std::io::Cursor;
+use tiff::decoder::{Decoder as TiffDecoder, DecodingResult};
+use tiff::ColorType;
+
+use crate::preprocess;
+
+/// Pages are rendered at 216 dpi, enough for codes printed at a third of the
+/// usual module size of payment slips.
+const PDF_SCALE: f32 = 3.0;
+
+/// Longest side of a rendered page, so that posters do not exhaust memory.
+const MAX_PAGE_DIMENSION: f32 = 6000.0;
+
+/// An image to scan and where in the input it was taken from.
+pub struct Page {
+    /// `page` or `frame` and its 1-based number, `None` for inputs that are a
+    /// single image.
+    pub position: Option<(&'static str, usize)>,
+    pub image: GrayImage,
+}
+
+/// Image data given as text: a `data:` uri, or an image encoded as base64.
+///
+/// Returns `None` for any other text, like the path of a file.
+pub fn from_text(text: &str, span: Span) -> Option<Result<Vec<u8>, LabeledError>> {
+    if let Some(uri) = text.trim().strip_prefix("data:") {
+        let (media_type, data) = uri.split_once(',').unwrap_or((uri, ""));
+        if !media_type.ends_with(";base64") {
+            return Some(Err(LabeledError {
+                label: "Invalid data uri".into(),
+                msg: "only base64 encoded data can be decoded".into(),
+                span: Some(span),
+            }));
+        }
+        return Some(base64(data).ok_or_else(|| LabeledError {
+            label: "Invalid data uri".into(),
+            msg: "data is not valid base64".into(),
+            span: Some(span),
+        }));
+    }
+    // Most paths are valid base64 too, but they do not decode to an image.
+    base64(text).filter(|bytes| is_image(bytes)).map(Ok)
+}
+
+/// Decode standard or url safe base64, which may be wrapped over lines.
+fn base64(text: &str) -> Option<Vec<u8>> {
+    let text: String = text.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let text = text.trim_end_matches('=');
+    let padded = format!("{}{}", text, "=".repeat((4 - text.len() % 4) % 4));
+    STANDARD
+        .decode(&padded)
+        .or_else(|_| URL_SAFE.decode(&padded))
+        .ok()
+}
+
+/// Whether `bytes` starts like an input that [pages] can load.
+fn is_image(bytes: &[u8]) -> bool {
+    bytes.starts_with(b"%PDF-")
+        || image::guess_format(bytes).is_ok()
+        || brands(bytes).next().is_some()
+}
+
+/// Load `bytes` as pages: every page of a pdf or tiff, every frame of an
+/// animated gif, or the image itself.
+pub fn pages(bytes: &[u8], input_span: Option<Span>) -> Result<Vec<Page>, LabeledError> {
+    if bytes.starts_with(b"%PDF-") {
+        return pdf_pages(bytes, input_span);
+    }
+    if is_heic(bytes) {
+        return heic(bytes, input_span).map(|image| {
+            vec![Page {
+                position: None,
+                image,
+            }]
+        });
+    }
+    #[cfg(not(feature = "avif"))]
+    if is_avif(bytes) {
+        return Err(not_enabled("avif", input_span));
+    }
+    let guessed = image::guess_format(bytes);
+    let sequence = match guessed {
+        Ok(ImageFormat::Gif) => gif_frames(bytes),
+        Ok(ImageFormat::Tiff) => tiff_pages(bytes),
+        _ => None,
+    };
+    if let Some(pages) = sequence {
+        return Ok(pages);
+    }
+    let format = guessed
+        .map(|x| (x.extensions_str(), x.to_mime_type()))
+        .unwrap_or((&[], "unknown"));
+    match image::load_from_memory(bytes) {
+        Ok(image) => Ok(vec![Page {
+            position: None,
+            image: preprocess::orient(image.into_luma8(), preprocess::orientation(bytes)),
+        }]),
+        Err(e) => Err(LabeledError {
+            label: format!("Unable to open image: {}", e),
+            msg: format!("Input is guessed as {}", format_image(format.1, format.0)),
+            span: input_span,
+        }),
+    }
+}
+
+fn format_image(format: &str, extension: &[&str]) -> String {
+    match extension.len() {
+        0 => {
+            format!("{} (unknown extension)", format)
+        }
+        _ => {
+            format!("{} ({})", format, extension.join(", "))
+        }
+    }
+}
+
+/// Render every page of a pdf document on a white background.
+fn pdf_pages(bytes: &[u8], input_span: Option<Span>) -> Result<Vec<Page>, LabeledError> {
+    let pdf = Pdf::new(bytes.to_vec()).map_err(|e| LabeledError {
+        label: "Unable to open pdf".into(),
+        msg: match e {
+            hayro::hayro_syntax::LoadPdfError::Decryption(_) => {
+                "encrypted documents are not supported".into()
+            }
+            hayro::hayro_syntax::LoadPdfError::Invalid => "the document is malformed".into(),
+        },
+        span: input_span,
+    })?;
+    let cache = RenderCache::new();
+    Ok(pdf
+        .pages()
+        .iter()
+        .enumerate()
+        .map(|(i, page)| {
+            let (w, h) = page.render_dimensions();
+            let scale = PDF_SCALE.min(MAX_PAGE_DIMENSION / w.max(h).max(1.0));
+            let pixmap = hayro::render(
+                page,
+                &cache,
+                &InterpreterSettings::default(),
+                &RenderSettings::default(),
+                &PixmapSettings {
+                    x_scale: scale,
+                    y_scale: scale,
+                    bg_color: WHITE,
+                },
+            );
+            let (w, h) = (pixmap.width() as u32, pixmap.height() as u32);
+            // The background is opaque, so the premultiplied colors are the
+            // colors themselves.
+            let luma = pixmap
+                .data()
+                .iter()
+                .map(|p| rgb_luma(&[p.r, p.g, p.b]))
+                .collect();
+            Page {
+                position: Some(("page", i + 1)),
+                image: GrayImage::from_raw(w, h, luma).unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+/// Brands of an ISO base media file (the container of heic and avif images),
+/// the major brand first.
+fn brands(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let size = match bytes.get(4..8) {
+        Some(b"ftyp") => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+        _ => 0,
+    };
+    let major = bytes.get(8..12.min(size)).into_iter();
+    // The minor version at 12..16 separates the major from the compatible
+    // brands.
+    let compatible = bytes.get(16..size).unwrap_or_default().chunks_exact(4);
+    major.chain(compatible)
+}
+
+/// Whether `bytes` is a heif image coded with hevc, as taken by iPhones.
+fn is_heic(bytes: &[u8]) -> bool {
+    brands(bytes).any(|b| matches!(b, b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx"))
+}
+
+/// Decode the primary image of a heic file with libheif, which also applies
+/// its rotation and mirroring.
+#[cfg(feature = "heic")]
+fn heic(bytes: &[u8], input_span: Option<Span>) -> Result<GrayImage, LabeledError> {
+    let error = |e: HeifError| LabeledError {
+        label: "Unable to open heic image".into(),
+        msg: e.to_string(),
+        span: input_span,
+    };
+    let context = HeifContext::read_from_bytes(bytes).map_err(error)?;
+    let handle = context.primary_image_handle().map_err(error)?;
+    let image = LibHeif::new()
+        .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(error)?;
+    let Some(plane) = image.planes().interleaved else {
+        return Err(LabeledError {
+            label: "Unable to open heic image".into(),
+            msg: "libheif returned no pixels".into(),
+            span: input_span,
+        });
+    };
+    let (w, h) = (plane.width as usize, plane.height as usize);
+    let pixels = (0..h)
+        .flat_map(|y| plane.data[y * plane.stride..][..w * 3].chunks_exact(3))
+        .map(rgb_luma)
+        .collect();
+    Ok(GrayImage::from_raw(w as u32, h as u32, pixels).unwrap_or_default())
+}
+
+#[cfg(not(feature = "heic"))]
+fn heic(_: &[u8], input_span: Option<Span>) -> Result<GrayImage, LabeledError> {
+    Err(not_enabled("heic", input_span))
+}
+
+/// Whether `bytes` is an avif image, as browsers save screenshots.
+///
+/// With the `avif` feature, these are decoded by the image crate like any
+/// other format.
+#[cfg(not(feature = "avif"))]
+fn is_avif(bytes: &[u8]) -> bool {
+    brands(bytes).any(|b| matches!(b, b"avif" | b"avis"))
+}
+
+/// Error for a `format` whose decoder is behind the feature of the same name.
+#[cfg(not(all(feature = "heic", feature = "avif")))]
+fn not_enabled(format: &str, input_span: Option<Span>) -> LabeledError {
+    LabeledError {
+        label: format!("Unable to open {} image", format),
+        msg: format!(
+            "{0} support is not enabled, reinstall with `cargo install nu_plugin_qr --features {0}`",
+            format
+        ),
+        span: input_span,
+    }
+}
+
+/// Every frame of an animated gif, composed onto the ones before it and a
+/// white background.
+///
+/// Returns `None` for still images, which are loaded like any other.
+fn gif_frames(bytes: &[u8]) -> Option<Vec<Page>> {
+    let frames = GifDecoder::new(Cursor::new(bytes))
+        .ok()?
+        .into_frames()
+        .collect_frames()
+        .ok()?;
+    (frames.len() > 1).then(|| {
+        frames
+            .into_iter()
+            .enumerate()
+            .map(|(i, frame)| Page {
+                position: Some(("frame", i + 1)),
+                image: {
+                    let frame = frame.into_buffer();
+                    let pixels = frame
+                        .pixels()
+                        .map(|p| 255 - ((255 - rgb_luma(&p.0) as u32) * p.0[3] as u32 / 255) as u8)
+                        .collect();
+                    GrayImage::from_raw(frame.width(), frame.height(), pixels).unwrap_or_default()
+                },
+            })
+            .collect()
+    })
+}
+
+/// Every page of a multi-page tiff.
+///
+/// Returns `None` for single pages, which are loaded like any other image.
+/// Pages in a layout other than 8 or 16 bit gray, rgb or cmyk samples are
+/// skipped.
+fn tiff_pages(bytes: &[u8]) -> Option<Vec<Page>> {
+    let mut decoder = TiffDecoder::new(Cursor::new(bytes)).ok()?;
+    if !decoder.more_images() {
+        return None;
+    }
+    let mut pages = Vec::new();
+    for number in 1.. {
+        if let Some(image) = tiff_page(&mut decoder) {
+            pages.push(Page {
+                position: Some(("page", number)),
+                image,
+            });
+        }
+        if !decoder.more_images() || decoder.next_image().is_err() {
+            break;
+        }
+    }
+    Some(pages)
+}
+
+/// Read the current page of `decoder` as a grayscale image.
+fn tiff_page(decoder: &mut TiffDecoder<Cursor<&[u8]>>) -> Option<GrayImage> {
+    let (w, h) = decoder.dimensions().ok()?;
+    let (channels, luma): (usize, fn(&[u8]) -> u8) = match decoder.colortype().ok()? {
+        ColorType::Gray(8 | 16) => (1, |p| p[0]),
+        ColorType::GrayA(8 | 16) => (2, |p| p[0]),
+        ColorType::RGB(8 | 16) => (3, rgb_luma),
+        ColorType::RGBA(8 | 16) => (4, rgb_luma),
+        ColorType::CMYK(8 | 16) => (4, |p| {
+            let ink = p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114;
+            ((255 * 1000 - ink) * (255 - p[3] as u32) / 255 / 1000) as u8
+        }),
+        _ => return None,
+    };
+    let samples = match decoder.read_image().ok()? {
+        DecodingResult::U8(samples) => samples,
+        DecodingResult::U16(samples) => samples.into_iter().map(|s| (s >> 8) as u8).collect(),
+        _ => return None,
+    };
+    let pixels = samples.chunks_exact(channels).map(luma).collect();
+    GrayImage::from_raw(w, h, pixels)
+}
+
+/// Luma of the first three samples of `p` as red, green and blue.
+fn rgb_luma(p: &[u8]) -> u8 {
+    ((p[0] as u32 * 299 + p[1] as u32 * 587 + p[2] as u32 * 114) / 1000) as u8
+}
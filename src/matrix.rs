@@ -0,0 +1,197 @@
+//! Two dimensional codes other than qr for `to qr --symbology`, encoded
+//! with rxing but for maxicode and han xin.
+
+use std::io::Cursor;
+
+use image::{GrayImage, ImageOutputFormat, Luma};
+use nu_plugin::{EvaluatedCall, LabeledError};
+use rxing::aztec::AztecWriter;
+use rxing::common::BitMatrix;
+use rxing::datamatrix::DataMatrixWriter;
+use rxing::{BarcodeFormat, EncodeHints, Writer};
+
+use crate::hanxin;
+use crate::maxicode::{self, Carrier};
+
+/// Light margin around the symbol, in modules; data matrix needs one and
+/// aztec and maxicode none. Han Xin needs three, see `Symbology::quiet_zone`.
+const QUIET_ZONE: u32 = 2;
+
+/// Two dimensional symbologies of `to qr`.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Symbology {
+    Qr,
+    DataMatrix,
+    Aztec,
+    MaxiCode,
+    HanXin,
+}
+
+impl Symbology {
+    /// The symbology called `name`, ignoring case, dashes and spaces.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().replace(['-', ' '], "").as_str() {
+            "qr" => Ok(Symbology::Qr),
+            "datamatrix" => Ok(Symbology::DataMatrix),
+            "aztec" => Ok(Symbology::Aztec),
+            "maxicode" => Ok(Symbology::MaxiCode),
+            "hanxin" => Ok(Symbology::HanXin),
+            _ => Err(format!(
+                "{} is not one of: qr, datamatrix, aztec, maxicode, hanxin",
+                name
+            )),
+        }
+    }
+
+    /// Read `--symbology`, which only `to qr` has.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let symbology = match call.get_flag::<String>("symbology")? {
+            Some(name) => Symbology::from_name(&name).map_err(|msg| LabeledError {
+                label: "Unknown symbology".into(),
+                msg,
+                span: Some(call.head),
+            })?,
+            None => Symbology::Qr,
+        };
+        let invalid = |msg: &str| LabeledError {
+            label: "Invalid flag".into(),
+            msg: msg.into(),
+            span: Some(call.head),
+        };
+        let carrier = ["postal-code", "country", "service-class"].map(|flag| call.has_flag(flag));
+        if symbology != Symbology::MaxiCode && carrier.contains(&true) {
+            return Err(invalid(
+                "--postal-code, --country and --service-class need --symbology maxicode",
+            ));
+        }
+        if carrier.contains(&true) && carrier.contains(&false) {
+            return Err(invalid(
+                "a structured carrier message needs all of --postal-code, --country and --service-class",
+            ));
+        }
+        match symbology {
+            Symbology::Qr | Symbology::Aztec | Symbology::MaxiCode | Symbology::HanXin
+                if call.has_flag("gs1") =>
+            {
+                Err(invalid("--gs1 needs --symbology datamatrix"))
+            }
+            Symbology::Qr => Ok(symbology),
+            _ if call.has_flag("shape") => Err(invalid("--shape only applies to qr codes")),
+            _ => Ok(symbology),
+        }
+    }
+
+    pub fn encode(
+        &self,
+        text: &str,
+        gs1: bool,
+        carrier: Option<&Carrier>,
+    ) -> Result<BitMatrix, String> {
+        // Latin-1 is the default, other text is marked as UTF-8 rather than
+        // whatever encoding is shortest.
+        let charset = text
+            .chars()
+            .any(|c| c as u32 > 0xff)
+            .then(|| "UTF-8".to_string());
+        match self {
+            Symbology::Qr => unreachable!("qr codes are rendered by fast_qr"),
+            // The minimal encoder is the one writing FNC1 for group separators.
+            Symbology::DataMatrix => DataMatrixWriter
+                .encode_with_hints(
+                    text,
+                    &BarcodeFormat::DATA_MATRIX,
+                    0,
+                    0,
+                    &EncodeHints {
+                        DataMatrixCompact: Some(true),
+                        Gs1Format: Some(gs1),
+                        CharacterSet: charset,
+                        ..Default::default()
+                    },
+                )
+                .map_err(|e| e.to_string()),
+            Symbology::Aztec => AztecWriter
+                .encode_with_hints(
+                    text,
+                    &BarcodeFormat::AZTEC,
+                    0,
+                    0,
+                    &EncodeHints {
+                        CharacterSet: charset,
+                        ..Default::default()
+                    },
+                )
+                .map_err(|e| e.to_string()),
+            Symbology::MaxiCode => maxicode::encode(text, carrier),
+            Symbology::HanXin => hanxin::encode(text),
+        }
+    }
+
+    /// Name of the symbology, as `--symbology` takes it.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Symbology::Qr => "qr",
+            Symbology::DataMatrix => "datamatrix",
+            Symbology::Aztec => "aztec",
+            Symbology::MaxiCode => "maxicode",
+            Symbology::HanXin => "hanxin",
+        }
+    }
+
+    /// Light margin around the symbol, in modules.
+    fn quiet_zone(&self) -> u32 {
+        match self {
+            Symbology::HanXin => 3,
+            _ => QUIET_ZONE,
+        }
+    }
+
+    /// Width and height of `matrix` with its quiet zone, in modules.
+    pub fn size(&self, matrix: &BitMatrix) -> (f64, f64) {
+        match self {
+            Symbology::MaxiCode => maxicode::size(QUIET_ZONE as f64),
+            _ => {
+                let quiet_zone = self.quiet_zone();
+                (
+                    (matrix.width() + 2 * quiet_zone) as f64,
+                    (matrix.height() + 2 * quiet_zone) as f64,
+                )
+            }
+        }
+    }
+
+    /// Draw `matrix` with `pixels` per module, whole pixels per square module.
+    pub fn image(&self, matrix: &BitMatrix, pixels: f64) -> GrayImage {
+        match self {
+            // Hexagons are drawn at any size, rather than whole pixels per module.
+            Symbology::MaxiCode => maxicode::image(matrix, pixels, QUIET_ZONE as f64),
+            _ => {
+                let quiet_zone = self.quiet_zone();
+                let (width, height) = (
+                    matrix.width() + 2 * quiet_zone,
+                    matrix.height() + 2 * quiet_zone,
+                );
+                let scale = (pixels as u32).max(1);
+                GrayImage::from_fn(width * scale, height * scale, |x, y| {
+                    let (x, y) = (
+                        (x / scale).wrapping_sub(quiet_zone),
+                        (y / scale).wrapping_sub(quiet_zone),
+                    );
+                    match matrix.try_get(x, y) {
+                        Some(true) => Luma([0]),
+                        _ => Luma([255]),
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Encode `image` as png.
+pub fn png(image: &GrayImage) -> Result<Vec<u8>, String> {
+    let mut buf = vec![];
+    image
+        .write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(buf)
+}
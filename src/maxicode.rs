@@ -0,0 +1,291 @@
+//! MaxiCode, the hexagonal code of parcel labels, encoded here as rxing only
+//! reads it.
+//!
+//! A symbol is 33 rows of 30 modules, odd rows shifted right by half a
+//! module, around a bullseye. It holds 144 codewords of 6 bits: a primary
+//! message of 10 codewords and 10 of error correction, and a secondary one of
+//! 84 and 40. Modes 2 and 3 put the structured carrier message of a postal
+//! code, country and service class in the primary message and text in the
+//! secondary one; mode 4 is text only.
+
+use std::sync::OnceLock;
+
+use image::{GrayImage, Luma};
+use rxing::common::reedsolomon::{PredefinedGenericGF, ReedSolomonEncoder};
+use rxing::common::BitMatrix;
+use rxing::maxicode::decoder::BitMatrixParser;
+
+const WIDTH: u32 = 30;
+const HEIGHT: u32 = 33;
+
+/// Codewords that are not characters in the code sets below.
+const NONE: char = '\u{ffff}';
+
+/// Code sets A to E by codeword, up to their shifts and latches.
+const SETS: [&str; 5] = [
+    "\rABCDEFGHIJKLMNOPQRSTUVWXYZ\u{ffff}\x1c\x1d\x1e\u{ffff} \u{ffff}\"#$%&'()*+,-./0123456789:",
+    "`abcdefghijklmnopqrstuvwxyz\u{ffff}\x1c\x1d\x1e\u{ffff}{\u{ffff}}~\x7f;<=>?[\\]^_ ,./:@!|",
+    "ÀÁÂÃÄÅÆÇÈÉÊËÌÍÎÏÐÑÒÓÔÕÖ×ØÙÚ\u{ffff}\x1c\x1d\x1e\u{ffff}ÛÜÝÞßª¬±²³µ¹º¼½¾\u{80}\u{81}\u{82}\u{83}\u{84}\u{85}\u{86}\u{87}\u{88}\u{89}",
+    "àáâãäåæçèéêëìíîïðñòóôõö÷øùú\u{ffff}\x1c\x1d\x1e\u{ffff}ûüýþÿ¡¨«¯°´·¸»¿\u{8a}\u{8b}\u{8c}\u{8d}\u{8e}\u{8f}\u{90}\u{91}\u{92}\u{93}\u{94}",
+    "\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\n\x0b\x0c\r\x0e\x0f\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x1a\u{ffff}\u{ffff}\u{ffff}\x1b\u{ffff}\x1c\x1d\x1e\x1f\u{9f}\u{a0}¢£¤¥¦§©\u{ad}®¶\u{95}\u{96}\u{97}\u{98}\u{99}\u{9a}\u{9b}\u{9c}\u{9d}\u{9e}",
+];
+
+/// Codeword filling the message after the text, in sets A and B.
+const PAD: u8 = 33;
+/// Shift to the other of sets A and B for a character, in both.
+const SHIFT: u8 = 59;
+/// Latch to the other of sets A and B, in both.
+const LATCH: u8 = 63;
+
+/// Modules that are always dark: the orientation pattern around the bullseye
+/// and the top right corner, as `(x, y)`.
+const DARK: [(u32, u32); 13] = [
+    (28, 0),
+    (29, 0),
+    (10, 9),
+    (11, 9),
+    (11, 10),
+    (7, 15),
+    (8, 16),
+    (20, 16),
+    (20, 17),
+    (10, 22),
+    (10, 23),
+    (17, 22),
+    (17, 23),
+];
+
+/// Radii of the edges of the three dark rings of the bullseye, in module
+/// widths from its center, outermost first.
+const RINGS: [f64; 6] = [4.4, 3.63, 2.86, 2.09, 1.32, 0.55];
+
+/// The postal code, ISO 3166 country and service class of a parcel.
+#[derive(Clone, Debug)]
+pub struct Carrier {
+    pub postal_code: String,
+    pub country: u32,
+    pub service_class: u32,
+}
+
+/// Codeword of `c` in code set `set`.
+fn codeword(set: usize, c: char) -> Option<u8> {
+    match c {
+        NONE => None,
+        c => SETS[set].chars().position(|s| s == c).map(|i| i as u8),
+    }
+}
+
+/// Encode `text` in code sets, starting in set A.
+///
+/// Runs of set A or B latch to it, other characters of them are shifted,
+/// and those of sets C to E are shifted to from either.
+fn message(text: &str) -> Result<Vec<u8>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = vec![];
+    let mut set = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if let Some(cw) = codeword(set, *c) {
+            out.push(cw);
+        } else if let Some(cw) = codeword(1 - set, *c) {
+            match chars.get(i + 1).and_then(|next| codeword(1 - set, *next)) {
+                Some(_) => {
+                    out.push(LATCH);
+                    set = 1 - set;
+                }
+                None => out.push(SHIFT),
+            }
+            out.push(cw);
+        } else if let Some((shifted, cw)) = (2..5).find_map(|s| Some((s, codeword(s, *c)?))) {
+            // Shift C to E are codewords 60 to 62 of both sets A and B.
+            out.push(58 + shifted as u8);
+            out.push(cw);
+        } else {
+            return Err(format!("{:?} cannot be encoded in maxicode", c));
+        }
+    }
+    Ok(out)
+}
+
+/// Mode and bits of the primary message of a structured carrier message,
+/// the lowest 6 bits in the first codeword.
+///
+/// Mode 2 has a numeric postal code of up to 9 digits and its length, mode 3
+/// up to 6 characters of code set A.
+fn primary(carrier: &Carrier) -> Result<(u64, u64), String> {
+    if carrier.country > 999 || carrier.service_class > 999 {
+        return Err("the country and service class should have at most 3 digits".into());
+    }
+    let code = carrier.postal_code.trim().to_uppercase();
+    let (mode, postal) =
+        if !code.is_empty() && code.len() <= 9 && code.chars().all(|c| c.is_ascii_digit()) {
+            let value: u64 = code.parse().map_err(|_| "invalid postal code")?;
+            (2, value | (code.len() as u64) << 30)
+        } else if code.chars().count() <= 6 {
+            let padded = format!("{:<6}", code);
+            let mut value = 0;
+            for c in padded.chars() {
+                let cw = codeword(0, c)
+                    .filter(|cw| *cw < 59)
+                    .ok_or_else(|| format!("{:?} cannot be in a postal code", c))?;
+                value = value << 6 | cw as u64;
+            }
+            (3, value)
+        } else {
+            return Err("postal codes are up to 9 digits or 6 letters and digits".into());
+        };
+    Ok((
+        mode,
+        mode | postal << 4 | (carrier.country as u64) << 40 | (carrier.service_class as u64) << 50,
+    ))
+}
+
+/// Reed-Solomon error correction codewords of `data`.
+fn error_correction(data: &[u8], ec: usize) -> Result<Vec<u8>, String> {
+    let mut codewords: Vec<i32> = data.iter().map(|cw| *cw as i32).collect();
+    codewords.resize(data.len() + ec, 0);
+    ReedSolomonEncoder::new(PredefinedGenericGF::MaxicodeField64.into())
+        .and_then(|mut encoder| encoder.encode(&mut codewords, ec))
+        .map_err(|e| e.to_string())?;
+    Ok(codewords[data.len()..].iter().map(|cw| *cw as u8).collect())
+}
+
+/// The 144 codewords of a symbol.
+fn codewords(text: &str, carrier: Option<&Carrier>) -> Result<Vec<u8>, String> {
+    let mut data = [PAD; 94];
+    let start = match carrier {
+        Some(carrier) => {
+            let (_, bits) = primary(carrier)?;
+            for (k, cw) in data[..10].iter_mut().enumerate() {
+                *cw = (bits >> (6 * k) & 0x3f) as u8;
+            }
+            10
+        }
+        None => {
+            data[0] = 4;
+            1
+        }
+    };
+    let message = message(text)?;
+    if message.len() > data.len() - start {
+        return Err(format!(
+            "text too long, it takes {} codewords and there is room for {}",
+            message.len(),
+            data.len() - start
+        ));
+    }
+    data[start..start + message.len()].copy_from_slice(&message);
+    let mut out = data[..10].to_vec();
+    out.extend(error_correction(&data[..10], 10)?);
+    out.extend(&data[10..]);
+    // The secondary message is corrected in two halves, interleaved.
+    let mut ec = vec![0; 40];
+    for half in 0..2 {
+        let data: Vec<u8> = data[10..].iter().skip(half).step_by(2).copied().collect();
+        for (k, cw) in error_correction(&data, 20)?.into_iter().enumerate() {
+            ec[2 * k + half] = cw;
+        }
+    }
+    out.extend(ec);
+    Ok(out)
+}
+
+/// Codeword and bit of a module, by row and column.
+type Layout = Vec<Vec<Option<(usize, u8)>>>;
+
+/// Codeword and bit of every module that holds one.
+///
+/// rxing keeps its table of them to itself, so it is read back from grids
+/// with one dark module each.
+fn layout() -> &'static Layout {
+    static LAYOUT: OnceLock<Layout> = OnceLock::new();
+    LAYOUT.get_or_init(|| {
+        (0..HEIGHT)
+            .map(|y| {
+                (0..WIDTH)
+                    .map(|x| {
+                        let mut grid = BitMatrix::new(WIDTH, HEIGHT).ok()?;
+                        grid.set(x, y);
+                        let codewords = BitMatrixParser::new(&grid).readCodewords();
+                        let (i, bit) = codewords.iter().enumerate().find(|(_, cw)| **cw != 0)?;
+                        Some((i, *bit))
+                    })
+                    .collect()
+            })
+            .collect()
+    })
+}
+
+/// Encode `text` into the grid of a symbol, with the structured carrier
+/// message of `carrier` if given.
+pub fn encode(text: &str, carrier: Option<&Carrier>) -> Result<BitMatrix, String> {
+    let codewords = codewords(text, carrier)?;
+    let mut grid = BitMatrix::new(WIDTH, HEIGHT).map_err(|e| e.to_string())?;
+    for (y, row) in layout().iter().enumerate() {
+        for (x, module) in row.iter().enumerate() {
+            if let Some((i, bit)) = module {
+                if codewords[*i] & bit != 0 {
+                    grid.set(x as u32, y as u32);
+                }
+            }
+        }
+    }
+    for (x, y) in DARK {
+        grid.set(x, y);
+    }
+    Ok(grid)
+}
+
+/// Width and height of a symbol with a quiet zone of `quiet_zone` modules,
+/// in module widths.
+pub fn size(quiet_zone: f64) -> (f64, f64) {
+    (
+        WIDTH as f64 + 0.5 + 2.0 * quiet_zone,
+        (HEIGHT - 1) as f64 * 3f64.sqrt() / 2.0 + 2.0 / 3f64.sqrt() + 2.0 * quiet_zone,
+    )
+}
+
+/// Render the grid of a symbol with hexagons `module` pixels wide, and a
+/// quiet zone of `quiet_zone` modules.
+pub fn image(grid: &BitMatrix, module: f64, quiet_zone: f64) -> GrayImage {
+    let row = module * 3f64.sqrt() / 2.0;
+    // Distance of the pointy top and bottom of a hexagon from its center.
+    let apex = module / 3f64.sqrt();
+    let margin = quiet_zone * module;
+    let (width, height) = size(quiet_zone);
+    let (width, height) = (width * module, height * module);
+    let center = |x: f64, y: u32| {
+        (
+            margin + (x + 0.5 + 0.5 * (y % 2) as f64) * module,
+            margin + apex + y as f64 * row,
+        )
+    };
+    let (bx, by) = center(14.0, 16);
+    GrayImage::from_fn(width.ceil() as u32, height.ceil() as u32, |px, py| {
+        let (px, py) = (px as f64 + 0.5, py as f64 + 0.5);
+        // Hexagon rows overlap, so the module is in the row above or below.
+        let y0 = ((py - margin - apex) / row).floor();
+        let dark = [y0, y0 + 1.0].iter().any(|y| {
+            if *y < 0.0 || *y >= HEIGHT as f64 {
+                return false;
+            }
+            let y = *y as u32;
+            let x = ((px - margin) / module - 0.5 * (y % 2) as f64).floor();
+            if x < 0.0 || x >= WIDTH as f64 || !grid.get(x as u32, y) {
+                return false;
+            }
+            let (cx, cy) = center(x, y);
+            // Slightly smaller than the grid, so that the hexagons stand out.
+            let half = 0.45 * module;
+            let (dx, dy) = ((px - cx).abs(), (py - cy).abs());
+            dx <= half && dx / 2.0 + dy * 3f64.sqrt() / 2.0 <= half
+        });
+        let distance = ((px - bx).powi(2) + (py - by).powi(2)).sqrt() / module;
+        let ring = RINGS
+            .chunks(2)
+            .any(|edges| distance <= edges[0] && distance > edges[1]);
+        match dark || ring {
+            true => Luma([0]),
+            false => Luma([255]),
+        }
+    })
+}
@@ -0,0 +1,85 @@
+//! Micro qr (MQR) and rectangular micro qr (rMQR) codes, which quirc does not
+//! know about.
+
+use image::GrayImage;
+use quircs::{Data, EccLevel, Point};
+use rxing::common::cpp_essentials::ConcentricPattern;
+use rxing::common::{BitMatrix, DetectorRXingResult, HybridBinarizer, Result};
+use rxing::qrcode::cpp_port::decoder::Decode;
+use rxing::qrcode::cpp_port::detector::{FindFinderPatterns, SampleMQR, SampleRMQR};
+use rxing::qrcode::detector::QRCodeDetectorResult;
+use rxing::{BinaryBitmap, Luma8LuminanceSource};
+
+/// Corners, width and height in modules, and data of a decoded symbol.
+pub type Symbol = ([Point; 4], (u32, u32), Data);
+
+/// Find and decode all rMQR symbols of a grayscale image.
+pub fn rmqr(image: &GrayImage) -> Vec<Symbol> {
+    detect(image, SampleRMQR)
+}
+
+/// Find and decode all MQR symbols of a grayscale image.
+pub fn mqr(image: &GrayImage) -> Vec<Symbol> {
+    detect(image, SampleMQR)
+}
+
+/// Find and decode all symbols of a grayscale image that `sample` recognizes.
+///
+/// Every finder pattern is tried as the corner of a symbol, so candidates
+/// that turn out not to be one are silently skipped.
+fn detect(
+    image: &GrayImage,
+    sample: fn(&BitMatrix, ConcentricPattern) -> Result<QRCodeDetectorResult>,
+) -> Vec<Symbol> {
+    let source = match Luma8LuminanceSource::new(image.to_vec(), image.width(), image.height()) {
+        Ok(source) => source,
+        Err(_) => return vec![],
+    };
+    let bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    let bits = bitmap.get_black_matrix();
+    let mut v = Vec::new();
+    for fp in FindFinderPatterns(bits, true, 0) {
+        let Ok(detected) = sample(bits, fp) else {
+            continue;
+        };
+        let Ok(result) = Decode(detected.getBits()) else {
+            continue;
+        };
+        let points = detected.getPoints();
+        if !result.isValid() || points.len() < 4 {
+            continue;
+        }
+        // The payload of a GS1 code starts with its symbology identifier, as
+        // for full size qr codes.
+        let mut payload = match result.symbologyIdentifier() == "]Q3" {
+            true => b"]Q3".to_vec(),
+            false => vec![],
+        };
+        // Text with an ECI has already been converted to utf-8.
+        match result.content().has_eci {
+            true => payload.extend(result.text().into_bytes()),
+            false => payload.extend(result.content().bytes()),
+        }
+        // rxing orders the corners as rows, quirc clockwise.
+        let corners = [0, 1, 3, 2].map(|i| Point {
+            x: points[i].x.round() as i32,
+            y: points[i].y.round() as i32,
+        });
+        v.push((
+            corners,
+            (detected.getBits().width(), detected.getBits().height()),
+            Data {
+                version: result.versionNumber() as usize,
+                ecc_level: match result.ecLevel() {
+                    "L" => EccLevel::L,
+                    "Q" => EccLevel::Q,
+                    "H" => EccLevel::H,
+                    _ => EccLevel::M,
+                },
+                payload,
+                ..Default::default()
+            },
+        ));
+    }
+    v
+}
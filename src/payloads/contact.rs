@@ -0,0 +1,270 @@
+//! Contact cards built from a record with the columns
+//! `name`, `org`, `phone`, `email`, `address` and `url`.
+//!
+//! `phone`, `email` and `url` may be lists. `address` is either a single line
+//! or a record with `street`, `city`, `region`, `postal` and `country`.
+//!
+//! MeCards are parsed back into a record with those columns.
+
+use nu_protocol::{Span, Value};
+
+use super::{
+    backslash_escape, backslash_unescape, split_escaped, strip_prefix_ignore_case, text_escape,
+};
+
+/// A postal address, the `address` column of a contact.
+#[derive(Clone, Debug, Default)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub region: String,
+    pub postal: String,
+    pub country: String,
+}
+
+/// A contact, the record given to `to qr vcard` and `to qr mecard`.
+#[derive(Clone, Debug, Default)]
+pub struct Contact {
+    /// Display name, with the family name last.
+    pub name: String,
+    pub org: Option<String>,
+    pub phones: Vec<String>,
+    pub emails: Vec<String>,
+    pub address: Option<Address>,
+    pub urls: Vec<String>,
+}
+
+impl Contact {
+    /// Split the display name into (family, given) names.
+    fn family_given(&self) -> (&str, &str) {
+        match self.name.trim().rsplit_once(' ') {
+            Some((given, family)) => (family, given.trim()),
+            None => (self.name.trim(), ""),
+        }
+    }
+
+    /// Serialize as a vCard 3.0.
+    pub fn vcard(&self) -> String {
+        let (family, given) = self.family_given();
+        let mut lines = vec![
+            "BEGIN:VCARD".to_string(),
+            "VERSION:3.0".to_string(),
+            format!("N:{};{};;;", text_escape(family), text_escape(given)),
+            format!("FN:{}", text_escape(&self.name)),
+        ];
+        if let Some(org) = &self.org {
+            lines.push(format!("ORG:{}", text_escape(org)));
+        }
+        for phone in &self.phones {
+            lines.push(format!("TEL:{}", text_escape(phone)));
+        }
+        for email in &self.emails {
+            lines.push(format!("EMAIL:{}", text_escape(email)));
+        }
+        if let Some(adr) = &self.address {
+            lines.push(format!(
+                "ADR:;;{};{};{};{};{}",
+                text_escape(&adr.street),
+                text_escape(&adr.city),
+                text_escape(&adr.region),
+                text_escape(&adr.postal),
+                text_escape(&adr.country)
+            ));
+        }
+        for url in &self.urls {
+            lines.push(format!("URL:{}", text_escape(url)));
+        }
+        lines.push("END:VCARD".to_string());
+        lines.join("\r\n")
+    }
+
+    /// Serialize as a MeCard, the compact format preferred by many Android scanners.
+    pub fn mecard(&self) -> String {
+        let escape = |s: &str| backslash_escape(s, &['\\', ';', ':']);
+        // the comma separates family and given names, so it is only reserved in `N`
+        let escape_name = |s: &str| backslash_escape(s, &['\\', ';', ':', ',']);
+        let (family, given) = self.family_given();
+        let mut out = if given.is_empty() {
+            format!("MECARD:N:{};", escape_name(family))
+        } else {
+            format!("MECARD:N:{},{};", escape_name(family), escape_name(given))
+        };
+        if let Some(org) = &self.org {
+            out.push_str(&format!("ORG:{};", escape(org)));
+        }
+        for phone in &self.phones {
+            out.push_str(&format!("TEL:{};", escape(phone)));
+        }
+        for email in &self.emails {
+            out.push_str(&format!("EMAIL:{};", escape(email)));
+        }
+        if let Some(adr) = &self.address {
+            let parts = [
+                &adr.street,
+                &adr.city,
+                &adr.region,
+                &adr.postal,
+                &adr.country,
+            ];
+            let line = parts
+                .iter()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            out.push_str(&format!("ADR:{};", escape(&line)));
+        }
+        for url in &self.urls {
+            out.push_str(&format!("URL:{};", escape(url)));
+        }
+        out.push(';');
+        out
+    }
+}
+
+/// Read a MeCard into a record with the columns `to qr mecard` takes, along
+/// with the `reading` (the phonetic `SOUND` of Japanese names), `birthday`
+/// and `note`.
+///
+/// Fields that may appear more than once are lists.
+pub fn parse_mecard(payload: &str, span: Span) -> Option<Value> {
+    let mut fields: Vec<(String, &str)> = vec![];
+    for field in split_escaped(strip_prefix_ignore_case(payload, "MECARD:")?, ';') {
+        if let Some((key, value)) = field.split_once(':') {
+            fields.push((key.to_uppercase(), value));
+        }
+    }
+    let all = |key: &str| -> Vec<String> {
+        fields
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| backslash_unescape(v))
+            .collect()
+    };
+    let first = |key: &str| match all(key).into_iter().next() {
+        Some(val) => Value::String { val, span },
+        None => Value::Nothing { span },
+    };
+    let list = |key: &str| Value::List {
+        vals: all(key)
+            .into_iter()
+            .map(|val| Value::String { val, span })
+            .collect(),
+        span,
+    };
+    // `N:family,given`, the reverse of how `to qr mecard` splits the name.
+    let (_, n) = fields.iter().find(|(k, _)| k == "N")?;
+    let name = match split_escaped(n, ',')[..] {
+        [family, given, ..] if !given.is_empty() => format!(
+            "{} {}",
+            backslash_unescape(given),
+            backslash_unescape(family)
+        ),
+        [family, ..] => backslash_unescape(family),
+        [] => String::new(),
+    };
+    Some(Value::Record {
+        cols: [
+            "name", "reading", "org", "phone", "email", "address", "url", "birthday", "note",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            Value::String { val: name, span },
+            first("SOUND"),
+            first("ORG"),
+            list("TEL"),
+            list("EMAIL"),
+            first("ADR"),
+            list("URL"),
+            first("BDAY"),
+            first("NOTE"),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contact() -> Contact {
+        Contact {
+            name: r"Jane Do;e,x\:y".into(),
+            org: Some(r"A;B,C\D:E".into()),
+            phones: vec!["+1 555 0100".into(), "0100".into()],
+            emails: vec!["jane@example.com".into()],
+            address: Some(Address {
+                street: "1 Main St; Apt 2".into(),
+                city: "Springfield".into(),
+                ..Default::default()
+            }),
+            urls: vec!["https://example.com/a;b?c=d:e".into()],
+        }
+    }
+
+    #[test]
+    fn vcard() {
+        assert_eq!(
+            contact().vcard(),
+            [
+                "BEGIN:VCARD",
+                "VERSION:3.0",
+                r"N:Do\;e\,x\\:y;Jane;;;",
+                r"FN:Jane Do\;e\,x\\:y",
+                r"ORG:A\;B\,C\\D:E",
+                "TEL:+1 555 0100",
+                "TEL:0100",
+                "EMAIL:jane@example.com",
+                r"ADR:;;1 Main St\; Apt 2;Springfield;;;",
+                r"URL:https://example.com/a\;b?c=d:e",
+                "END:VCARD",
+            ]
+            .join("\r\n")
+        );
+        let single = Contact {
+            name: "Cher".into(),
+            ..Default::default()
+        };
+        assert!(single.vcard().contains("\r\nN:Cher;;;;\r\n"));
+    }
+
+    #[test]
+    fn mecard_roundtrip() {
+        let contact = contact();
+        let payload = contact.mecard();
+        assert!(
+            payload.starts_with(r"MECARD:N:Do\;e\,x\\\:y,Jane;"),
+            "{}",
+            payload
+        );
+        let record = parse_mecard(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        let strings =
+            |vals: &[&str]| Value::test_list(vals.iter().map(|v| Value::test_string(*v)).collect());
+        assert_eq!(column("name"), Value::test_string(contact.name));
+        assert_eq!(column("org"), Value::test_string(r"A;B,C\D:E"));
+        assert_eq!(column("phone"), strings(&["+1 555 0100", "0100"]));
+        assert_eq!(column("email"), strings(&["jane@example.com"]));
+        assert_eq!(
+            column("address"),
+            Value::test_string("1 Main St; Apt 2, Springfield")
+        );
+        assert_eq!(column("url"), strings(&["https://example.com/a;b?c=d:e"]));
+        assert_eq!(column("reading"), Value::test_nothing());
+    }
+
+    #[test]
+    fn mecard_fields() {
+        let payload =
+            r"MECARD:N:Yamada,Taro;SOUND:yamada\,taro;TEL:1;TEL:2;BDAY:19700101;NOTE:a\;b;;";
+        let record = parse_mecard(payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("name"), Value::test_string("Taro Yamada"));
+        assert_eq!(column("reading"), Value::test_string("yamada,taro"));
+        assert_eq!(column("birthday"), Value::test_string("19700101"));
+        assert_eq!(column("note"), Value::test_string("a;b"));
+        assert_eq!(column("org"), Value::test_nothing());
+        assert!(parse_mecard("MECARD:TEL:1;;", Span::test_data()).is_none());
+    }
+}
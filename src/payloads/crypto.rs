@@ -0,0 +1,278 @@
+//! BIP-21 style `<scheme>:<address>?amount=...` cryptocurrency payment uri.
+//!
+//! Addresses of bitcoin, litecoin and dogecoin are checked against their
+//! Base58Check or Bech32(m) checksum, other schemes are passed through.
+
+use sha2::{Digest, Sha256};
+
+use super::{percent_encode, Error};
+
+/// Known schemes with their Base58Check version bytes and Bech32 prefixes.
+const SCHEMES: &[(&str, &[u8], &[&str])] = &[
+    ("bitcoin", &[0x00, 0x05, 0x6f, 0xc4], &["bc", "tb", "bcrt"]),
+    (
+        "litecoin",
+        &[0x30, 0x32, 0x05, 0x6f, 0x3a],
+        &["ltc", "tltc"],
+    ),
+    ("dogecoin", &[0x1e, 0x16, 0x71, 0xc4], &[]),
+];
+
+const BASE58: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const BECH32: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Decode a Base58Check string, returning the version byte.
+fn base58check(address: &str) -> Result<u8, String> {
+    let mut bytes: Vec<u8> = vec![];
+    for c in address.bytes() {
+        let mut carry = BASE58
+            .iter()
+            .position(|x| *x == c)
+            .ok_or_else(|| format!("'{}' is not a base58 character", c as char))?
+            as u32;
+        for b in bytes.iter_mut().rev() {
+            carry += *b as u32 * 58;
+            *b = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.insert(0, carry as u8);
+            carry >>= 8;
+        }
+    }
+    let zeros = address.bytes().take_while(|c| *c == b'1').count();
+    let bytes: Vec<u8> = std::iter::repeat_n(0, zeros).chain(bytes).collect();
+    if bytes.len() != 25 {
+        return Err("base58 address should decode to 25 bytes".into());
+    }
+    let (payload, checksum) = bytes.split_at(21);
+    if Sha256::digest(Sha256::digest(payload))[..4] != *checksum {
+        return Err("base58 checksum does not match".into());
+    }
+    Ok(payload[0])
+}
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GEN: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut chk: u32 = 1;
+    for v in values {
+        let top = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ v as u32;
+        for (i, g) in GEN.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                chk ^= g;
+            }
+        }
+    }
+    chk
+}
+
+/// Verify a segwit Bech32 (v0) or Bech32m (v1+) address, returning its prefix.
+fn bech32(address: &str) -> Result<String, String> {
+    if address.to_lowercase() != address && address.to_uppercase() != address {
+        return Err("bech32 address should not mix cases".into());
+    }
+    let address = address.to_lowercase();
+    let (hrp, data) = address
+        .rsplit_once('1')
+        .ok_or_else(|| "bech32 address has no separator".to_string())?;
+    if hrp.is_empty() || data.len() < 7 {
+        return Err("bech32 address is too short".into());
+    }
+    let data = data
+        .bytes()
+        .map(|c| BECH32.iter().position(|x| *x == c).map(|p| p as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or_else(|| "invalid bech32 character".to_string())?;
+    let expanded = hrp
+        .bytes()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|c| c & 31))
+        .chain(data.iter().copied());
+    let expected = match data[0] {
+        0 => 1,
+        _ => 0x2bc830a3,
+    };
+    if bech32_polymod(expanded) != expected {
+        return Err("bech32 checksum does not match".into());
+    }
+    Ok(hrp.to_string())
+}
+
+fn validate(scheme: &str, address: &str) -> Result<(), String> {
+    let Some((_, versions, prefixes)) = SCHEMES.iter().find(|(s, _, _)| *s == scheme) else {
+        if address.is_empty() || !address.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err("address should only contain letters and digits".into());
+        }
+        return Ok(());
+    };
+    if let Some((hrp, _)) = address.to_lowercase().rsplit_once('1') {
+        if prefixes.contains(&hrp) {
+            return bech32(address).map(|_| ());
+        }
+    }
+    let version = base58check(address)?;
+    if !versions.contains(&version) {
+        return Err(format!(
+            "version byte {:#04x} is not a {} address",
+            version, scheme
+        ));
+    }
+    Ok(())
+}
+
+/// Format an amount without float noise, e.g. `0.001` instead of `0.00100000`.
+///
+/// Amounts are whole multiples of 0.00000001, the smallest unit of bitcoin,
+/// give or take float noise such as that of `0.1 + 0.2`; others would be
+/// rounded to a different amount or to nothing.
+fn format_amount(amount: f64) -> Result<String, String> {
+    if !(amount > 0.0 && amount.is_finite()) {
+        return Err("should be a positive amount".into());
+    }
+    let units = amount * 1e8;
+    let rounded = units.round();
+    if rounded < 1.0 {
+        return Err("should be at least 0.00000001".into());
+    }
+    // float noise is relative to the amount, a fraction of a unit is not
+    if (units - rounded).abs() > units * 1e-12 {
+        return Err(format!("{} has more than 8 decimal places", amount));
+    }
+    let s = format!("{:.8}", rounded / 1e8);
+    Ok(s.trim_end_matches('0').trim_end_matches('.').to_string())
+}
+
+/// A payment request, the flags of `to qr crypto`.
+#[derive(Clone, Debug, Default)]
+pub struct Crypto {
+    pub address: String,
+    /// Uri scheme of the currency, `bitcoin` by default.
+    pub scheme: Option<String>,
+    /// Amount in whole coins.
+    pub amount: Option<f64>,
+    /// Name of the recipient.
+    pub label: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Build the payload of `crypto`.
+pub fn build(crypto: &Crypto) -> Result<String, Error> {
+    let scheme = crypto.scheme.as_deref().unwrap_or("bitcoin").to_lowercase();
+    if scheme.is_empty()
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err(Error::new(
+            "Invalid scheme",
+            format!("{} is not an uri scheme", scheme),
+        ));
+    }
+    let address = crypto.address.trim();
+    validate(&scheme, address).map_err(|e| Error::new("Invalid address", e))?;
+
+    let mut query = vec![];
+    if let Some(amount) = crypto.amount {
+        let amount = format_amount(amount).map_err(|e| Error::new("Invalid amount", e))?;
+        query.push(format!("amount={}", amount));
+    }
+    if let Some(label) = &crypto.label {
+        query.push(format!("label={}", percent_encode(label)));
+    }
+    if let Some(message) = &crypto.message {
+        query.push(format!("message={}", percent_encode(message)));
+    }
+    let mut payload = format!("{}:{}", scheme, address);
+    if !query.is_empty() {
+        payload.push('?');
+        payload.push_str(&query.join("&"));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts() {
+        assert_eq!(format_amount(0.001).unwrap(), "0.001");
+        assert_eq!(format_amount(0.1 + 0.2).unwrap(), "0.3");
+        assert_eq!(format_amount(1.1 * 3.0).unwrap(), "3.3");
+        assert_eq!(format_amount(0.00000001).unwrap(), "0.00000001");
+        assert_eq!(format_amount(20999999.9769).unwrap(), "20999999.9769");
+        assert_eq!(format_amount(42.0).unwrap(), "42");
+        assert!(format_amount(0.000000001).is_err());
+        assert!(format_amount(0.000000015).is_err());
+        assert!(format_amount(0.123456789).is_err());
+        assert!(format_amount(0.0).is_err());
+        assert!(format_amount(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn addresses() {
+        for (scheme, address) in [
+            ("bitcoin", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"),
+            ("bitcoin", "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"),
+            ("bitcoin", "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq"),
+            ("bitcoin", "BC1QAR0SRRR7XFKVY5L643LYDNW9RE59GTZZWF5MDQ"),
+            (
+                "bitcoin",
+                "bc1p5d7rjq7g6rdk2yhzks9smlaqtedr4dekq08ge8ztwac72sfr9rusxg3297",
+            ),
+            ("monero", "4AdUndXHHZ6cfufTMvppY6JwXNouMBzSkbLYfpAV5Usx"),
+        ] {
+            assert_eq!(validate(scheme, address), Ok(()), "{}", address);
+        }
+        let error = |scheme, address| validate(scheme, address).unwrap_err();
+        assert_eq!(
+            error("bitcoin", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN3"),
+            "base58 checksum does not match"
+        );
+        assert_eq!(
+            error("bitcoin", "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdp"),
+            "bech32 checksum does not match"
+        );
+        assert_eq!(
+            error("bitcoin", "bc1qar0srrr7xfkvy5l643lydnw9re59GTZZWF5MDQ"),
+            "bech32 address should not mix cases"
+        );
+        assert_eq!(
+            error("litecoin", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2"),
+            "version byte 0x00 is not a litecoin address"
+        );
+        assert!(validate("bitcoin", "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNV0").is_err());
+    }
+
+    #[test]
+    fn payload() {
+        let crypto = Crypto {
+            address: " 1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2 ".into(),
+            amount: Some(0.1 + 0.2),
+            label: Some("Jane & Co".into()),
+            message: Some("order 7".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            build(&crypto).unwrap(),
+            "bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=0.3&label=Jane%20%26%20Co&message=order%207"
+        );
+        let label = |crypto: Crypto| build(&crypto).unwrap_err().label;
+        assert_eq!(
+            label(Crypto {
+                scheme: Some("bit coin".into()),
+                ..crypto.clone()
+            }),
+            "Invalid scheme"
+        );
+        assert_eq!(
+            label(Crypto {
+                amount: Some(0.123456789),
+                ..crypto
+            }),
+            "Invalid amount"
+        );
+    }
+}
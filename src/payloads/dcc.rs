@@ -0,0 +1,501 @@
+//! EU Digital COVID Certificates: `HC1:` followed by the base45 text of a
+//! zlib compressed, signed (COSE) CBOR web token.
+//!
+//! The signature is not verified, that needs the trust list of the issuing
+//! countries.
+
+use std::io::Read;
+
+use chrono::{TimeZone, Utc};
+use nu_protocol::{Span, Value};
+
+const BASE45: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Decode base45 (RFC 9285), `None` if `s` is not valid base45.
+fn base45(s: &str) -> Option<Vec<u8>> {
+    let digits = s
+        .bytes()
+        .map(|c| BASE45.iter().position(|b| *b == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut out = vec![];
+    for chunk in digits.chunks(3) {
+        match *chunk {
+            [c, d, e] => {
+                let n = c + d * 45 + e * 45 * 45;
+                out.extend(u16::try_from(n).ok()?.to_be_bytes());
+            }
+            [c, d] => out.push(u8::try_from(c + d * 45).ok()?),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+/// The items of CBOR (RFC 8949) that CWTs are made of.
+#[derive(Clone)]
+enum Cbor {
+    Int(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Tag(u64, Box<Cbor>),
+    Bool(bool),
+    Null,
+    Float(f64),
+}
+
+/// Certificates are a few hundred bytes and nested a few levels, so anything
+/// far beyond is not one.
+const MAX_SIZE: u64 = 64 * 1024;
+const MAX_DEPTH: usize = 32;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        let taken = self.bytes.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(taken)
+    }
+
+    /// The argument of an item head, `None` for the indefinite length.
+    fn argument(&mut self, info: u8) -> Option<Option<u64>> {
+        let n = match info {
+            0..=23 => return Some(Some(info as u64)),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            31 => return Some(None),
+            _ => return None,
+        };
+        let bytes = self.take(n)?;
+        Some(Some(bytes.iter().fold(0, |n, b| n << 8 | *b as u64)))
+    }
+
+    /// The chunks of a string up to the break of an indefinite length one.
+    fn string(&mut self, major: u8, length: Option<u64>) -> Option<Vec<u8>> {
+        match length {
+            Some(n) => Some(self.take(usize::try_from(n).ok()?)?.to_vec()),
+            None => {
+                let mut out = vec![];
+                while self.bytes.get(self.pos) != Some(&0xff) {
+                    match self.item()? {
+                        Cbor::Bytes(chunk) if major == 2 => out.extend(chunk),
+                        Cbor::Text(chunk) if major == 3 => out.extend(chunk.into_bytes()),
+                        _ => return None,
+                    }
+                }
+                self.pos += 1;
+                Some(out)
+            }
+        }
+    }
+
+    /// The items of an array or map, up to the break of an indefinite length one.
+    fn items(&mut self, length: Option<u64>) -> Option<Vec<Cbor>> {
+        let mut items = vec![];
+        match length {
+            Some(n) => {
+                for _ in 0..n {
+                    items.push(self.item()?);
+                }
+            }
+            None => {
+                while self.bytes.get(self.pos) != Some(&0xff) {
+                    items.push(self.item()?);
+                }
+                self.pos += 1;
+            }
+        }
+        Some(items)
+    }
+
+    fn item(&mut self) -> Option<Cbor> {
+        self.depth += 1;
+        let item = self.item_at_depth();
+        self.depth -= 1;
+        item
+    }
+
+    fn item_at_depth(&mut self) -> Option<Cbor> {
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        let head = *self.take(1)?.first()?;
+        let (major, info) = (head >> 5, head & 0x1f);
+        if major == 7 {
+            return Some(match info {
+                20 => Cbor::Bool(false),
+                21 => Cbor::Bool(true),
+                22 | 23 => Cbor::Null,
+                25 => Cbor::Float(half(u16::from_be_bytes(self.take(2)?.try_into().ok()?))),
+                26 => Cbor::Float(f32::from_be_bytes(self.take(4)?.try_into().ok()?) as f64),
+                27 => Cbor::Float(f64::from_be_bytes(self.take(8)?.try_into().ok()?)),
+                _ => return None,
+            });
+        }
+        let length = self.argument(info)?;
+        Some(match major {
+            0 => Cbor::Int(length? as i128),
+            1 => Cbor::Int(-1 - length? as i128),
+            2 => Cbor::Bytes(self.string(major, length)?),
+            3 => Cbor::Text(String::from_utf8(self.string(major, length)?).ok()?),
+            4 => Cbor::Array(self.items(length)?),
+            5 => {
+                let mut items = self.items(length.map(|n| n.saturating_mul(2)))?.into_iter();
+                let mut pairs = vec![];
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    pairs.push((key, value));
+                }
+                Cbor::Map(pairs)
+            }
+            _ => Cbor::Tag(length?, Box::new(self.item()?)),
+        })
+    }
+}
+
+/// Convert an IEEE 754 half precision float.
+fn half(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10 & 0x1f) as i32;
+    let fraction = (bits & 0x3ff) as f64;
+    sign * match exponent {
+        0 => fraction * 2f64.powi(-24),
+        31 if fraction == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        e => (1.0 + fraction / 1024.0) * 2f64.powi(e - 15),
+    }
+}
+
+fn decode_cbor(bytes: &[u8]) -> Option<Cbor> {
+    Reader {
+        bytes,
+        pos: 0,
+        depth: 0,
+    }
+    .item()
+}
+
+impl Cbor {
+    fn get(&self, key: i128) -> Option<&Cbor> {
+        match self {
+            Cbor::Map(pairs) => pairs
+                .iter()
+                .find(|(k, _)| matches!(k, Cbor::Int(k) if *k == key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Convert to a nu value, with the keys of maps as strings and epoch
+    /// times (tag 1) as datetimes.
+    fn into_value(self, span: Span) -> Value {
+        match self {
+            Cbor::Int(n) => match i64::try_from(n) {
+                Ok(val) => Value::Int { val, span },
+                Err(_) => Value::Float {
+                    val: n as f64,
+                    span,
+                },
+            },
+            Cbor::Bytes(val) => Value::Binary { val, span },
+            Cbor::Text(val) => Value::String { val, span },
+            Cbor::Array(items) => Value::List {
+                vals: items.into_iter().map(|i| i.into_value(span)).collect(),
+                span,
+            },
+            Cbor::Map(pairs) => {
+                let (cols, vals) = pairs
+                    .into_iter()
+                    .map(|(k, v)| {
+                        let key = match k {
+                            Cbor::Text(key) => key,
+                            Cbor::Int(key) => key.to_string(),
+                            _ => String::new(),
+                        };
+                        (key, v.into_value(span))
+                    })
+                    .unzip();
+                Value::Record { cols, vals, span }
+            }
+            Cbor::Tag(1, time) => match *time {
+                Cbor::Int(n) => epoch(n, span),
+                time => time.into_value(span),
+            },
+            Cbor::Tag(_, item) => item.into_value(span),
+            Cbor::Bool(val) => Value::Bool { val, span },
+            Cbor::Null => Value::Nothing { span },
+            Cbor::Float(val) => Value::Float { val, span },
+        }
+    }
+}
+
+/// A datetime of seconds since the unix epoch.
+fn epoch(seconds: i128, span: Span) -> Value {
+    match i64::try_from(seconds)
+        .ok()
+        .and_then(|s| Utc.timestamp_opt(s, 0).single())
+    {
+        Some(time) => Value::Date {
+            val: time.fixed_offset(),
+            span,
+        },
+        None => Value::Nothing { span },
+    }
+}
+
+/// Read a certificate into `{issuer, issued, expires, kid, algorithm,
+/// certificate}`, with the health certificate claim as a record in the
+/// `certificate` column (`nam`, `dob` and one of `v`, `t` or `r`).
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let compressed = base45(payload.trim().strip_prefix("HC1:")?.trim_start())?;
+    let mut cose = vec![];
+    flate2::read::ZlibDecoder::new(&compressed[..])
+        .take(MAX_SIZE)
+        .read_to_end(&mut cose)
+        .ok()?;
+    // COSE_Sign1 is `[protected, unprotected, payload, signature]`, tagged 18.
+    let parts = match decode_cbor(&cose)? {
+        Cbor::Tag(18, sign1) => *sign1,
+        sign1 => sign1,
+    };
+    let Cbor::Array(parts) = parts else {
+        return None;
+    };
+    let [Cbor::Bytes(protected), unprotected, Cbor::Bytes(payload), _] = &parts[..] else {
+        return None;
+    };
+    let protected = decode_cbor(protected).unwrap_or(Cbor::Map(vec![]));
+    let header = |key| protected.get(key).or_else(|| unprotected.get(key));
+    let claims = decode_cbor(payload)?;
+    let int = |key| match claims.get(key) {
+        Some(Cbor::Int(n)) => epoch(*n, span),
+        _ => Value::Nothing { span },
+    };
+    let certificate = match claims.get(-260).and_then(|hcert| hcert.get(1)) {
+        Some(hcert @ Cbor::Map(_)) => hcert.clone().into_value(span),
+        _ => return None,
+    };
+    Some(Value::Record {
+        cols: [
+            "issuer",
+            "issued",
+            "expires",
+            "kid",
+            "algorithm",
+            "certificate",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            match claims.get(1) {
+                Some(Cbor::Text(val)) => Value::String {
+                    val: val.clone(),
+                    span,
+                },
+                _ => Value::Nothing { span },
+            },
+            int(6),
+            int(4),
+            match header(4) {
+                Some(Cbor::Bytes(kid)) => Value::String {
+                    val: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, kid),
+                    span,
+                },
+                _ => Value::Nothing { span },
+            },
+            match header(1) {
+                Some(Cbor::Int(-7)) => Value::String {
+                    val: "ES256".into(),
+                    span,
+                },
+                Some(Cbor::Int(-37)) => Value::String {
+                    val: "PS256".into(),
+                    span,
+                },
+                Some(Cbor::Int(n)) => Value::Int {
+                    val: *n as i64,
+                    span,
+                },
+                _ => Value::Nothing { span },
+            },
+            certificate,
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn head(major: u8, n: u64) -> Vec<u8> {
+        let major = major << 5;
+        match n {
+            0..=23 => vec![major | n as u8],
+            24..=0xff => vec![major | 24, n as u8],
+            0x100..=0xffff => [vec![major | 25], (n as u16).to_be_bytes().to_vec()].concat(),
+            _ => [vec![major | 26], (n as u32).to_be_bytes().to_vec()].concat(),
+        }
+    }
+
+    fn int(n: i64) -> Vec<u8> {
+        match n {
+            0.. => head(0, n as u64),
+            _ => head(1, (-1 - n) as u64),
+        }
+    }
+
+    fn bytes(b: &[u8]) -> Vec<u8> {
+        [head(2, b.len() as u64), b.to_vec()].concat()
+    }
+
+    fn text(s: &str) -> Vec<u8> {
+        [head(3, s.len() as u64), s.as_bytes().to_vec()].concat()
+    }
+
+    fn map(pairs: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut out = head(5, pairs.len() as u64);
+        for (k, v) in pairs {
+            out.extend(k);
+            out.extend(v);
+        }
+        out
+    }
+
+    fn base45_encode(bytes: &[u8]) -> String {
+        let mut out = String::new();
+        for chunk in bytes.chunks(2) {
+            let (mut n, digits) = match *chunk {
+                [a, b] => (u32::from(a) << 8 | u32::from(b), 3),
+                [a] => (u32::from(a), 2),
+                _ => unreachable!(),
+            };
+            for _ in 0..digits {
+                out.push(BASE45[(n % 45) as usize] as char);
+                n /= 45;
+            }
+        }
+        out
+    }
+
+    /// A certificate signed with ES256, with a name and date of birth.
+    fn certificate(hcert: Option<Vec<u8>>) -> String {
+        let mut claims = vec![
+            (int(1), text("DE")),
+            (int(4), int(1_700_000_000)),
+            (int(6), int(1_600_000_000)),
+        ];
+        if let Some(hcert) = hcert {
+            claims.push((int(-260), map(&[(int(1), hcert)])));
+        }
+        let mut cose = head(6, 18);
+        cose.extend(head(4, 4));
+        cose.extend(bytes(&map(&[(int(1), int(-7))])));
+        cose.extend(map(&[(int(4), bytes(b"kid1"))]));
+        cose.extend(bytes(&map(&claims)));
+        cose.extend(bytes(b"signature"));
+        let mut zlib = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+        zlib.write_all(&cose).unwrap();
+        format!("HC1:{}", base45_encode(&zlib.finish().unwrap()))
+    }
+
+    #[test]
+    fn base45_vectors() {
+        // The examples of RFC 9285.
+        assert_eq!(base45("BB8").unwrap(), b"AB");
+        assert_eq!(base45("%69 VD92EX0").unwrap(), b"Hello!!");
+        assert_eq!(base45("UJCLQE7W581").unwrap(), b"base-45");
+        assert_eq!(base45_encode(b"ietf!"), "QED8WEX0");
+        // Lowercase, a lone trailing digit and a triple above 0xffff.
+        assert!(base45("bb8").is_none());
+        assert!(base45("BB8A").is_none());
+        assert!(base45("GGW").is_none());
+    }
+
+    #[test]
+    fn signed_certificate() {
+        let hcert = map(&[
+            (text("ver"), text("1.3.0")),
+            (
+                text("nam"),
+                map(&[
+                    (text("fn"), text("Mustermann")),
+                    (text("gn"), text("Erika")),
+                ]),
+            ),
+            (text("dob"), text("1964-08-12")),
+        ]);
+        let record = parse(&certificate(Some(hcert)), Span::test_data()).unwrap();
+        let date = |s| Value::test_date(Utc.timestamp_opt(s, 0).single().unwrap().fixed_offset());
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec![
+                    "issuer",
+                    "issued",
+                    "expires",
+                    "kid",
+                    "algorithm",
+                    "certificate"
+                ],
+                vec![
+                    Value::test_string("DE"),
+                    date(1_600_000_000),
+                    date(1_700_000_000),
+                    Value::test_string("a2lkMQ=="),
+                    Value::test_string("ES256"),
+                    Value::test_record(
+                        vec!["ver", "nam", "dob"],
+                        vec![
+                            Value::test_string("1.3.0"),
+                            Value::test_record(
+                                vec!["fn", "gn"],
+                                vec![
+                                    Value::test_string("Mustermann"),
+                                    Value::test_string("Erika")
+                                ]
+                            ),
+                            Value::test_string("1964-08-12"),
+                        ]
+                    ),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn cbor_items() {
+        let decode = |b: &[u8]| decode_cbor(b).map(|c| c.into_value(Span::test_data()));
+        // Half precision, indefinite length text and an epoch time.
+        assert_eq!(decode(&[0xf9, 0x3e, 0x00]), Some(Value::test_float(1.5)));
+        assert_eq!(
+            decode(&[0x7f, 0x62, b'n', b'u', 0x61, b'!', 0xff]),
+            Some(Value::test_string("nu!"))
+        );
+        assert_eq!(
+            decode(&[0xc1, 0x00]),
+            Some(Value::test_date(
+                Utc.timestamp_opt(0, 0).unwrap().fixed_offset()
+            ))
+        );
+        // Truncated, and nested far too deep.
+        assert!(decode(&[0x62, b'n']).is_none());
+        assert!(decode(&[0x81; 64]).is_none());
+    }
+
+    #[test]
+    fn not_certificates() {
+        // A token without the health certificate claim.
+        assert!(parse(&certificate(None), Span::test_data()).is_none());
+        assert!(parse("HC1:not base45", Span::test_data()).is_none());
+        assert!(parse("BB8", Span::test_data()).is_none());
+    }
+}
@@ -0,0 +1,450 @@
+//! EPC069-12 SEPA credit transfer ("GiroCode").
+
+use nu_protocol::{Span, Value};
+
+use super::Error;
+
+/// Compute the ISO 7064 mod 97-10 remainder of an alphanumeric reference,
+/// after moving its first four characters to the end.
+fn mod97(s: &str) -> Option<u32> {
+    let rearranged = s[4..].chars().chain(s[..4].chars());
+    let mut rem = 0;
+    for c in rearranged {
+        let v = c.to_digit(36)?;
+        rem = if v < 10 { rem * 10 + v } else { rem * 100 + v } % 97;
+    }
+    Some(rem)
+}
+
+fn validate_iban(iban: &str) -> Result<String, String> {
+    let iban: String = iban
+        .chars()
+        .filter(|c| *c != ' ')
+        .collect::<String>()
+        .to_uppercase();
+    if !(15..=34).contains(&iban.len()) || !iban.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("an IBAN has 15 to 34 letters and digits".into());
+    }
+    if !iban[..2].chars().all(|c| c.is_ascii_uppercase())
+        || !iban[2..4].chars().all(|c| c.is_ascii_digit())
+    {
+        return Err("an IBAN starts with a country code and two check digits".into());
+    }
+    if mod97(&iban) != Some(1) {
+        return Err("check digits do not match".into());
+    }
+    Ok(iban)
+}
+
+fn validate_bic(bic: &str) -> Result<String, String> {
+    let bic = bic.trim().to_uppercase();
+    if !(bic.len() == 8 || bic.len() == 11)
+        || !bic.is_ascii()
+        || !bic[..6].chars().all(|c| c.is_ascii_uppercase())
+        || !bic[6..].chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        return Err("a BIC has 8 or 11 characters, starting with 6 letters".into());
+    }
+    Ok(bic)
+}
+
+/// Validate an ISO 11649 structured creditor reference (`RF..`).
+fn validate_reference(reference: &str) -> Result<String, String> {
+    let reference: String = reference
+        .chars()
+        .filter(|c| *c != ' ')
+        .collect::<String>()
+        .to_uppercase();
+    if !reference.starts_with("RF")
+        || !(5..=25).contains(&reference.len())
+        || !reference.chars().all(|c| c.is_ascii_alphanumeric())
+        || mod97(&reference) != Some(1)
+    {
+        return Err("not a valid RF creditor reference, use --text for free text".into());
+    }
+    Ok(reference)
+}
+
+/// A SEPA credit transfer, the flags of `to qr epc`.
+#[derive(Clone, Debug, Default)]
+pub struct Epc {
+    /// Name of the beneficiary.
+    pub name: String,
+    pub iban: String,
+    pub bic: Option<String>,
+    /// Amount in euros.
+    pub amount: Option<f64>,
+    /// Four letter purpose code, e.g. `CHAR` for charity.
+    pub purpose: Option<String>,
+    /// Structured creditor reference, `RF` and check digits.
+    pub reference: Option<String>,
+    /// Unstructured remittance information, instead of a `reference`.
+    pub text: Option<String>,
+    /// Note to the payer.
+    pub info: Option<String>,
+}
+
+/// Build the payload of `epc`.
+pub fn build(epc: &Epc) -> Result<String, Error> {
+    let max_len = |label: &str, s: &str, max: usize| {
+        if s.chars().count() > max {
+            Err(Error::new(
+                label,
+                format!("should be at most {} characters", max),
+            ))
+        } else {
+            Ok(())
+        }
+    };
+    let name = epc.name.trim();
+    if name.is_empty() {
+        return Err(Error::new(
+            "Invalid name",
+            "beneficiary name should not be empty",
+        ));
+    }
+    max_len("Invalid name", &epc.name, 70)?;
+    let iban = validate_iban(&epc.iban).map_err(|e| Error::new("Invalid IBAN", e))?;
+    let bic = epc
+        .bic
+        .as_deref()
+        .map(validate_bic)
+        .transpose()
+        .map_err(|e| Error::new("Invalid BIC", e))?;
+    let amount = match epc.amount {
+        Some(amount) => {
+            let cents = (amount * 100.0).round();
+            if (amount * 100.0 - cents).abs() > 1e-6 {
+                return Err(Error::new(
+                    "Invalid amount",
+                    "should have at most 2 decimals",
+                ));
+            }
+            if !(1.0..=99_999_999_999.0).contains(&cents) {
+                return Err(Error::new(
+                    "Invalid amount",
+                    "should be between 0.01 and 999999999.99",
+                ));
+            }
+            format!("EUR{:.2}", cents / 100.0)
+        }
+        None => String::new(),
+    };
+    let purpose = epc.purpose.as_deref().unwrap_or_default().to_uppercase();
+    if !purpose.is_empty()
+        && (purpose.len() != 4 || !purpose.chars().all(|c| c.is_ascii_alphanumeric()))
+    {
+        return Err(Error::new(
+            "Invalid purpose",
+            "should be a 4 letter purpose code",
+        ));
+    }
+    if epc.reference.is_some() && epc.text.is_some() {
+        return Err(Error::new(
+            "Conflicting remittance information",
+            "only one of --reference and --text can be given",
+        ));
+    }
+    let reference = epc
+        .reference
+        .as_deref()
+        .map(validate_reference)
+        .transpose()
+        .map_err(|e| Error::new("Invalid reference", e))?
+        .unwrap_or_default();
+    let text = epc.text.as_deref().unwrap_or_default();
+    max_len("Invalid text", text, 140)?;
+    let info = epc.info.as_deref().unwrap_or_default();
+    max_len("Invalid info", info, 70)?;
+
+    let lines = [
+        "BCD",
+        "002",
+        "1",
+        "SCT",
+        bic.as_deref().unwrap_or_default(),
+        name,
+        &iban,
+        &amount,
+        &purpose,
+        &reference,
+        text,
+        info,
+    ];
+    let payload = lines.join("\n").trim_end_matches('\n').to_string();
+    if payload.len() > 331 {
+        return Err(Error::new(
+            "Payload too large",
+            format!("EPC payload is {} bytes, the limit is 331", payload.len()),
+        ));
+    }
+    Ok(payload)
+}
+
+/// Read a payload into `{name, iban, bic, amount, currency, purpose,
+/// reference, text, info, errors}`.
+///
+/// Fields that fail the checks of `to qr epc` are kept as written and
+/// reported in `errors`, which is empty for a valid transfer.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let lines: Vec<&str> = payload.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let [service, version, _charset, "SCT", rest @ ..] = &lines[..] else {
+        return None;
+    };
+    if *service != "BCD" || !matches!(*version, "001" | "002") {
+        return None;
+    }
+    let line = |i: usize| rest.get(i).copied().unwrap_or_default().trim();
+    let mut errors = vec![];
+    // keep the field as written when it fails validation
+    let mut check = |field: &str, raw: &str, result: Result<String, String>| match result {
+        Ok(val) => val,
+        Err(e) => {
+            errors.push(format!("{}: {}", field, e));
+            raw.to_string()
+        }
+    };
+    let bic = match line(0) {
+        "" if *version == "001" => check("bic", "", Err("required by version 001".into())),
+        "" => String::new(),
+        bic => check("bic", bic, validate_bic(bic)),
+    };
+    let name = match line(1) {
+        "" => check(
+            "name",
+            "",
+            Err("beneficiary name should not be empty".into()),
+        ),
+        name => name.to_string(),
+    };
+    let iban = check("iban", line(2), validate_iban(line(2)));
+    let (currency, amount) = match line(3) {
+        "" => (None, None),
+        amount => {
+            let (currency, value) = amount.split_at(
+                amount
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(amount.len()),
+            );
+            if currency != "EUR" {
+                check("amount", amount, Err("the currency should be EUR".into()));
+            }
+            let value = match value.parse::<f64>() {
+                Ok(value) if value >= 0.01 => Some(value),
+                _ => {
+                    check(
+                        "amount",
+                        amount,
+                        Err(format!("{} is not a valid amount", value)),
+                    );
+                    None
+                }
+            };
+            (Some(currency.to_string()), value)
+        }
+    };
+    let reference = match line(5) {
+        "" => String::new(),
+        reference => check("reference", reference, validate_reference(reference)),
+    };
+    let string = |val: &str| match val {
+        "" => Value::Nothing { span },
+        val => Value::String {
+            val: val.to_string(),
+            span,
+        },
+    };
+    Some(Value::Record {
+        cols: [
+            "name",
+            "iban",
+            "bic",
+            "amount",
+            "currency",
+            "purpose",
+            "reference",
+            "text",
+            "info",
+            "errors",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            string(&name),
+            string(&iban),
+            string(&bic),
+            match amount {
+                Some(val) => Value::Float { val, span },
+                None => Value::Nothing { span },
+            },
+            string(currency.as_deref().unwrap_or_default()),
+            string(line(4)),
+            string(&reference),
+            string(line(6)),
+            string(line(7)),
+            Value::List {
+                vals: errors
+                    .into_iter()
+                    .map(|val| Value::String { val, span })
+                    .collect(),
+                span,
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn epc() -> Epc {
+        Epc {
+            name: "Red Cross".into(),
+            iban: "de89 3704 0044 0532 0130 00".into(),
+            bic: Some("cobadeffxxx".into()),
+            amount: Some(12.3),
+            purpose: Some("char".into()),
+            reference: Some("RF18 5390 0754 7034".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let payload = build(&epc()).unwrap();
+        assert_eq!(
+            payload,
+            "BCD\n002\n1\nSCT\nCOBADEFFXXX\nRed Cross\nDE89370400440532013000\nEUR12.30\nCHAR\nRF18539007547034"
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("name"), Value::test_string("Red Cross"));
+        assert_eq!(column("iban"), Value::test_string("DE89370400440532013000"));
+        assert_eq!(column("bic"), Value::test_string("COBADEFFXXX"));
+        assert_eq!(column("amount"), Value::test_float(12.3));
+        assert_eq!(column("currency"), Value::test_string("EUR"));
+        assert_eq!(column("purpose"), Value::test_string("CHAR"));
+        assert_eq!(column("reference"), Value::test_string("RF18539007547034"));
+        assert_eq!(column("text"), Value::test_nothing());
+        assert_eq!(column("errors"), Value::test_list(vec![]));
+    }
+
+    #[test]
+    fn text_and_info() {
+        let epc = Epc {
+            bic: None,
+            amount: None,
+            purpose: None,
+            reference: None,
+            text: Some("Invoice 42; thanks".into()),
+            info: Some("see you".into()),
+            ..epc()
+        };
+        let payload = build(&epc).unwrap();
+        assert_eq!(
+            payload,
+            "BCD\n002\n1\nSCT\n\nRed Cross\nDE89370400440532013000\n\n\n\nInvoice 42; thanks\nsee you"
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record.get_data_by_key("text"),
+            Some(Value::test_string("Invoice 42; thanks"))
+        );
+        assert_eq!(
+            record.get_data_by_key("info"),
+            Some(Value::test_string("see you"))
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let label = |epc: Epc| build(&epc).unwrap_err().label;
+        let iban = |iban: &str| Epc {
+            iban: iban.into(),
+            ..epc()
+        };
+        assert_eq!(label(iban("DE88370400440532013000")), "Invalid IBAN");
+        assert_eq!(label(iban("DE89")), "Invalid IBAN");
+        assert_eq!(
+            label(Epc {
+                name: " ".into(),
+                ..epc()
+            }),
+            "Invalid name"
+        );
+        assert_eq!(
+            label(Epc {
+                bic: Some("COBA".into()),
+                ..epc()
+            }),
+            "Invalid BIC"
+        );
+        assert_eq!(
+            label(Epc {
+                amount: Some(0.001),
+                ..epc()
+            }),
+            "Invalid amount"
+        );
+        assert_eq!(
+            label(Epc {
+                amount: Some(1e10),
+                ..epc()
+            }),
+            "Invalid amount"
+        );
+        assert_eq!(
+            label(Epc {
+                reference: Some("RF19539007547034".into()),
+                ..epc()
+            }),
+            "Invalid reference"
+        );
+        assert_eq!(
+            label(Epc {
+                text: Some("x".into()),
+                ..epc()
+            }),
+            "Conflicting remittance information"
+        );
+        assert_eq!(
+            label(Epc {
+                reference: None,
+                text: Some("x".repeat(141)),
+                ..epc()
+            }),
+            "Invalid text"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let record = parse(
+            "BCD\n001\n1\nSCT\n\nName\nDE88370400440532013000\nUSD1.00",
+            Span::test_data(),
+        )
+        .unwrap();
+        let errors = record.get_data_by_key("errors").unwrap();
+        let errors: Vec<String> = errors
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|e| e.as_string().unwrap())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                "bic: required by version 001",
+                "iban: check digits do not match",
+                "amount: the currency should be EUR",
+            ]
+        );
+        // Fields that fail their checks are kept as written.
+        assert_eq!(
+            record.get_data_by_key("iban"),
+            Some(Value::test_string("DE88370400440532013000"))
+        );
+        assert!(parse("BCD\n003\n1\nSCT", Span::test_data()).is_none());
+    }
+}
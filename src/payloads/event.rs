@@ -0,0 +1,189 @@
+//! iCalendar `VEVENT` built from a record with the columns
+//! `title`, `start`, `end`, `location` and `description`.
+//!
+//! `start` and `end` are datetimes and are written in UTC.
+//!
+//! Events are parsed back into a record with those columns.
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, NaiveDateTime, TimeZone};
+use nu_protocol::{Span, Value};
+
+use super::{text_escape, text_unescape, Error};
+
+/// An event, the record given to `to qr vevent`.
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub title: String,
+    pub start: DateTime<FixedOffset>,
+    pub end: Option<DateTime<FixedOffset>>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Format a datetime as an iCalendar UTC date-time.
+fn utc(time: &DateTime<FixedOffset>) -> String {
+    time.naive_utc().format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Build the payload of `event`.
+pub fn build(event: &Event) -> Result<String, Error> {
+    if matches!(event.end, Some(end) if end < event.start) {
+        return Err(Error::new("Invalid end", "event ends before it starts"));
+    }
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("SUMMARY:{}", text_escape(&event.title)),
+        format!("DTSTART:{}", utc(&event.start)),
+    ];
+    if let Some(end) = &event.end {
+        lines.push(format!("DTEND:{}", utc(end)));
+    }
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", text_escape(location)));
+    }
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", text_escape(description)));
+    }
+    lines.push("END:VEVENT".to_string());
+    Ok(lines.join("\r\n"))
+}
+
+/// Read an iCalendar DATE or DATE-TIME.
+///
+/// Times without a `Z` are floating or in a `TZID` zone, which are both taken
+/// as local time; dates are midnight local time.
+fn parse_date(value: &str) -> Option<DateTime<FixedOffset>> {
+    if let Some(utc) = value.strip_suffix(['Z', 'z']) {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(time.and_utc().fixed_offset());
+    }
+    let time = match NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+        Ok(time) => time,
+        Err(_) => NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?,
+    };
+    Some(Local.from_local_datetime(&time).earliest()?.fixed_offset())
+}
+
+/// Read the first `VEVENT` of a payload into `{title, start, end, location,
+/// description}`, with the `SUMMARY` as the title and datetimes for `DTSTART`
+/// and `DTEND`.
+///
+/// The event may stand alone or be wrapped in a `VCALENDAR`.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    // Long content lines are folded by a line break followed by a space or tab.
+    let unfolded = payload
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut lines = unfolded.lines();
+    lines.find(|line| line.trim().eq_ignore_ascii_case("BEGIN:VEVENT"))?;
+    let mut fields: Vec<(String, &str)> = vec![];
+    for line in lines.take_while(|line| !line.trim().eq_ignore_ascii_case("END:VEVENT")) {
+        // Parameters such as `;TZID=` sit between the name and the colon.
+        if let Some((name, value)) = line.split_once(':') {
+            let name = name.split(';').next().unwrap_or_default();
+            fields.push((name.to_uppercase(), value.trim_end()));
+        }
+    }
+    let find = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| *v);
+    let text = |key: &str| match find(key) {
+        Some(v) => Value::String {
+            val: text_unescape(v),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    let date = |key: &str| match find(key).and_then(parse_date) {
+        Some(val) => Value::Date { val, span },
+        None => Value::Nothing { span },
+    };
+    Some(Value::Record {
+        cols: ["title", "start", "end", "location", "description"]
+            .map(String::from)
+            .to_vec(),
+        vals: vec![
+            text("SUMMARY"),
+            date("DTSTART"),
+            date("DTEND"),
+            text("LOCATION"),
+            text("DESCRIPTION"),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn event() -> Event {
+        Event {
+            title: "Launch; v1, final\\draft".into(),
+            start: date("2024-03-01T10:00:00+02:00"),
+            end: Some(date("2024-03-01T11:30:00+02:00")),
+            location: Some("Room 1, 2nd floor".into()),
+            description: Some("Agenda:\nintro; demo".into()),
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let event = event();
+        let payload = build(&event).unwrap();
+        assert_eq!(
+            payload,
+            [
+                "BEGIN:VEVENT",
+                r"SUMMARY:Launch\; v1\, final\\draft",
+                "DTSTART:20240301T080000Z",
+                "DTEND:20240301T093000Z",
+                r"LOCATION:Room 1\, 2nd floor",
+                r"DESCRIPTION:Agenda:\nintro\; demo",
+                "END:VEVENT",
+            ]
+            .join("\r\n")
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("title"), Value::test_string(&event.title));
+        assert_eq!(column("start"), Value::test_date(event.start));
+        assert_eq!(column("end"), Value::test_date(event.end.unwrap()));
+        assert_eq!(column("location"), Value::test_string("Room 1, 2nd floor"));
+        assert_eq!(
+            column("description"),
+            Value::test_string("Agenda:\nintro; demo")
+        );
+    }
+
+    #[test]
+    fn calendar() {
+        // Folded lines, parameters and a wrapping VCALENDAR.
+        let payload = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Long\r\n  title\r\nDTSTART;VALUE=DATE-TIME:20240301T080000Z\r\nEND:VEVENT\r\nEND:VCALENDAR";
+        let record = parse(payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record.get_data_by_key("title"),
+            Some(Value::test_string("Long title"))
+        );
+        assert_eq!(
+            record.get_data_by_key("start"),
+            Some(Value::test_date(date("2024-03-01T08:00:00Z")))
+        );
+        assert_eq!(record.get_data_by_key("end"), Some(Value::test_nothing()));
+    }
+
+    #[test]
+    fn ends_before_start() {
+        let event = Event {
+            end: Some(date("2024-03-01T09:59:59+02:00")),
+            ..event()
+        };
+        assert_eq!(build(&event).unwrap_err().label, "Invalid end");
+    }
+}
@@ -0,0 +1,123 @@
+//! `geo:<lat>,<lon>[,<alt>]` location uri (RFC 5870).
+
+use nu_protocol::{Span, Value};
+
+use super::{strip_prefix_ignore_case, Error};
+
+/// A location, the flags of `to qr geo`.
+#[derive(Clone, Debug, Default)]
+pub struct Geo {
+    pub lat: f64,
+    pub lon: f64,
+    /// Meters above sea level.
+    pub alt: Option<f64>,
+}
+
+/// Build the payload of `geo`.
+pub fn build(geo: &Geo) -> Result<String, Error> {
+    let Geo { lat, lon, alt } = *geo;
+    if !(-90.0..=90.0).contains(&lat) {
+        return Err(Error::new(
+            "Invalid latitude",
+            "should be between -90 and 90",
+        ));
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        return Err(Error::new(
+            "Invalid longitude",
+            "should be between -180 and 180",
+        ));
+    }
+    Ok(match alt {
+        Some(alt) if alt.is_finite() => format!("geo:{},{},{}", lat, lon, alt),
+        Some(_) => {
+            return Err(Error::new(
+                "Invalid altitude",
+                "should be a finite number of meters",
+            ))
+        }
+        None => format!("geo:{},{}", lat, lon),
+    })
+}
+
+/// Read a uri into `{latitude, longitude, altitude}` in degrees and meters.
+///
+/// Parameters such as `;u=` (uncertainty) and the `?q=` query of Android
+/// are ignored.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let rest = strip_prefix_ignore_case(payload, "geo:")?;
+    let coordinates = rest.split([';', '?']).next()?;
+    let numbers = coordinates
+        .split(',')
+        .map(|n| n.trim().parse::<f64>().ok().filter(|n| n.is_finite()))
+        .collect::<Option<Vec<_>>>()?;
+    let (lat, lon, alt) = match numbers[..] {
+        [lat, lon] => (lat, lon, None),
+        [lat, lon, alt] => (lat, lon, Some(alt)),
+        _ => return None,
+    };
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    Some(Value::Record {
+        cols: vec!["latitude".into(), "longitude".into(), "altitude".into()],
+        vals: vec![
+            Value::Float { val: lat, span },
+            Value::Float { val: lon, span },
+            match alt {
+                Some(val) => Value::Float { val, span },
+                None => Value::Nothing { span },
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let geo = Geo {
+            lat: 47.3769,
+            lon: -8.5417,
+            alt: Some(408.5),
+        };
+        let payload = build(&geo).unwrap();
+        assert_eq!(payload, "geo:47.3769,-8.5417,408.5");
+        let record = parse(&payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["latitude", "longitude", "altitude"],
+                vec![
+                    Value::test_float(47.3769),
+                    Value::test_float(-8.5417),
+                    Value::test_float(408.5)
+                ]
+            )
+        );
+        let flat = Geo { alt: None, ..geo };
+        assert_eq!(build(&flat).unwrap(), "geo:47.3769,-8.5417");
+        let record = parse("GEO:1,2;u=35?q=zurich", Span::test_data()).unwrap();
+        assert_eq!(
+            record.get_data_by_key("longitude"),
+            Some(Value::test_float(2.0))
+        );
+        assert_eq!(
+            record.get_data_by_key("altitude"),
+            Some(Value::test_nothing())
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let label = |lat, lon, alt| build(&Geo { lat, lon, alt }).unwrap_err().label;
+        assert_eq!(label(90.5, 0.0, None), "Invalid latitude");
+        assert_eq!(label(0.0, -181.0, None), "Invalid longitude");
+        assert_eq!(label(0.0, 0.0, Some(f64::NAN)), "Invalid altitude");
+        assert!(parse("geo:91,0", Span::test_data()).is_none());
+        assert!(parse("geo:1", Span::test_data()).is_none());
+    }
+}
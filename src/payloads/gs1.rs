@@ -0,0 +1,340 @@
+//! GS1 element strings: the application identifiers (AIs) and data of trade
+//! items and logistic units.
+
+use chrono::{Datelike, Local, NaiveDate, NaiveDateTime, TimeZone};
+use nu_protocol::{Span, Value};
+
+/// Group separator, the FNC1 that ends a field of variable length.
+const GS: char = '\x1d';
+
+/// Symbology identifiers of GS1 qr, data matrix, code 128, databar and dotcode.
+const IDENTIFIERS: [&str; 5] = ["]Q3", "]d2", "]C1", "]e0", "]J1"];
+
+#[derive(Clone, Copy)]
+enum Format {
+    /// This many digits, kept as a string for the leading zeros.
+    Digits(usize),
+    /// Up to this many characters.
+    Text(usize),
+    /// Up to this many digits, read as an int.
+    Count(usize),
+    /// `YYMMDD`, where a day of `00` is the last day of the month.
+    Date,
+    /// `YYMMDDHHMM`
+    DateTime,
+    /// Six digits with as many decimals as the last digit of the AI.
+    Measure,
+    /// Up to 15 digits with as many decimals as the last digit of the AI.
+    Amount,
+    /// An ISO 4217 numeric currency code followed by an `Amount`.
+    CurrencyAmount,
+}
+
+use Format::*;
+
+/// AI, column name and format of the common application identifiers.
+///
+/// The AIs of `Measure`, `Amount` and `CurrencyAmount` are followed by one
+/// more digit, the number of decimals.
+#[rustfmt::skip]
+const AIS: &[(&str, &str, Format)] = &[
+    ("00", "sscc", Digits(18)), ("01", "gtin", Digits(14)), ("02", "content", Digits(14)),
+    ("10", "batch", Text(20)), ("11", "production_date", Date), ("12", "due_date", Date),
+    ("13", "packaging_date", Date), ("15", "best_before", Date), ("16", "sell_by", Date),
+    ("17", "expiry", Date), ("20", "variant", Digits(2)), ("21", "serial", Text(20)),
+    ("22", "cpv", Text(20)), ("235", "tpx", Text(28)), ("240", "additional_id", Text(30)),
+    ("241", "customer_part", Text(30)), ("242", "mto_variant", Count(6)), ("243", "pcn", Text(20)),
+    ("250", "secondary_serial", Text(30)), ("251", "source_reference", Text(30)),
+    ("253", "gdti", Text(30)), ("254", "gln_extension", Text(20)), ("255", "gcn", Text(25)),
+    ("30", "variable_count", Count(8)), ("37", "count", Count(8)),
+    ("310", "net_weight_kg", Measure), ("311", "length_m", Measure), ("312", "width_m", Measure),
+    ("313", "height_m", Measure), ("314", "area_m2", Measure), ("315", "net_volume_l", Measure),
+    ("316", "net_volume_m3", Measure), ("320", "net_weight_lb", Measure),
+    ("330", "gross_weight_kg", Measure), ("390", "amount", Amount), ("391", "amount", CurrencyAmount),
+    ("392", "price", Amount), ("393", "price", CurrencyAmount), ("400", "order_number", Text(30)),
+    ("401", "ginc", Text(30)), ("402", "gsin", Digits(17)), ("403", "route", Text(30)),
+    ("410", "ship_to", Digits(13)), ("411", "bill_to", Digits(13)),
+    ("412", "purchased_from", Digits(13)), ("413", "ship_for", Digits(13)),
+    ("414", "location", Digits(13)), ("415", "pay_to", Digits(13)),
+    ("416", "production_location", Digits(13)), ("420", "ship_to_postal", Text(20)),
+    ("422", "origin", Digits(3)), ("7003", "expiry_time", DateTime), ("8004", "giai", Text(30)),
+    ("8005", "unit_price", Digits(6)), ("8020", "payment_slip", Text(25)),
+    ("90", "internal", Text(30)), ("91", "internal_91", Text(90)), ("92", "internal_92", Text(90)),
+    ("93", "internal_93", Text(90)), ("94", "internal_94", Text(90)), ("95", "internal_95", Text(90)),
+    ("96", "internal_96", Text(90)), ("97", "internal_97", Text(90)), ("98", "internal_98", Text(90)),
+    ("99", "internal_99", Text(90)),
+];
+
+/// The entry of an AI and its number of decimals, if the AI has them.
+fn lookup(ai: &str) -> Option<(&'static str, Format, Option<u32>)> {
+    AIS.iter().find_map(|(code, name, format)| match format {
+        Measure | Amount | CurrencyAmount => {
+            let decimals = ai.strip_prefix(code)?.parse().ok()?;
+            Some((*name, *format, Some(decimals)))
+        }
+        _ => (ai == *code).then_some((*name, *format, None)),
+    })
+}
+
+/// Split an element string into AIs and their data.
+///
+/// Fields of variable length end at a group separator or the end.
+fn elements(mut s: &str) -> Option<Vec<(String, &str)>> {
+    let mut v = vec![];
+    loop {
+        s = s.trim_start_matches(GS);
+        if s.is_empty() {
+            return Some(v);
+        }
+        let ai = (2..=4)
+            .filter_map(|n| s.get(..n))
+            .find(|ai| lookup(ai).is_some())?;
+        let (_, format, _) = lookup(ai)?;
+        let rest = &s[ai.len()..];
+        let len = match format {
+            Digits(n) => n,
+            Date | Measure => 6,
+            DateTime => 10,
+            Text(_) | Count(_) | Amount | CurrencyAmount => rest.find(GS).unwrap_or(rest.len()),
+        };
+        v.push((ai.to_string(), rest.get(..len)?));
+        s = &rest[len..];
+    }
+}
+
+/// Split a human readable element string, `(01)09501101530003(17)250101`.
+fn bracketed(s: &str) -> Option<Vec<(String, &str)>> {
+    let mut v = vec![];
+    for element in s.strip_prefix('(')?.split('(') {
+        let (ai, data) = element.split_once(')')?;
+        v.push((ai.to_string(), data));
+    }
+    Some(v)
+}
+
+/// Year of a two digit year, at most 49 years ago and 50 years from now.
+fn year(yy: i32) -> i32 {
+    let now = Local::now().year();
+    let year = now - now % 100 + yy;
+    match year - now {
+        d if d > 50 => year - 100,
+        d if d < -49 => year + 100,
+        _ => year,
+    }
+}
+
+/// Read the data of an AI, `None` if it does not match its format.
+fn value(format: Format, decimals: Option<u32>, data: &str, span: Span) -> Option<Value> {
+    let digits = |max: usize| {
+        (!data.is_empty() && data.len() <= max && data.bytes().all(|b| b.is_ascii_digit()))
+            .then_some(data)
+    };
+    let decimal = |digits: &str| {
+        let scale = 10f64.powi(decimals.unwrap_or_default() as i32);
+        Some(digits.parse::<u64>().ok()? as f64 / scale)
+    };
+    let date = |s: &str| -> Option<NaiveDate> {
+        let year = year(s[..2].parse().ok()?);
+        let month = s[2..4].parse().ok()?;
+        match s[4..6].parse().ok()? {
+            0 => NaiveDate::from_ymd_opt(year, month, 1)?
+                .checked_add_months(chrono::Months::new(1))?
+                .pred_opt(),
+            day => NaiveDate::from_ymd_opt(year, month, day),
+        }
+    };
+    let local = |time: NaiveDateTime| {
+        Some(Value::Date {
+            val: Local.from_local_datetime(&time).earliest()?.fixed_offset(),
+            span,
+        })
+    };
+    match format {
+        Digits(n) => Some(Value::String {
+            val: digits(n).filter(|d| d.len() == n)?.to_string(),
+            span,
+        }),
+        Text(n) => (!data.is_empty() && data.chars().count() <= n).then(|| Value::String {
+            val: data.to_string(),
+            span,
+        }),
+        Count(n) => Some(Value::Int {
+            val: digits(n)?.parse().ok()?,
+            span,
+        }),
+        Date => local(date(digits(6).filter(|d| d.len() == 6)?)?.and_hms_opt(0, 0, 0)?),
+        DateTime => {
+            let d = digits(10).filter(|d| d.len() == 10)?;
+            local(date(d)?.and_hms_opt(d[6..8].parse().ok()?, d[8..].parse().ok()?, 0)?)
+        }
+        Measure => Some(Value::Float {
+            val: decimal(digits(6).filter(|d| d.len() == 6)?)?,
+            span,
+        }),
+        Amount => Some(Value::Float {
+            val: decimal(digits(15)?)?,
+            span,
+        }),
+        CurrencyAmount => Some(Value::Float {
+            val: decimal(digits(18)?.get(3..).filter(|d| !d.is_empty())?)?,
+            span,
+        }),
+    }
+}
+
+/// Read an element string into a record with a column for each AI, named
+/// after its data: `gtin`, `batch`, `expiry` and so on.
+///
+/// The string starts with a GS1 symbology identifier (`]Q3`, `]d2`, `]C1`,
+/// ...) or FNC1, or is written with the AIs in brackets. Dates are datetimes
+/// at midnight local time, and measures and amounts floats, with the ISO 4217
+/// code of an amount in `<column>_currency`.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let elements = match IDENTIFIERS.iter().find_map(|id| payload.strip_prefix(id)) {
+        Some(rest) => elements(rest)?,
+        None if payload.starts_with(GS) => elements(payload)?,
+        None => bracketed(payload)?,
+    };
+    let mut cols: Vec<String> = vec![];
+    let mut vals = vec![];
+    for (ai, data) in elements {
+        let (name, format, decimals) = lookup(&ai)?;
+        if let CurrencyAmount = format {
+            cols.push(format!("{}_currency", name));
+            vals.push(Value::String {
+                val: data.get(..3)?.to_string(),
+                span,
+            });
+        }
+        cols.push(name.to_string());
+        vals.push(value(format, decimals, data, span)?);
+    }
+    if cols.is_empty() || (1..cols.len()).any(|i| cols[i..].contains(&cols[i - 1])) {
+        return None;
+    }
+    Some(Value::Record { cols, vals, span })
+}
+
+/// The element string to encode in a GS1 symbol, from its human readable
+/// form or one with group separators already, `None` if it is not made of
+/// known AIs.
+///
+/// Fields of variable length are ended with a group separator, but for the
+/// last one.
+pub fn element_string(s: &str) -> Option<String> {
+    if !s.starts_with('(') {
+        let s = s.trim_start_matches(GS);
+        return elements(s).filter(|e| !e.is_empty()).map(|_| s.to_string());
+    }
+    let elements = bracketed(s)?;
+    let mut out = String::new();
+    for (i, (ai, data)) in elements.iter().enumerate() {
+        let (_, format, _) = lookup(ai)?;
+        let fixed = match format {
+            Digits(n) => Some(n),
+            Date | Measure => Some(6),
+            DateTime => Some(10),
+            Text(_) | Count(_) | Amount | CurrencyAmount => None,
+        };
+        match fixed {
+            Some(n) if data.len() != n => return None,
+            Some(_) => out.push_str(&format!("{}{}", ai, data)),
+            None if i + 1 < elements.len() => out.push_str(&format!("{}{}{}", ai, data, GS)),
+            None => out.push_str(&format!("{}{}", ai, data)),
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn midnight(y: i32, m: u32, d: u32) -> Value {
+        let time = NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        Value::test_date(Local.from_local_datetime(&time).unwrap().fixed_offset())
+    }
+
+    #[test]
+    fn scanned() {
+        // A variable length batch ends at the group separator.
+        let payload = "]Q3010950110153000310AB-12\x1d17250100";
+        let record = parse(payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["gtin", "batch", "expiry"],
+                vec![
+                    Value::test_string("09501101530003"),
+                    Value::test_string("AB-12"),
+                    // A day of 00 is the last day of the month.
+                    midnight(2025, 1, 31),
+                ]
+            )
+        );
+        assert_eq!(parse(&payload[3..], Span::test_data()), None);
+        assert_eq!(
+            parse(&format!("\x1d{}", &payload[3..]), Span::test_data()),
+            Some(record)
+        );
+    }
+
+    #[test]
+    fn measures_and_amounts() {
+        let record = parse("(3103)001250(3932)978500", Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["net_weight_kg", "price_currency", "price"],
+                vec![
+                    Value::test_float(1.25),
+                    Value::test_string("978"),
+                    Value::test_float(5.0),
+                ]
+            )
+        );
+        assert_eq!(
+            parse("(3922)1999", Span::test_data()),
+            Some(Value::test_record(
+                vec!["price"],
+                vec![Value::test_float(19.99)]
+            ))
+        );
+        // 392 and 393 are both a price.
+        assert!(parse("(3922)1999(3932)978500", Span::test_data()).is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        let parse = |s: &str| parse(s, Span::test_data());
+        // Unknown AI, short fixed field, letters in a date, repeated column.
+        assert!(parse("(04)123").is_none());
+        assert!(parse("]Q301123").is_none());
+        assert!(parse("(17)25AB01").is_none());
+        assert!(parse("(10)A(10)B").is_none());
+        assert!(parse("hello").is_none());
+    }
+
+    #[test]
+    fn element_strings() {
+        assert_eq!(
+            element_string("(01)09501101530003(10)AB-12(17)250101").as_deref(),
+            Some("010950110153000310AB-12\x1d17250101")
+        );
+        // The last field needs no separator, nor does a fixed one.
+        assert_eq!(
+            element_string("(17)250101(10)AB-12").as_deref(),
+            Some("1725010110AB-12")
+        );
+        assert_eq!(
+            element_string("\x1d010950110153000310AB").as_deref(),
+            Some("010950110153000310AB")
+        );
+        assert_eq!(element_string("(01)123"), None);
+        assert_eq!(element_string("hello"), None);
+        assert_eq!(element_string(""), None);
+    }
+}
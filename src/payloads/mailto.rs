@@ -0,0 +1,154 @@
+//! `mailto:<to>?subject=...&body=...` email compose uri (RFC 6068).
+
+use nu_protocol::{Span, Value};
+
+use super::{percent_decode, percent_encode, strip_prefix_ignore_case, Error};
+
+/// Encode a comma separated list of addresses, keeping `@` and the separators readable.
+fn addresses(list: &str) -> String {
+    list.split(',')
+        .map(|a| percent_encode(a.trim()).replace("%40", "@"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// An email to compose, the flags of `to qr mailto`.
+///
+/// Recipients are comma separated addresses.
+#[derive(Clone, Debug, Default)]
+pub struct Mailto {
+    pub to: Option<String>,
+    pub cc: Option<String>,
+    pub bcc: Option<String>,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Build the payload of `mailto`.
+pub fn build(mailto: &Mailto) -> Result<String, Error> {
+    if mailto.to.is_none() && mailto.cc.is_none() && mailto.bcc.is_none() {
+        return Err(Error::new(
+            "Missing recipient",
+            "at least one of --to, --cc or --bcc is required",
+        ));
+    }
+    let mut query = vec![];
+    if let Some(cc) = &mailto.cc {
+        query.push(format!("cc={}", addresses(cc)));
+    }
+    if let Some(bcc) = &mailto.bcc {
+        query.push(format!("bcc={}", addresses(bcc)));
+    }
+    if let Some(subject) = &mailto.subject {
+        query.push(format!("subject={}", percent_encode(subject)));
+    }
+    if let Some(body) = &mailto.body {
+        // line breaks in a mailto body are required to be CRLF
+        let body = body.replace("\r\n", "\n").replace('\n', "\r\n");
+        query.push(format!("body={}", percent_encode(&body)));
+    }
+    let mut payload = format!(
+        "mailto:{}",
+        mailto.to.as_deref().map(addresses).unwrap_or_default()
+    );
+    if !query.is_empty() {
+        payload.push('?');
+        payload.push_str(&query.join("&"));
+    }
+    Ok(payload)
+}
+
+/// Read a uri into `{to, cc, bcc, subject, body}`, with lists of addresses.
+///
+/// Unlike in form data, `+` is not a space in a mailto uri.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let rest = strip_prefix_ignore_case(payload, "mailto:")?;
+    let (to, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let mut fields: Vec<(String, String)> = vec![("to".into(), to.into())];
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        fields.push((percent_decode(key).to_lowercase(), value.into()));
+    }
+    let addresses = |key: &str| Value::List {
+        vals: fields
+            .iter()
+            .filter(|(k, _)| k == key)
+            .flat_map(|(_, v)| v.split(','))
+            .map(|a| percent_decode(a).trim().to_string())
+            .filter(|a| !a.is_empty())
+            .map(|val| Value::String { val, span })
+            .collect(),
+        span,
+    };
+    let text = |key: &str| match fields.iter().find(|(k, _)| k == key) {
+        // Line breaks are CRLF in the uri.
+        Some((_, v)) => Value::String {
+            val: percent_decode(v).replace("\r\n", "\n"),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    Some(Value::Record {
+        cols: vec![
+            "to".into(),
+            "cc".into(),
+            "bcc".into(),
+            "subject".into(),
+            "body".into(),
+        ],
+        vals: vec![
+            addresses("to"),
+            addresses("cc"),
+            addresses("bcc"),
+            text("subject"),
+            text("body"),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mailto = Mailto {
+            to: Some("a@example.com, b+tag@example.com".into()),
+            cc: Some("c@example.com".into()),
+            subject: Some("Hi & bye?".into()),
+            body: Some("line 1\nline 2 = 100%".into()),
+            ..Default::default()
+        };
+        let payload = build(&mailto).unwrap();
+        assert_eq!(
+            payload,
+            "mailto:a@example.com,b%2Btag@example.com?cc=c@example.com&subject=Hi%20%26%20bye%3F&body=line%201%0D%0Aline%202%20%3D%20100%25"
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        let addresses =
+            |vals: &[&str]| Value::test_list(vals.iter().map(|v| Value::test_string(*v)).collect());
+        assert_eq!(
+            column("to"),
+            addresses(&["a@example.com", "b+tag@example.com"])
+        );
+        assert_eq!(column("cc"), addresses(&["c@example.com"]));
+        assert_eq!(column("bcc"), addresses(&[]));
+        assert_eq!(column("subject"), Value::test_string("Hi & bye?"));
+        assert_eq!(column("body"), Value::test_string("line 1\nline 2 = 100%"));
+    }
+
+    #[test]
+    fn recipients() {
+        let bcc = Mailto {
+            bcc: Some("x@example.com".into()),
+            ..Default::default()
+        };
+        assert_eq!(build(&bcc).unwrap(), "mailto:?bcc=x@example.com");
+        assert_eq!(
+            build(&Mailto::default()).unwrap_err().label,
+            "Missing recipient"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! Builders and parsers for well-known qr payload formats.
+//!
+//! Builders take plain structs, such as [wifi::Wifi] for [wifi::build], so
+//! they work without nushell; parsers return nushell values for `--parse`.
+
+use std::fmt;
+
+use nu_protocol::{Span, Value};
+
+pub mod contact;
+pub mod crypto;
+pub mod dcc;
+pub mod epc;
+pub mod event;
+pub mod geo;
+pub mod gs1;
+pub mod mailto;
+pub mod otpauth;
+pub mod pix;
+pub mod shc;
+pub mod sms;
+pub mod swiss;
+pub mod tel;
+pub mod upi;
+pub mod url;
+pub mod wifi;
+pub mod wireguard;
+
+/// Why a payload can not be built: a short `label` such as `Invalid ssid`,
+/// and a `msg` telling what is wrong.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub label: String,
+    pub msg: String,
+}
+
+impl Error {
+    fn new(label: &str, msg: impl Into<String>) -> Self {
+        Error {
+            label: label.into(),
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.label, self.msg)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Prefix every character of `special` in `s` with a backslash.
+fn backslash_escape(s: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Split `s` at every `separator` that is not escaped with a backslash,
+/// keeping the escapes.
+fn split_escaped(s: &str, separator: char) -> Vec<&str> {
+    let (mut parts, mut start, mut escaped) = (vec![], 0, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == separator => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Remove the backslash escapes of `s`.
+fn backslash_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Strip `prefix` from `s`, ignoring case.
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    match s.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&s[prefix.len()..]),
+        _ => None,
+    }
+}
+
+/// Parse a payload of one of the well-known formats into a record, `None`
+/// if it is in none of them.
+///
+/// The record starts with the `type` column of [`kind`].
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let mut record = wifi::parse(payload, span)
+        .or_else(|| contact::parse_mecard(payload, span))
+        .or_else(|| otpauth::parse(payload, span))
+        .or_else(|| geo::parse(payload, span))
+        .or_else(|| mailto::parse(payload, span))
+        .or_else(|| sms::parse(payload, span))
+        .or_else(|| tel::parse(payload, span))
+        .or_else(|| event::parse(payload, span))
+        .or_else(|| epc::parse(payload, span))
+        .or_else(|| swiss::parse(payload, span))
+        .or_else(|| upi::parse(payload, span))
+        .or_else(|| pix::parse(payload, span))
+        .or_else(|| gs1::parse(payload, span))
+        .or_else(|| shc::parse(payload, span))
+        .or_else(|| url::parse(payload, span))?;
+    if let Value::Record { cols, vals, .. } = &mut record {
+        cols.insert(0, "type".into());
+        vals.insert(
+            0,
+            Value::String {
+                val: kind(Some(payload)).into(),
+                span,
+            },
+        );
+    }
+    Some(record)
+}
+
+/// Schemes of cryptocurrency payment uris.
+const COINS: [&str; 6] = [
+    "bitcoin",
+    "bitcoincash",
+    "litecoin",
+    "dogecoin",
+    "ethereum",
+    "monero",
+];
+
+/// Guess what a payload is from its prefix: `wifi`, `vcard`, `mecard`,
+/// `otpauth`, `geo`, `mailto`, `sms`, `tel`, `event`, `payment`, `gs1`, `dcc`,
+/// `shc`, `wireguard`, `url`, `json` or else `text`, and `binary` for payloads
+/// that are not text.
+pub fn kind(payload: Option<&str>) -> &'static str {
+    let Some(payload) = payload else {
+        return "binary";
+    };
+    let text = payload.trim_start();
+    let starts = |prefix: &str| strip_prefix_ignore_case(text, prefix).is_some();
+    let scheme = text.split_once(':').map(|(s, _)| s.to_lowercase());
+    match () {
+        _ if starts("WIFI:") => "wifi",
+        _ if starts("BEGIN:VCARD") => "vcard",
+        _ if starts("MECARD:") => "mecard",
+        _ if starts("otpauth://") => "otpauth",
+        _ if starts("geo:") => "geo",
+        _ if starts("mailto:") => "mailto",
+        _ if starts("SMSTO:") || starts("sms:") => "sms",
+        _ if starts("tel:") => "tel",
+        _ if starts("BEGIN:VEVENT") || starts("BEGIN:VCALENDAR") => "event",
+        _ if starts("BCD\n") || starts("BCD\r\n") || starts("SPC\n") || starts("SPC\r\n") => {
+            "payment"
+        }
+        // EMV merchant presented codes such as PIX start with the format indicator.
+        _ if starts("000201") || starts("upi://pay") => "payment",
+        _ if scheme.is_some_and(|s| COINS.contains(&s.as_str())) => "payment",
+        _ if gs1::parse(text, Span::unknown()).is_some() => "gs1",
+        _ if starts("HC1:") => "dcc",
+        _ if starts("shc:/") => "shc",
+        _ if starts("[Interface]") => "wireguard",
+        _ if url::parse(text, Span::unknown()).is_some() => "url",
+        _ if text.starts_with(['{', '['])
+            && serde_json::from_str::<serde_json::Value>(text).is_ok() =>
+        {
+            "json"
+        }
+        _ => "text",
+    }
+}
+
+/// Columns of parsed payloads that `--redact` hides.
+const SECRETS: [&str; 2] = ["secret", "password"];
+
+/// Hide the secrets of a parsed payload, such as the key of an otp code.
+pub fn redact(value: &mut Value) {
+    if let Value::Record { cols, vals, span } = value {
+        for (col, val) in cols.iter().zip(vals.iter_mut()) {
+            if SECRETS.contains(&col.as_str()) && !matches!(val, Value::Nothing { .. }) {
+                *val = Value::String {
+                    val: "[redacted]".into(),
+                    span: *span,
+                };
+            }
+        }
+    }
+}
+
+/// Escape a TEXT value of vCard and iCalendar content lines.
+fn text_escape(s: &str) -> String {
+    backslash_escape(s, &['\\', ';', ','])
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Remove the escapes of a TEXT value of vCard and iCalendar content lines.
+fn text_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                c => out.extend(c),
+            },
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Percent-encode everything but the RFC 3986 unreserved characters.
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// Decode `%XX` escapes, leaving malformed ones as they are.
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Strip visual separators from a phone number, keeping a leading `+`.
+fn phone_number(s: &str) -> Result<String, String> {
+    let s = s.trim();
+    let (plus, rest) = match s.strip_prefix('+') {
+        Some(rest) => ("+", rest),
+        None => ("", s),
+    };
+    let mut digits = String::from(plus);
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '(' | ')' => {}
+            _ => return Err(format!("'{}' is not allowed in a phone number", c)),
+        }
+    }
+    if digits.len() == plus.len() {
+        return Err("phone number should contain digits".into());
+    }
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_separators() {
+        assert_eq!(
+            split_escaped(r"a;b\;c;;d\\;e", ';'),
+            ["a", r"b\;c", "", r"d\\", "e"]
+        );
+        assert_eq!(split_escaped("", ';'), [""]);
+        assert_eq!(split_escaped(r"trailing\", ';'), [r"trailing\"]);
+        let escaped = backslash_escape(r"a;b,c\d:e", &['\\', ';', ':']);
+        assert_eq!(escaped, r"a\;b,c\\d\:e");
+        assert_eq!(backslash_unescape(&escaped), r"a;b,c\d:e");
+        assert_eq!(split_escaped(&escaped, ';').len(), 1);
+    }
+
+    #[test]
+    fn text_values() {
+        let text = "a;b,c\\d\r\nline\nend";
+        assert_eq!(text_escape(text), r"a\;b\,c\\d\nline\nend");
+        assert_eq!(text_unescape(&text_escape(text)), "a;b,c\\d\nline\nend");
+        // Escapes that do not need one are tolerated, and `\N` is a newline.
+        assert_eq!(text_unescape(r"a\:b\Nc\"), "a:b\nc");
+    }
+
+    #[test]
+    fn percent_escapes() {
+        assert_eq!(percent_encode("a b&c=d/é~"), "a%20b%26c%3Dd%2F%C3%A9~");
+        assert_eq!(percent_decode(&percent_encode("a b&c=d/é~")), "a b&c=d/é~");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+
+    #[test]
+    fn phone_numbers() {
+        assert_eq!(
+            phone_number(" +1 (555) 010-99.99 ").unwrap(),
+            "+15550109999"
+        );
+        assert!(phone_number("+").is_err());
+        assert!(phone_number("555 CALL").is_err());
+    }
+
+    #[test]
+    fn kinds() {
+        let kinds = [
+            ("WIFI:S:home;;", "wifi"),
+            ("BEGIN:VCARD\r\nEND:VCARD", "vcard"),
+            ("mecard:N:Doe;;", "mecard"),
+            ("bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2", "payment"),
+            ("BCD\n002\n1\nSCT", "payment"),
+            ("[Interface]\nPrivateKey = x", "wireguard"),
+            ("https://www.nushell.sh", "url"),
+            ("[1, 2]", "json"),
+            ("hello", "text"),
+        ];
+        for (payload, kind) in kinds {
+            assert_eq!(super::kind(Some(payload)), kind, "{}", payload);
+        }
+        assert_eq!(super::kind(None), "binary");
+    }
+}
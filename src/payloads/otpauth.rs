@@ -0,0 +1,283 @@
+//! `otpauth://totp/<issuer>:<account>?secret=...` authenticator enrollment uri.
+
+use nu_protocol::{Span, Value};
+
+use super::{percent_decode, percent_encode, strip_prefix_ignore_case, Error};
+
+/// Normalize a base32 secret: drop spaces, dashes and padding, and uppercase it.
+fn normalize_secret(secret: &str) -> Result<String, String> {
+    let secret: String = secret
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '='))
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if secret.is_empty() {
+        return Err("secret should not be empty".into());
+    }
+    if let Some(c) = secret.chars().find(|c| !matches!(c, 'A'..='Z' | '2'..='7')) {
+        return Err(format!("'{}' is not a base32 character", c));
+    }
+    // a base32 block of 8 characters encodes 5 bytes; these remainders can't end on a byte
+    if matches!(secret.len() % 8, 1 | 3 | 6) {
+        return Err(format!(
+            "{} characters is not a valid base32 length",
+            secret.len()
+        ));
+    }
+    if secret.len() * 5 / 8 < 10 {
+        return Err("secret should be at least 80 bits (16 base32 characters)".into());
+    }
+    Ok(secret)
+}
+
+/// A time based one-time password, the flags of `to qr totp`.
+#[derive(Clone, Debug, Default)]
+pub struct Totp {
+    pub issuer: Option<String>,
+    pub account: String,
+    /// Shared secret in base32.
+    pub secret: String,
+    /// Length of the codes, 6 by default.
+    pub digits: Option<i64>,
+    /// Seconds a code is valid, 30 by default.
+    pub period: Option<i64>,
+    /// `SHA1` (the default), `SHA256` or `SHA512`.
+    pub algorithm: Option<String>,
+}
+
+/// Build the payload of `totp`.
+pub fn build(totp: &Totp) -> Result<String, Error> {
+    let Totp {
+        issuer, account, ..
+    } = totp;
+    let digits = totp.digits.unwrap_or(6);
+    let period = totp.period.unwrap_or(30);
+    let secret = normalize_secret(&totp.secret).map_err(|e| Error::new("Invalid secret", e))?;
+    if account.is_empty() {
+        return Err(Error::new("Invalid account", "account should not be empty"));
+    }
+    if issuer.as_deref().unwrap_or_default().contains(':') || account.contains(':') {
+        return Err(Error::new(
+            "Invalid label",
+            "issuer and account can not contain ':'",
+        ));
+    }
+    if !(6..=8).contains(&digits) {
+        return Err(Error::new("Invalid digits", "should be between 6 and 8"));
+    }
+    if period <= 0 {
+        return Err(Error::new(
+            "Invalid period",
+            "should be a positive number of seconds",
+        ));
+    }
+    let algorithm = match totp.algorithm.as_ref().map(|x| x.to_uppercase()).as_deref() {
+        Some("SHA1") | None => "SHA1",
+        Some("SHA256") => "SHA256",
+        Some("SHA512") => "SHA512",
+        _ => {
+            return Err(Error::new(
+                "Unknown algorithm parameter",
+                "should be one of SHA1, SHA256, SHA512",
+            ))
+        }
+    };
+
+    let mut payload = match issuer {
+        Some(issuer) => format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}",
+            percent_encode(issuer),
+            percent_encode(account),
+            secret,
+            percent_encode(issuer)
+        ),
+        None => format!(
+            "otpauth://totp/{}?secret={}",
+            percent_encode(account),
+            secret
+        ),
+    };
+    payload.push_str(&format!(
+        "&algorithm={}&digits={}&period={}",
+        algorithm, digits, period
+    ));
+    Ok(payload)
+}
+
+/// Read a uri into `{type, issuer, account, secret, digits, period, algorithm}`,
+/// with the defaults of authenticator apps for missing parameters.
+///
+/// Hotp codes have a `counter` instead of a `period`.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let rest = strip_prefix_ignore_case(payload, "otpauth://")?;
+    let (kind, rest) = rest.split_once('/')?;
+    let kind = kind.to_lowercase();
+    if kind != "totp" && kind != "hotp" {
+        return None;
+    }
+    let (label, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let params: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .map(|(k, v)| (k.to_lowercase(), v.into_owned()))
+        .collect();
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    let label = percent_decode(label);
+    let (label_issuer, account) = match label.split_once(':') {
+        Some((issuer, account)) => (Some(issuer.trim()), account.trim()),
+        None => (None, label.trim()),
+    };
+    let string = |val: &str| Value::String {
+        val: val.into(),
+        span,
+    };
+    let int = |key: &str, default: i64| Value::Int {
+        val: param(key).and_then(|v| v.parse().ok()).unwrap_or(default),
+        span,
+    };
+    let (counter_col, counter) = match kind.as_str() {
+        "hotp" => ("counter", int("counter", 0)),
+        _ => ("period", int("period", 30)),
+    };
+    Some(Value::Record {
+        cols: vec![
+            "type".into(),
+            "issuer".into(),
+            "account".into(),
+            "secret".into(),
+            "digits".into(),
+            counter_col.into(),
+            "algorithm".into(),
+        ],
+        vals: vec![
+            string(&kind),
+            // The parameter is preferred over the prefix of the label.
+            match param("issuer").or(label_issuer) {
+                Some(issuer) => string(issuer),
+                None => Value::Nothing { span },
+            },
+            string(account),
+            match param("secret") {
+                Some(secret) => string(secret),
+                None => Value::Nothing { span },
+            },
+            int("digits", 6),
+            counter,
+            string(&param("algorithm").unwrap_or("SHA1").to_uppercase()),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totp() -> Totp {
+        Totp {
+            issuer: Some("ACME Co/Dev & Ops".into()),
+            account: "jane@example.com".into(),
+            secret: "jbsw y3dp-ehpk 3pxp==".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let payload = build(&totp()).unwrap();
+        assert_eq!(
+            payload,
+            "otpauth://totp/ACME%20Co%2FDev%20%26%20Ops:jane%40example.com?secret=JBSWY3DPEHPK3PXP&issuer=ACME%20Co%2FDev%20%26%20Ops&algorithm=SHA1&digits=6&period=30"
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("type"), Value::test_string("totp"));
+        assert_eq!(column("issuer"), Value::test_string("ACME Co/Dev & Ops"));
+        assert_eq!(column("account"), Value::test_string("jane@example.com"));
+        assert_eq!(column("secret"), Value::test_string("JBSWY3DPEHPK3PXP"));
+        assert_eq!(column("digits"), Value::test_int(6));
+        assert_eq!(column("period"), Value::test_int(30));
+        assert_eq!(column("algorithm"), Value::test_string("SHA1"));
+    }
+
+    #[test]
+    fn parameters() {
+        let totp = Totp {
+            issuer: None,
+            digits: Some(8),
+            period: Some(60),
+            algorithm: Some("sha512".into()),
+            ..totp()
+        };
+        let payload = build(&totp).unwrap();
+        assert!(payload.starts_with("otpauth://totp/jane%40example.com?secret="));
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("issuer"), Value::test_nothing());
+        assert_eq!(column("digits"), Value::test_int(8));
+        assert_eq!(column("period"), Value::test_int(60));
+        assert_eq!(column("algorithm"), Value::test_string("SHA512"));
+
+        let hotp = parse(
+            "otpauth://hotp/Bank:me?secret=ABC&counter=7",
+            Span::test_data(),
+        )
+        .unwrap();
+        assert_eq!(
+            hotp.get_data_by_key("issuer"),
+            Some(Value::test_string("Bank"))
+        );
+        assert_eq!(hotp.get_data_by_key("counter"), Some(Value::test_int(7)));
+        assert!(parse("otpauth://other/x", Span::test_data()).is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        let label = |totp: Totp| build(&totp).unwrap_err().label;
+        assert_eq!(
+            label(Totp {
+                secret: "JBSWY3DP1".into(),
+                ..totp()
+            }),
+            "Invalid secret"
+        );
+        assert_eq!(
+            label(Totp {
+                secret: "JBSWY3DP".into(),
+                ..totp()
+            }),
+            "Invalid secret"
+        );
+        assert_eq!(
+            label(Totp {
+                account: "a:b".into(),
+                ..totp()
+            }),
+            "Invalid label"
+        );
+        assert_eq!(
+            label(Totp {
+                digits: Some(5),
+                ..totp()
+            }),
+            "Invalid digits"
+        );
+        assert_eq!(
+            label(Totp {
+                period: Some(0),
+                ..totp()
+            }),
+            "Invalid period"
+        );
+        assert_eq!(
+            label(Totp {
+                algorithm: Some("MD5".into()),
+                ..totp()
+            }),
+            "Unknown algorithm parameter"
+        );
+    }
+}
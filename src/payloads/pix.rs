@@ -0,0 +1,336 @@
+//! PIX "BR Code", an EMV QRCPS merchant presented payload.
+//!
+//! Fields can be given as flags or as columns of the input record, flags win.
+
+use nu_protocol::{Span, Value};
+
+use super::Error;
+
+/// CRC-16/CCITT-FALSE, as required for the EMV `63` field.
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for b in data {
+        crc ^= (*b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Encode one EMV id/length/value field.
+fn tlv(id: &str, value: &str) -> String {
+    format!("{}{:02}{}", id, value.len(), value)
+}
+
+/// Split EMV id/length/value fields, `None` if they do not add up.
+fn fields(mut s: &str) -> Option<Vec<(&str, &str)>> {
+    let mut v = vec![];
+    while !s.is_empty() {
+        let id = s.get(..2)?;
+        let len: usize = s.get(2..4)?.parse().ok()?;
+        v.push((id, s.get(4..4 + len)?));
+        s = &s[4 + len..];
+    }
+    Some(v)
+}
+
+/// The value of the field `id`.
+fn find<'a>(fields: &[(&str, &'a str)], id: &str) -> Option<&'a str> {
+    fields.iter().find(|(i, _)| *i == id).map(|(_, v)| *v)
+}
+
+/// Replace Portuguese accented letters, which scanners are not required to support.
+fn ascii_fold(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' | 'ä' => 'a',
+            'Á' | 'À' | 'Â' | 'Ã' | 'Ä' => 'A',
+            'é' | 'ê' | 'è' => 'e',
+            'É' | 'Ê' | 'È' => 'E',
+            'í' | 'ì' => 'i',
+            'Í' | 'Ì' => 'I',
+            'ó' | 'ô' | 'õ' | 'ò' => 'o',
+            'Ó' | 'Ô' | 'Õ' | 'Ò' => 'O',
+            'ú' | 'ü' | 'ù' => 'u',
+            'Ú' | 'Ü' | 'Ù' => 'U',
+            'ç' => 'c',
+            'Ç' => 'C',
+            c => c,
+        })
+        .collect()
+}
+
+/// A payment to a merchant, the flags or input columns of `to qr pix`.
+#[derive(Clone, Debug, Default)]
+pub struct Pix {
+    /// PIX key of the merchant: a tax id, phone number, email or random key.
+    pub key: Option<String>,
+    pub name: Option<String>,
+    pub city: Option<String>,
+    pub description: Option<String>,
+    /// Transaction id, `***` if there is none.
+    pub txid: Option<String>,
+    /// Amount in reais.
+    pub amount: Option<f64>,
+}
+
+/// Build the payload of `pix`.
+pub fn build(pix: &Pix) -> Result<String, Error> {
+    let text = |label: &str, value: &Option<String>, max: usize| match value {
+        Some(v) => {
+            let v = ascii_fold(v.trim());
+            if !v.is_ascii() {
+                Err(Error::new(label, "should only contain ASCII characters"))
+            } else if v.len() > max {
+                Err(Error::new(
+                    label,
+                    format!("should be at most {} characters", max),
+                ))
+            } else {
+                Ok(Some(v))
+            }
+        }
+        None => Ok(None),
+    };
+
+    let key = text("Invalid key", &pix.key, 77)?
+        .ok_or_else(|| Error::new("Missing key", "a PIX key is required"))?;
+    let name = text("Invalid name", &pix.name, 25)?
+        .ok_or_else(|| Error::new("Missing name", "a merchant name is required"))?;
+    let city = text("Invalid city", &pix.city, 15)?
+        .ok_or_else(|| Error::new("Missing city", "a merchant city is required"))?;
+    let description = text("Invalid description", &pix.description, 72)?;
+    let txid = text("Invalid txid", &pix.txid, 25)?.unwrap_or_else(|| "***".into());
+    if txid != "***" && !txid.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(Error::new(
+            "Invalid txid",
+            "should only contain letters and digits",
+        ));
+    }
+
+    let mut account = tlv("00", "br.gov.bcb.pix") + &tlv("01", &key);
+    if let Some(description) = description {
+        account += &tlv("02", &description);
+    }
+    if account.len() > 99 {
+        return Err(Error::new(
+            "Payload too large",
+            "key and description together are too long",
+        ));
+    }
+    let mut payload =
+        tlv("00", "01") + &tlv("26", &account) + &tlv("52", "0000") + &tlv("53", "986");
+    if let Some(amount) = pix.amount {
+        if !(amount > 0.0 && amount < 1e10) {
+            return Err(Error::new("Invalid amount", "should be a positive amount"));
+        }
+        payload += &tlv("54", &format!("{:.2}", amount));
+    }
+    payload += &tlv("58", "BR");
+    payload += &tlv("59", &name);
+    payload += &tlv("60", &city);
+    payload += &tlv("62", &tlv("05", &txid));
+    payload += "6304";
+    let crc = crc16(payload.as_bytes());
+    Ok(format!("{}{:04X}", payload, crc))
+}
+
+/// Read a BR Code into `{key, name, city, amount, description, txid, url,
+/// errors}`, the fields of `to qr pix` and the `url` of dynamic codes.
+///
+/// A checksum that does not match is reported in `errors`, which is empty
+/// for a valid code.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let payload = payload.trim();
+    if !payload.starts_with("000201") {
+        return None;
+    }
+    let top = fields(payload)?;
+    // The merchant account is one of the templates 26 to 51, told apart by
+    // their globally unique identifier.
+    let account = top
+        .iter()
+        .filter(|(id, _)| ("26".."52").contains(id))
+        .filter_map(|(_, v)| fields(v))
+        .find(
+            |f| matches!(find(f, "00"), Some(gui) if gui.eq_ignore_ascii_case("br.gov.bcb.pix")),
+        )?;
+    let mut errors = vec![];
+    match (top.last(), payload.len().checked_sub(4)) {
+        (Some(("63", crc)), Some(end)) => {
+            let expected = format!("{:04X}", crc16(&payload.as_bytes()[..end]));
+            if !crc.eq_ignore_ascii_case(&expected) {
+                errors.push(format!("crc: is {}, expected {}", crc, expected));
+            }
+        }
+        _ => errors.push("crc: missing, it should be the last field".to_string()),
+    }
+    if find(&top, "53").is_some_and(|currency| currency != "986") {
+        errors.push("currency: should be 986 (BRL)".to_string());
+    }
+    let string = |val: Option<&str>| match val {
+        Some(val) => Value::String {
+            val: val.into(),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    let additional = find(&top, "62").and_then(fields).unwrap_or_default();
+    Some(Value::Record {
+        cols: [
+            "key",
+            "name",
+            "city",
+            "amount",
+            "description",
+            "txid",
+            "url",
+            "errors",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            string(find(&account, "01")),
+            string(find(&top, "59")),
+            string(find(&top, "60")),
+            match find(&top, "54").and_then(|am| am.parse().ok()) {
+                Some(val) => Value::Float { val, span },
+                None => Value::Nothing { span },
+            },
+            string(find(&account, "02")),
+            string(find(&additional, "05")),
+            string(find(&account, "25")),
+            Value::List {
+                vals: errors
+                    .into_iter()
+                    .map(|val| Value::String { val, span })
+                    .collect(),
+                span,
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pix() -> Pix {
+        Pix {
+            key: Some("123e4567-e12b-12d1-a456-426655440000".into()),
+            name: Some("Fulano de Tal".into()),
+            city: Some("BRASILIA".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn crc() {
+        // The check value of CRC-16/CCITT-FALSE.
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(b""), 0xFFFF);
+    }
+
+    #[test]
+    fn fields_of_tlv() {
+        assert_eq!(tlv("05", "***"), "0503***");
+        assert_eq!(
+            tlv("26", &"x".repeat(10)),
+            format!("2610{}", "x".repeat(10))
+        );
+        assert_eq!(
+            fields("0002010503***").unwrap(),
+            [("00", "01"), ("05", "***")]
+        );
+        // A length running past the end, or not a number.
+        assert!(fields("0005abc").is_none());
+        assert!(fields("00x1a").is_none());
+        assert!(fields("0").is_none());
+    }
+
+    #[test]
+    fn manual_example() {
+        // The static code of the example in the BR Code manual.
+        assert_eq!(
+            build(&pix()).unwrap(),
+            "00020126580014br.gov.bcb.pix0136123e4567-e12b-12d1-a456-4266554400005204000053039865802BR5913Fulano de Tal6008BRASILIA62070503***63041D3D"
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let pix = Pix {
+            name: Some("João Conceição".into()),
+            city: Some(" São Paulo ".into()),
+            description: Some("Pedido 42".into()),
+            txid: Some("PEDIDO42".into()),
+            amount: Some(10.5),
+            ..pix()
+        };
+        let payload = build(&pix).unwrap();
+        assert!(payload.contains("540510.50"), "{}", payload);
+        let record = parse(&payload, Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("key"), Value::test_string(pix.key.unwrap()));
+        assert_eq!(column("name"), Value::test_string("Joao Conceicao"));
+        assert_eq!(column("city"), Value::test_string("Sao Paulo"));
+        assert_eq!(column("amount"), Value::test_float(10.5));
+        assert_eq!(column("description"), Value::test_string("Pedido 42"));
+        assert_eq!(column("txid"), Value::test_string("PEDIDO42"));
+        assert_eq!(column("url"), Value::test_nothing());
+        assert_eq!(column("errors"), Value::test_list(vec![]));
+
+        // A changed amount no longer matches the checksum.
+        let tampered = payload.replace("540510.50", "540590.50");
+        let record = parse(&tampered, Span::test_data()).unwrap();
+        let errors = record.get_data_by_key("errors").unwrap();
+        assert_eq!(errors.as_list().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn invalid() {
+        let label = |pix: Pix| build(&pix).unwrap_err().label;
+        assert_eq!(label(Pix { key: None, ..pix() }), "Missing key");
+        assert_eq!(
+            label(Pix {
+                city: Some("Cidade Muito Grande".into()),
+                ..pix()
+            }),
+            "Invalid city"
+        );
+        assert_eq!(
+            label(Pix {
+                name: Some("Fulano ☃".into()),
+                ..pix()
+            }),
+            "Invalid name"
+        );
+        assert_eq!(
+            label(Pix {
+                txid: Some("not-alnum".into()),
+                ..pix()
+            }),
+            "Invalid txid"
+        );
+        assert_eq!(
+            label(Pix {
+                amount: Some(-1.0),
+                ..pix()
+            }),
+            "Invalid amount"
+        );
+        assert_eq!(
+            label(Pix {
+                description: Some("x".repeat(40)),
+                ..pix()
+            }),
+            "Payload too large"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! SMART Health Cards: `shc:/` followed by the digits of a signed (JWS),
+//! deflate compressed FHIR bundle.
+//!
+//! The signature is not verified, and cards split over several codes
+//! (`shc:/1/2/...`) are not joined.
+
+use std::io::Read;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+use nu_protocol::{Span, Value};
+
+use crate::serialize;
+
+/// Cards fit in a single qr code, so anything far beyond is not one.
+const MAX_SIZE: u64 = 64 * 1024;
+
+/// Read a card into `{issuer, issued, kid, algorithm, types, bundle}`, with
+/// the FHIR bundle of the credential as a record.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let digits = payload.trim().strip_prefix("shc:/")?.as_bytes();
+    if digits.is_empty() || digits.len() % 2 != 0 {
+        return None;
+    }
+    // Every pair of digits is a character of the JWS, offset by 45.
+    let jws = digits
+        .chunks(2)
+        .map(|pair| {
+            let n = std::str::from_utf8(pair).ok()?.parse::<u8>().ok()?;
+            Some(char::from(n.checked_add(45)?))
+        })
+        .collect::<Option<String>>()?;
+    let [header, body, _signature] = jws.split('.').collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let header: serde_json::Value =
+        serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).ok()?).ok()?;
+    let compressed = URL_SAFE_NO_PAD.decode(body).ok()?;
+    let mut json = vec![];
+    flate2::read::DeflateDecoder::new(&compressed[..])
+        .take(MAX_SIZE)
+        .read_to_end(&mut json)
+        .ok()?;
+    let mut claims: serde_json::Value = serde_json::from_slice(&json).ok()?;
+    let string = |val: Option<&str>| match val {
+        Some(val) => Value::String {
+            val: val.into(),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    let issued = match claims["nbf"].as_f64() {
+        Some(nbf) => match Utc.timestamp_opt(nbf as i64, 0).single() {
+            Some(time) => Value::Date {
+                val: time.fixed_offset(),
+                span,
+            },
+            None => Value::Nothing { span },
+        },
+        None => Value::Nothing { span },
+    };
+    let types = serialize::from_json(claims["vc"]["type"].take(), span);
+    let bundle = serialize::from_json(claims["vc"]["credentialSubject"]["fhirBundle"].take(), span);
+    Some(Value::Record {
+        cols: ["issuer", "issued", "kid", "algorithm", "types", "bundle"]
+            .map(String::from)
+            .to_vec(),
+        vals: vec![
+            string(claims["iss"].as_str()),
+            issued,
+            string(header["kid"].as_str()),
+            string(header["alg"].as_str()),
+            types,
+            bundle,
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// The numeric qr payload of a JWS with a deflated body.
+    fn card(header: &str, claims: &str) -> String {
+        let mut deflate =
+            flate2::write::DeflateEncoder::new(vec![], flate2::Compression::default());
+        deflate.write_all(claims.as_bytes()).unwrap();
+        let jws = format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(header),
+            URL_SAFE_NO_PAD.encode(deflate.finish().unwrap()),
+            URL_SAFE_NO_PAD.encode("signature"),
+        );
+        let digits: String = jws.bytes().map(|b| format!("{:02}", b - 45)).collect();
+        format!("shc:/{}", digits)
+    }
+
+    #[test]
+    fn health_card() {
+        let payload = card(
+            r#"{"zip":"DEF","alg":"ES256","kid":"key-1"}"#,
+            r#"{"iss":"https://spec.smarthealth.cards/examples/issuer","nbf":1600000000,
+                "vc":{"type":["https://smarthealth.cards#health-card"],
+                "credentialSubject":{"fhirVersion":"4.0.1",
+                "fhirBundle":{"resourceType":"Bundle","type":"collection"}}}}"#,
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["issuer", "issued", "kid", "algorithm", "types", "bundle"],
+                vec![
+                    Value::test_string("https://spec.smarthealth.cards/examples/issuer"),
+                    Value::test_date(Utc.timestamp_opt(1_600_000_000, 0).unwrap().fixed_offset()),
+                    Value::test_string("key-1"),
+                    Value::test_string("ES256"),
+                    Value::test_list(vec![Value::test_string(
+                        "https://smarthealth.cards#health-card"
+                    )]),
+                    Value::test_record(
+                        vec!["resourceType", "type"],
+                        vec![
+                            Value::test_string("Bundle"),
+                            Value::test_string("collection")
+                        ]
+                    ),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn not_cards() {
+        let parse = |s: &str| parse(s, Span::test_data());
+        let payload = card(r#"{"alg":"ES256"}"#, r#"{"iss":"x"}"#);
+        assert!(parse(&payload).is_some());
+        // An odd number of digits, a pair above 'z' and a missing part.
+        assert!(parse(&payload[..payload.len() - 1]).is_none());
+        assert!(parse("shc:/99").is_none());
+        let dot = (5..payload.len())
+            .step_by(2)
+            .rfind(|i| &payload[*i..i + 2] == "01")
+            .unwrap();
+        assert!(parse(&payload[..dot]).is_none());
+        assert!(parse("shc:/").is_none());
+        assert!(parse("shc:/1/2/0000").is_none());
+    }
+}
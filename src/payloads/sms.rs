@@ -0,0 +1,136 @@
+//! `SMSTO:<number>:<message>` and `sms:<number>?body=<message>` text message payloads.
+
+use nu_protocol::{Span, Value};
+
+use super::{percent_decode, percent_encode, phone_number, strip_prefix_ignore_case, Error};
+
+/// A text message to send, the flags of `to qr sms`.
+#[derive(Clone, Debug, Default)]
+pub struct Sms {
+    pub number: String,
+    pub message: Option<String>,
+    /// `SMSTO` (the default) or `sms`.
+    pub format: Option<String>,
+}
+
+/// Build the payload of `sms`.
+pub fn build(sms: &Sms) -> Result<String, Error> {
+    let number = phone_number(&sms.number).map_err(|e| Error::new("Invalid phone number", e))?;
+    match sms.format.as_ref().map(|x| x.to_uppercase()).as_deref() {
+        // everything after the second colon is the message, so nothing needs escaping
+        Some("SMSTO") | None => Ok(match &sms.message {
+            Some(message) => format!("SMSTO:{}:{}", number, message),
+            None => format!("SMSTO:{}", number),
+        }),
+        Some("SMS") => Ok(match &sms.message {
+            Some(message) => format!("sms:{}?body={}", number, percent_encode(message)),
+            None => format!("sms:{}", number),
+        }),
+        _ => Err(Error::new(
+            "Unknown format parameter",
+            "should be one of SMSTO, sms",
+        )),
+    }
+}
+
+/// Read either format into `{number, message}`, with the number stripped of
+/// visual separators.
+///
+/// A number that is not one (say a short code with letters) is kept as is.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let (number, message) = if let Some(rest) = strip_prefix_ignore_case(payload, "SMSTO:") {
+        match rest.split_once(':') {
+            Some((number, message)) => (number, Some(message.to_string())),
+            None => (rest, None),
+        }
+    } else {
+        let rest = strip_prefix_ignore_case(payload, "sms:")?;
+        let (number, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let body = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| key.eq_ignore_ascii_case("body"))
+            .map(|(_, value)| percent_decode(value));
+        (number, body)
+    };
+    Some(Value::Record {
+        cols: vec!["number".into(), "message".into()],
+        vals: vec![
+            Value::String {
+                val: phone_number(number).unwrap_or_else(|_| number.trim().to_string()),
+                span,
+            },
+            match message {
+                Some(val) => Value::String { val, span },
+                None => Value::Nothing { span },
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(sms: &Sms) -> (String, Value) {
+        let payload = build(sms).unwrap();
+        let record = parse(&payload, Span::test_data()).unwrap();
+        (payload, record)
+    }
+
+    #[test]
+    fn formats() {
+        let sms = Sms {
+            number: "+1 (555) 010-0100".into(),
+            message: Some("meet at 10:30? a&b".into()),
+            format: None,
+        };
+        let (payload, record) = roundtrip(&sms);
+        assert_eq!(payload, "SMSTO:+15550100100:meet at 10:30? a&b");
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["number", "message"],
+                vec![
+                    Value::test_string("+15550100100"),
+                    Value::test_string("meet at 10:30? a&b")
+                ]
+            )
+        );
+        let sms = Sms {
+            format: Some("sms".into()),
+            ..sms
+        };
+        let (payload, same) = roundtrip(&sms);
+        assert_eq!(
+            payload,
+            "sms:+15550100100?body=meet%20at%2010%3A30%3F%20a%26b"
+        );
+        assert_eq!(same, record);
+        let (payload, record) = roundtrip(&Sms {
+            message: None,
+            ..sms
+        });
+        assert_eq!(payload, "sms:+15550100100");
+        assert_eq!(
+            record.get_data_by_key("message"),
+            Some(Value::test_nothing())
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let sms = Sms {
+            number: "555-CALL".into(),
+            ..Default::default()
+        };
+        assert_eq!(build(&sms).unwrap_err().label, "Invalid phone number");
+        let sms = Sms {
+            number: "5550100".into(),
+            format: Some("mms".into()),
+            ..Default::default()
+        };
+        assert_eq!(build(&sms).unwrap_err().label, "Unknown format parameter");
+    }
+}
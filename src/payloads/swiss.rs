@@ -0,0 +1,222 @@
+//! Swiss QR-bill (`SPC`), the payment part of Swiss invoices.
+
+use nu_protocol::{Span, Value};
+
+/// Read the address block that starts at `lines[0]`, `None` when it is empty.
+///
+/// Structured addresses (`S`) have a street, building number, postal code and
+/// town; combined ones (`K`) two free address lines.
+fn address(lines: &[&str], span: Span) -> Option<Value> {
+    let field = |i: usize| match lines.get(i).copied().unwrap_or_default() {
+        "" => Value::Nothing { span },
+        val => Value::String {
+            val: val.to_string(),
+            span,
+        },
+    };
+    let (cols, vals) = match lines.first().copied().unwrap_or_default() {
+        "S" => (
+            vec!["name", "street", "building", "postal", "town", "country"],
+            vec![field(1), field(2), field(3), field(4), field(5), field(6)],
+        ),
+        "K" => (
+            vec!["name", "line1", "line2", "country"],
+            vec![field(1), field(2), field(3), field(6)],
+        ),
+        _ => return None,
+    };
+    Some(Value::Record {
+        cols: cols.into_iter().map(String::from).collect(),
+        vals,
+        span,
+    })
+}
+
+/// Read a payload into `{account, creditor, ultimate_creditor, amount,
+/// currency, debtor, reference_type, reference, message, billing,
+/// alternatives}`, with the parties as address records.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let lines: Vec<&str> = payload.lines().map(|l| l.trim()).collect();
+    if lines.len() < 31 || lines[0] != "SPC" || !lines[1].starts_with("02") || lines[30] != "EPD" {
+        return None;
+    }
+    let string = |val: &str| match val {
+        "" => Value::Nothing { span },
+        val => Value::String {
+            val: val.to_string(),
+            span,
+        },
+    };
+    let party =
+        |start: usize| address(&lines[start..start + 7], span).unwrap_or(Value::Nothing { span });
+    Some(Value::Record {
+        cols: [
+            "account",
+            "creditor",
+            "ultimate_creditor",
+            "amount",
+            "currency",
+            "debtor",
+            "reference_type",
+            "reference",
+            "message",
+            "billing",
+            "alternatives",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            string(&lines[3].replace(' ', "")),
+            party(4),
+            party(11),
+            match lines[18].parse() {
+                Ok(val) => Value::Float { val, span },
+                Err(_) => Value::Nothing { span },
+            },
+            string(lines[19]),
+            party(20),
+            string(lines[27]),
+            string(lines[28]),
+            string(lines[29]),
+            string(lines.get(31).copied().unwrap_or_default()),
+            Value::List {
+                vals: lines
+                    .iter()
+                    .skip(32)
+                    .filter(|l| !l.is_empty())
+                    .map(|l| string(l))
+                    .collect(),
+                span,
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The lines of a QR-bill with a QR reference.
+    fn bill() -> Vec<&'static str> {
+        vec![
+            "SPC",
+            "0200",
+            "1",
+            "CH44 3199 9123 0008 8901 2",
+            "S",
+            "Robert Schneider AG",
+            "Rue du Lac",
+            "1268",
+            "2501",
+            "Biel",
+            "CH",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "1949.75",
+            "CHF",
+            "K",
+            "Pia-Maria Rutschmann-Schnyder",
+            "Grosse Marktgasse 28",
+            "9400 Rorschach",
+            "",
+            "",
+            "CH",
+            "QRR",
+            "210000000003139471430009017",
+            "Order of 15 June 2020",
+            "EPD",
+            "//S1/10/10201409/11/200701/20/140.000-53",
+            "eBill/B/41010560425610173",
+        ]
+    }
+
+    #[test]
+    fn qr_bill() {
+        let record = parse(&bill().join("\r\n"), Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(
+            column("account"),
+            Value::test_string("CH4431999123000889012")
+        );
+        assert_eq!(
+            column("creditor"),
+            Value::test_record(
+                vec!["name", "street", "building", "postal", "town", "country"],
+                vec![
+                    Value::test_string("Robert Schneider AG"),
+                    Value::test_string("Rue du Lac"),
+                    Value::test_string("1268"),
+                    Value::test_string("2501"),
+                    Value::test_string("Biel"),
+                    Value::test_string("CH"),
+                ]
+            )
+        );
+        assert_eq!(column("ultimate_creditor"), Value::test_nothing());
+        assert_eq!(column("amount"), Value::test_float(1949.75));
+        assert_eq!(column("currency"), Value::test_string("CHF"));
+        assert_eq!(
+            column("debtor"),
+            Value::test_record(
+                vec!["name", "line1", "line2", "country"],
+                vec![
+                    Value::test_string("Pia-Maria Rutschmann-Schnyder"),
+                    Value::test_string("Grosse Marktgasse 28"),
+                    Value::test_string("9400 Rorschach"),
+                    Value::test_string("CH"),
+                ]
+            )
+        );
+        assert_eq!(column("reference_type"), Value::test_string("QRR"));
+        assert_eq!(
+            column("reference"),
+            Value::test_string("210000000003139471430009017")
+        );
+        assert_eq!(
+            column("message"),
+            Value::test_string("Order of 15 June 2020")
+        );
+        assert_eq!(
+            column("billing"),
+            Value::test_string("//S1/10/10201409/11/200701/20/140.000-53")
+        );
+        assert_eq!(
+            column("alternatives"),
+            Value::test_list(vec![Value::test_string("eBill/B/41010560425610173")])
+        );
+    }
+
+    #[test]
+    fn open_amount() {
+        // No amount, debtor, billing information or alternatives.
+        let mut lines = bill();
+        lines.truncate(31);
+        lines[18] = "";
+        for line in &mut lines[20..27] {
+            *line = "";
+        }
+        let record = parse(&lines.join("\n"), Span::test_data()).unwrap();
+        let column = |name: &str| record.get_data_by_key(name).unwrap();
+        assert_eq!(column("amount"), Value::test_nothing());
+        assert_eq!(column("debtor"), Value::test_nothing());
+        assert_eq!(column("billing"), Value::test_nothing());
+        assert_eq!(column("alternatives"), Value::test_list(vec![]));
+    }
+
+    #[test]
+    fn invalid() {
+        let mut lines = bill();
+        lines[1] = "0100";
+        assert!(parse(&lines.join("\n"), Span::test_data()).is_none());
+        let mut lines = bill();
+        lines[30] = "END";
+        assert!(parse(&lines.join("\n"), Span::test_data()).is_none());
+        assert!(parse(&bill()[..30].join("\n"), Span::test_data()).is_none());
+    }
+}
@@ -0,0 +1,188 @@
+//! `tel:<number>` call uri, optionally normalized to E.164.
+
+use nu_protocol::{Span, Value};
+
+use super::{percent_decode, phone_number, strip_prefix_ignore_case, Error};
+
+/// ISO 3166-1 alpha-2 code, country calling code and national trunk prefix.
+#[rustfmt::skip]
+const COUNTRIES: &[(&str, &str, &str)] = &[
+    ("AD", "376", ""), ("AE", "971", "0"), ("AF", "93", "0"), ("AG", "1", "1"), ("AI", "1", "1"),
+    ("AL", "355", "0"), ("AM", "374", "0"), ("AO", "244", ""), ("AR", "54", "0"), ("AS", "1", "1"),
+    ("AT", "43", "0"), ("AU", "61", "0"), ("AW", "297", ""), ("AZ", "994", "0"), ("BA", "387", "0"),
+    ("BB", "1", "1"), ("BD", "880", "0"), ("BE", "32", "0"), ("BF", "226", ""), ("BG", "359", "0"),
+    ("BH", "973", ""), ("BI", "257", ""), ("BJ", "229", ""), ("BM", "1", "1"), ("BN", "673", ""),
+    ("BO", "591", "0"), ("BR", "55", "0"), ("BS", "1", "1"), ("BT", "975", ""), ("BW", "267", ""),
+    ("BY", "375", "8"), ("BZ", "501", ""), ("CA", "1", "1"), ("CD", "243", "0"), ("CF", "236", ""),
+    ("CG", "242", ""), ("CH", "41", "0"), ("CI", "225", ""), ("CL", "56", ""), ("CM", "237", ""),
+    ("CN", "86", "0"), ("CO", "57", ""), ("CR", "506", ""), ("CU", "53", "0"), ("CV", "238", ""),
+    ("CY", "357", ""), ("CZ", "420", ""), ("DE", "49", "0"), ("DJ", "253", ""), ("DK", "45", ""),
+    ("DM", "1", "1"), ("DO", "1", "1"), ("DZ", "213", "0"), ("EC", "593", "0"), ("EE", "372", ""),
+    ("EG", "20", "0"), ("ER", "291", "0"), ("ES", "34", ""), ("ET", "251", "0"), ("FI", "358", "0"),
+    ("FJ", "679", ""), ("FM", "691", ""), ("FO", "298", ""), ("FR", "33", "0"), ("GA", "241", ""),
+    ("GB", "44", "0"), ("GD", "1", "1"), ("GE", "995", "0"), ("GH", "233", "0"), ("GI", "350", ""),
+    ("GL", "299", ""), ("GM", "220", ""), ("GN", "224", ""), ("GQ", "240", ""), ("GR", "30", ""),
+    ("GT", "502", ""), ("GU", "1", "1"), ("GW", "245", ""), ("GY", "592", ""), ("HK", "852", ""),
+    ("HN", "504", ""), ("HR", "385", "0"), ("HT", "509", ""), ("HU", "36", "06"), ("ID", "62", "0"),
+    ("IE", "353", "0"), ("IL", "972", "0"), ("IN", "91", "0"), ("IQ", "964", "0"), ("IR", "98", "0"),
+    ("IS", "354", ""), ("IT", "39", ""), ("JM", "1", "1"), ("JO", "962", "0"), ("JP", "81", "0"),
+    ("KE", "254", "0"), ("KG", "996", "0"), ("KH", "855", "0"), ("KN", "1", "1"), ("KR", "82", "0"),
+    ("KW", "965", ""), ("KY", "1", "1"), ("KZ", "7", "8"), ("LA", "856", "0"), ("LB", "961", "0"),
+    ("LC", "1", "1"), ("LI", "423", ""), ("LK", "94", "0"), ("LR", "231", "0"), ("LS", "266", ""),
+    ("LT", "370", "8"), ("LU", "352", ""), ("LV", "371", ""), ("LY", "218", "0"), ("MA", "212", "0"),
+    ("MC", "377", ""), ("MD", "373", "0"), ("ME", "382", "0"), ("MG", "261", "0"), ("MK", "389", "0"),
+    ("ML", "223", ""), ("MM", "95", "0"), ("MN", "976", "0"), ("MO", "853", ""), ("MP", "1", "1"),
+    ("MR", "222", ""), ("MS", "1", "1"), ("MT", "356", ""), ("MU", "230", ""), ("MV", "960", ""),
+    ("MW", "265", "0"), ("MX", "52", ""), ("MY", "60", "0"), ("MZ", "258", ""), ("NA", "264", "0"),
+    ("NE", "227", ""), ("NG", "234", "0"), ("NI", "505", ""), ("NL", "31", "0"), ("NO", "47", ""),
+    ("NP", "977", "0"), ("NZ", "64", "0"), ("OM", "968", ""), ("PA", "507", ""), ("PE", "51", "0"),
+    ("PG", "675", ""), ("PH", "63", "0"), ("PK", "92", "0"), ("PL", "48", ""), ("PR", "1", "1"),
+    ("PS", "970", "0"), ("PT", "351", ""), ("PY", "595", "0"), ("QA", "974", ""), ("RO", "40", "0"),
+    ("RS", "381", "0"), ("RU", "7", "8"), ("RW", "250", ""), ("SA", "966", "0"), ("SB", "677", ""),
+    ("SC", "248", ""), ("SD", "249", "0"), ("SE", "46", "0"), ("SG", "65", ""), ("SI", "386", "0"),
+    ("SK", "421", "0"), ("SL", "232", "0"), ("SM", "378", ""), ("SN", "221", ""), ("SO", "252", "0"),
+    ("SR", "597", ""), ("SS", "211", "0"), ("SV", "503", ""), ("SX", "1", "1"), ("SY", "963", "0"),
+    ("SZ", "268", ""), ("TC", "1", "1"), ("TD", "235", ""), ("TG", "228", ""), ("TH", "66", "0"),
+    ("TJ", "992", ""), ("TL", "670", ""), ("TM", "993", "8"), ("TN", "216", ""), ("TO", "676", ""),
+    ("TR", "90", "0"), ("TT", "1", "1"), ("TW", "886", "0"), ("TZ", "255", "0"), ("UA", "380", "0"),
+    ("UG", "256", "0"), ("US", "1", "1"), ("UY", "598", "0"), ("UZ", "998", ""), ("VC", "1", "1"),
+    ("VE", "58", "0"), ("VG", "1", "1"), ("VI", "1", "1"), ("VN", "84", "0"), ("VU", "678", ""),
+    ("WS", "685", ""), ("XK", "383", "0"), ("YE", "967", "0"), ("ZA", "27", "0"), ("ZM", "260", "0"),
+    ("ZW", "263", "0"),
+];
+
+/// Convert a national or international number to E.164 (`+<country code><subscriber>`).
+fn e164(number: &str, country: &str) -> Result<String, String> {
+    let (_, calling, trunk) = COUNTRIES
+        .iter()
+        .find(|(iso, _, _)| iso.eq_ignore_ascii_case(country))
+        .ok_or_else(|| {
+            format!(
+                "unknown country code {}, expected ISO 3166 alpha-2",
+                country
+            )
+        })?;
+    let number = phone_number(number)?;
+    let digits = if let Some(international) = number.strip_prefix('+') {
+        international.to_string()
+    } else if let Some(international) = number.strip_prefix("00") {
+        international.to_string()
+    } else if let Some(international) = number.strip_prefix("011").filter(|_| *calling == "1") {
+        international.to_string()
+    } else {
+        let national = match number.strip_prefix(trunk) {
+            Some(national) if !trunk.is_empty() => national,
+            _ => &number,
+        };
+        format!("{}{}", calling, national)
+    };
+    if !(8..=15).contains(&digits.len()) {
+        return Err(format!(
+            "+{} has {} digits, E.164 numbers have 8 to 15",
+            digits,
+            digits.len()
+        ));
+    }
+    Ok(format!("+{}", digits))
+}
+
+/// A number to call, the flags of `to qr tel`.
+#[derive(Clone, Debug, Default)]
+pub struct Tel {
+    pub number: String,
+    /// ISO 3166-1 alpha-2 code of the country of a national number, which
+    /// normalizes it to E.164.
+    pub country: Option<String>,
+}
+
+/// Build the payload of `tel`.
+pub fn build(tel: &Tel) -> Result<String, Error> {
+    let number = match &tel.country {
+        Some(country) => e164(&tel.number, country),
+        None => phone_number(&tel.number),
+    }
+    .map_err(|e| Error::new("Invalid phone number", e))?;
+    Ok(format!("tel:{}", number))
+}
+
+/// Read a uri into `{number, extension}`, with the number stripped of visual
+/// separators and any other parameters (RFC 3966) dropped.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let rest = strip_prefix_ignore_case(payload, "tel:")?;
+    let mut params = rest.split(';');
+    let number = percent_decode(params.next().unwrap_or_default());
+    let extension = params
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("ext"))
+        .map(|(_, value)| percent_decode(value));
+    Some(Value::Record {
+        cols: vec!["number".into(), "extension".into()],
+        vals: vec![
+            Value::String {
+                val: phone_number(&number).unwrap_or_else(|_| number.trim().to_string()),
+                span,
+            },
+            match extension {
+                Some(val) => Value::String { val, span },
+                None => Value::Nothing { span },
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tel(number: &str, country: Option<&str>) -> Result<String, Error> {
+        build(&Tel {
+            number: number.into(),
+            country: country.map(String::from),
+        })
+    }
+
+    #[test]
+    fn e164() {
+        assert_eq!(tel("030 1234567", Some("de")).unwrap(), "tel:+49301234567");
+        assert_eq!(
+            tel("(555) 010-0100", Some("US")).unwrap(),
+            "tel:+15550100100"
+        );
+        assert_eq!(
+            tel("+44 20 7946 0958", Some("FR")).unwrap(),
+            "tel:+442079460958"
+        );
+        assert_eq!(tel("555 0100", None).unwrap(), "tel:5550100");
+        assert_eq!(
+            tel("12", Some("DE")).unwrap_err().label,
+            "Invalid phone number"
+        );
+        assert_eq!(
+            tel("call me", None).unwrap_err().label,
+            "Invalid phone number"
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        let payload = tel("030 1234567", Some("DE")).unwrap();
+        let record = parse(&payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["number", "extension"],
+                vec![Value::test_string("+49301234567"), Value::test_nothing()]
+            )
+        );
+        let record = parse("tel:+1-555-010-0100;ext=42", Span::test_data()).unwrap();
+        assert_eq!(
+            record.get_data_by_key("number"),
+            Some(Value::test_string("+15550100100"))
+        );
+        assert_eq!(
+            record.get_data_by_key("extension"),
+            Some(Value::test_string("42"))
+        );
+    }
+}
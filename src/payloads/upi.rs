@@ -0,0 +1,169 @@
+//! `upi://pay?pa=...&pn=...` Indian Unified Payments Interface request.
+
+use nu_protocol::{Span, Value};
+
+use super::{percent_decode, percent_encode, strip_prefix_ignore_case, Error};
+
+/// A payment request, the flags of `to qr upi`.
+#[derive(Clone, Debug, Default)]
+pub struct Upi {
+    /// Virtual payment address of the payee, like `name@bank`.
+    pub address: String,
+    /// Name of the payee.
+    pub name: Option<String>,
+    pub amount: Option<f64>,
+    /// Note shown with the payment.
+    pub note: Option<String>,
+    /// ISO 4217 code, `INR` by default.
+    pub currency: Option<String>,
+}
+
+/// Build the payload of `upi`.
+pub fn build(upi: &Upi) -> Result<String, Error> {
+    let address = upi.address.trim();
+    match address.split_once('@') {
+        Some((user, handle))
+            if !user.is_empty()
+                && !handle.is_empty()
+                && !handle.contains('@')
+                && address
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@')) => {}
+        _ => {
+            return Err(Error::new(
+                "Invalid address",
+                "should be a virtual payment address like name@bank",
+            ))
+        }
+    }
+
+    let mut payload = format!("upi://pay?pa={}", address);
+    if let Some(name) = &upi.name {
+        payload.push_str(&format!("&pn={}", percent_encode(name)));
+    }
+    if let Some(amount) = upi.amount {
+        if !(amount > 0.0 && amount.is_finite()) {
+            return Err(Error::new("Invalid amount", "should be a positive amount"));
+        }
+        payload.push_str(&format!("&am={:.2}", amount));
+    }
+    if let Some(note) = &upi.note {
+        payload.push_str(&format!("&tn={}", percent_encode(note)));
+    }
+    let currency = upi.currency.as_deref().unwrap_or("INR").to_uppercase();
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(Error::new(
+            "Invalid currency",
+            "should be a 3 letter ISO 4217 code",
+        ));
+    }
+    payload.push_str(&format!("&cu={}", currency));
+    Ok(payload)
+}
+
+/// Read a request into `{address, name, amount, currency, note, reference}`,
+/// the flags of `to qr upi` and the transaction reference (`tr`).
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let query = strip_prefix_ignore_case(payload.trim(), "upi://pay?")?;
+    let fields: Vec<(String, String)> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_lowercase(), percent_decode(value)))
+        .collect();
+    let find = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let string = |key: &str| match find(key) {
+        Some(val) => Value::String {
+            val: val.clone(),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    find("pa")?;
+    Some(Value::Record {
+        cols: ["address", "name", "amount", "currency", "note", "reference"]
+            .map(String::from)
+            .to_vec(),
+        vals: vec![
+            string("pa"),
+            string("pn"),
+            match find("am").and_then(|am| am.parse().ok()) {
+                Some(val) => Value::Float { val, span },
+                None => Value::Nothing { span },
+            },
+            string("cu"),
+            string("tn"),
+            string("tr"),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upi() -> Upi {
+        Upi {
+            address: " shop.42@okbank ".into(),
+            name: Some("Chai & Co".into()),
+            amount: Some(99.5),
+            note: Some("order #7".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let payload = build(&upi()).unwrap();
+        assert_eq!(
+            payload,
+            "upi://pay?pa=shop.42@okbank&pn=Chai%20%26%20Co&am=99.50&tn=order%20%237&cu=INR"
+        );
+        let record = parse(&payload, Span::test_data()).unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["address", "name", "amount", "currency", "note", "reference"],
+                vec![
+                    Value::test_string("shop.42@okbank"),
+                    Value::test_string("Chai & Co"),
+                    Value::test_float(99.5),
+                    Value::test_string("INR"),
+                    Value::test_string("order #7"),
+                    Value::test_nothing(),
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        let label = |upi: Upi| build(&upi).unwrap_err().label;
+        for address in ["shop", "@bank", "shop@", "a@b@c", "sh op@bank"] {
+            assert_eq!(
+                label(Upi {
+                    address: address.into(),
+                    ..upi()
+                }),
+                "Invalid address",
+                "{}",
+                address
+            );
+        }
+        assert_eq!(
+            label(Upi {
+                amount: Some(0.0),
+                ..upi()
+            }),
+            "Invalid amount"
+        );
+        assert_eq!(
+            label(Upi {
+                currency: Some("rupee".into()),
+                ..upi()
+            }),
+            "Invalid currency"
+        );
+        assert!(parse("upi://pay?pn=x", Span::test_data()).is_none());
+    }
+}
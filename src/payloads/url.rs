@@ -0,0 +1,101 @@
+//! Web links, split into their components.
+
+use nu_protocol::{Span, Value};
+
+/// The query of a url as a record, where a repeated key keeps its last value.
+pub fn query_record(url: &url::Url, span: Span) -> Value {
+    let (mut cols, mut vals): (Vec<String>, Vec<Value>) = (vec![], vec![]);
+    for (key, value) in url.query_pairs() {
+        let value = Value::String {
+            val: value.into_owned(),
+            span,
+        };
+        match cols.iter().position(|k| *k == key) {
+            Some(i) => vals[i] = value,
+            None => {
+                cols.push(key.into_owned());
+                vals.push(value);
+            }
+        }
+    }
+    Value::Record { cols, vals, span }
+}
+
+/// Read a url with a host into `{scheme, host, path, query, fragment}`, with
+/// the query decoded into a record.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    if !payload.contains("://") || payload.trim().contains(char::is_whitespace) {
+        return None;
+    }
+    let url = url::Url::parse(payload.trim()).ok()?;
+    let host = url.host_str()?;
+    let string = |val: Option<&str>| match val {
+        Some(val) => Value::String {
+            val: val.into(),
+            span,
+        },
+        None => Value::Nothing { span },
+    };
+    Some(Value::Record {
+        cols: ["scheme", "host", "path", "query", "fragment"]
+            .map(String::from)
+            .to_vec(),
+        vals: vec![
+            string(Some(url.scheme())),
+            string(Some(host)),
+            string(Some(url.path())),
+            query_record(&url, span),
+            string(url.fragment()),
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn components() {
+        let record = parse(
+            " https://Example.com/a%20b/c?q=nu%20shell&page=1&q=last#top ",
+            Span::test_data(),
+        )
+        .unwrap();
+        assert_eq!(
+            record,
+            Value::test_record(
+                vec!["scheme", "host", "path", "query", "fragment"],
+                vec![
+                    Value::test_string("https"),
+                    Value::test_string("example.com"),
+                    Value::test_string("/a%20b/c"),
+                    // The query is decoded, and a repeated key keeps its last value.
+                    Value::test_record(
+                        vec!["q", "page"],
+                        vec![Value::test_string("last"), Value::test_string("1")]
+                    ),
+                    Value::test_string("top"),
+                ]
+            )
+        );
+        let record = parse("ftp://files.example.com", Span::test_data()).unwrap();
+        assert_eq!(
+            record.get_data_by_key("query"),
+            Some(Value::test_record(Vec::<&str>::new(), vec![]))
+        );
+        assert_eq!(
+            record.get_data_by_key("fragment"),
+            Some(Value::test_nothing())
+        );
+    }
+
+    #[test]
+    fn not_links() {
+        let parse = |s: &str| parse(s, Span::test_data());
+        assert!(parse("mailto:me@example.com").is_none());
+        assert!(parse("see https://example.com").is_none());
+        assert!(parse("file:///etc/hosts").is_none());
+        assert!(parse("just text").is_none());
+    }
+}
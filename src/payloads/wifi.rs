@@ -0,0 +1,219 @@
+//! `WIFI:S:<ssid>;T:<security>;P:<password>;H:<hidden>;;` network configuration.
+
+use nu_protocol::{Span, Value};
+
+use super::{backslash_escape, backslash_unescape, split_escaped, strip_prefix_ignore_case, Error};
+
+const SPECIAL: &[char] = &['\\', ';', ',', '"', ':'];
+
+/// Escape a field, quoting it if it would otherwise be read as hex.
+fn field(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit()) {
+        format!("\"{}\"", s)
+    } else {
+        backslash_escape(s, SPECIAL)
+    }
+}
+
+/// A network to join, the flags of `to qr wifi`.
+#[derive(Clone, Debug, Default)]
+pub struct Wifi {
+    pub ssid: String,
+    pub password: Option<String>,
+    /// `WPA`, `WEP` or `nopass`; `WPA` if there is a password, else `nopass`.
+    pub security: Option<String>,
+    pub hidden: bool,
+}
+
+/// Build the payload of `wifi`.
+pub fn build(wifi: &Wifi) -> Result<String, Error> {
+    if wifi.ssid.is_empty() {
+        return Err(Error::new("Invalid ssid", "ssid should not be empty"));
+    }
+    let security = match wifi.security.as_ref().map(|x| x.to_uppercase()).as_deref() {
+        Some("WPA") | Some("WPA2") => "WPA",
+        Some("WEP") => "WEP",
+        Some("NOPASS") | Some("NONE") => "nopass",
+        None if wifi.password.is_some() => "WPA",
+        None => "nopass",
+        _ => {
+            return Err(Error::new(
+                "Unknown security parameter",
+                "should be one of WPA, WEP, nopass",
+            ))
+        }
+    };
+    let mut payload = format!("WIFI:S:{};T:{};", field(&wifi.ssid), security);
+    match (security, &wifi.password) {
+        ("nopass", Some(_)) => {
+            return Err(Error::new(
+                "Unexpected password",
+                "an open network (nopass) can not have a password",
+            ))
+        }
+        ("nopass", None) => {}
+        (_, Some(password)) if !password.is_empty() => {
+            payload.push_str(&format!("P:{};", field(password)));
+        }
+        (_, _) => {
+            return Err(Error::new(
+                "Missing password",
+                format!("{} network requires --password", security),
+            ))
+        }
+    }
+    if wifi.hidden {
+        payload.push_str("H:true;");
+    }
+    payload.push(';');
+    Ok(payload)
+}
+
+/// Read a payload into `{ssid, security, password, hidden}`.
+pub fn parse(payload: &str, span: Span) -> Option<Value> {
+    let mut fields = vec![];
+    for field in split_escaped(strip_prefix_ignore_case(payload, "WIFI:")?, ';') {
+        if field.is_empty() {
+            continue;
+        }
+        let (key, value) = field.split_once(':')?;
+        // Fields that would read as hex are quoted, the others escaped; an
+        // escaped quote is part of the value.
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(unquoted) => unquoted.to_string(),
+            None => backslash_unescape(value),
+        };
+        fields.push((key.to_uppercase(), value));
+    }
+    let get = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, v)| v);
+    let string = |val: &str| Value::String {
+        val: val.into(),
+        span,
+    };
+    Some(Value::Record {
+        cols: vec![
+            "ssid".into(),
+            "security".into(),
+            "password".into(),
+            "hidden".into(),
+        ],
+        vals: vec![
+            string(get("S")?),
+            // Networks without a type are open.
+            string(get("T").map_or("nopass", |t| t.as_str())),
+            match get("P") {
+                Some(password) => string(password),
+                None => Value::Nothing { span },
+            },
+            Value::Bool {
+                val: get("H").is_some_and(|h| h.eq_ignore_ascii_case("true")),
+                span,
+            },
+        ],
+        span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(wifi: &Wifi) -> Value {
+        parse(&build(wifi).unwrap(), Span::test_data()).unwrap()
+    }
+
+    fn column(record: &Value, name: &str) -> Value {
+        record.get_data_by_key(name).unwrap()
+    }
+
+    #[test]
+    fn special_characters() {
+        for ssid in [
+            "a;b",
+            "a,b",
+            "a\\b",
+            "a:b",
+            "\"quoted\"",
+            r"a\;b",
+            ";,\\:\"",
+        ] {
+            let wifi = Wifi {
+                ssid: ssid.into(),
+                password: Some(format!("{}!", ssid)),
+                ..Default::default()
+            };
+            let record = roundtrip(&wifi);
+            assert_eq!(column(&record, "ssid"), Value::test_string(ssid));
+            assert_eq!(
+                column(&record, "password"),
+                Value::test_string(format!("{}!", ssid))
+            );
+        }
+    }
+
+    #[test]
+    fn hex_fields_are_quoted() {
+        let wifi = Wifi {
+            ssid: "cafe".into(),
+            password: Some("0123456789".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            build(&wifi).unwrap(),
+            "WIFI:S:\"cafe\";T:WPA;P:\"0123456789\";;"
+        );
+        let record = roundtrip(&wifi);
+        assert_eq!(column(&record, "ssid"), Value::test_string("cafe"));
+        assert_eq!(
+            column(&record, "password"),
+            Value::test_string("0123456789")
+        );
+    }
+
+    #[test]
+    fn security() {
+        let open = Wifi {
+            ssid: "guest".into(),
+            hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(build(&open).unwrap(), "WIFI:S:guest;T:nopass;H:true;;");
+        let record = roundtrip(&open);
+        assert_eq!(column(&record, "security"), Value::test_string("nopass"));
+        assert_eq!(column(&record, "password"), Value::test_nothing());
+        assert_eq!(column(&record, "hidden"), Value::test_bool(true));
+
+        let wep = Wifi {
+            ssid: "old".into(),
+            password: Some("secret".into()),
+            security: Some("wep".into()),
+            ..Default::default()
+        };
+        assert_eq!(build(&wep).unwrap(), "WIFI:S:old;T:WEP;P:secret;;");
+
+        let label = |wifi: Wifi| build(&wifi).unwrap_err().label;
+        assert_eq!(label(Wifi::default()), "Invalid ssid");
+        assert_eq!(
+            label(Wifi {
+                security: Some("WPA".into()),
+                ..open.clone()
+            }),
+            "Missing password"
+        );
+        assert_eq!(
+            label(Wifi {
+                password: Some("secret".into()),
+                security: Some("nopass".into()),
+                ..open.clone()
+            }),
+            "Unexpected password"
+        );
+        assert_eq!(
+            label(Wifi {
+                security: Some("WPA3".into()),
+                ..open
+            }),
+            "Unknown security parameter"
+        );
+    }
+}
@@ -0,0 +1,181 @@
+//! WireGuard client configuration, as imported by the official mobile apps.
+//!
+//! The input is either the config text or a record like
+//! `{Interface: {PrivateKey: .., Address: ..}, Peer: {PublicKey: .., Endpoint: ..}}`,
+//! where `Peer` may also be a list of peers.
+
+use super::Error;
+
+/// A section of a config, its name and its `Key = Value` entries.
+pub type Section = (String, Vec<(String, String)>);
+
+/// Parse the ini-like config format of `wg-quick`.
+pub fn parse(config: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = vec![];
+    for (n, line) in config.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            sections.push((name.trim().to_string(), vec![]));
+        } else if let Some((key, value)) = line.split_once('=') {
+            let (_, entries) = sections
+                .last_mut()
+                .ok_or_else(|| format!("line {}: entry outside of a section", n + 1))?;
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        } else {
+            return Err(format!("line {}: expected `Key = Value`", n + 1));
+        }
+    }
+    Ok(sections)
+}
+
+/// A key is the base64 encoding of 32 bytes.
+fn is_key(s: &str) -> bool {
+    s.len() == 44
+        && s.ends_with('=')
+        && s[..43]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/')
+}
+
+fn validate(sections: &[Section]) -> Result<(), String> {
+    let find = |entries: &[(String, String)], key: &str| {
+        entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.clone())
+    };
+    let interfaces: Vec<_> = sections
+        .iter()
+        .filter(|(s, _)| s.eq_ignore_ascii_case("Interface"))
+        .collect();
+    let peers: Vec<_> = sections
+        .iter()
+        .filter(|(s, _)| s.eq_ignore_ascii_case("Peer"))
+        .collect();
+    if let Some((name, _)) = sections
+        .iter()
+        .find(|(s, _)| !s.eq_ignore_ascii_case("Interface") && !s.eq_ignore_ascii_case("Peer"))
+    {
+        return Err(format!("unknown section [{}]", name));
+    }
+    let [(_, interface)] = interfaces.as_slice() else {
+        return Err("config should have exactly one [Interface]".into());
+    };
+    match find(interface, "PrivateKey") {
+        Some(key) if is_key(&key) => {}
+        Some(_) => return Err("PrivateKey is not a base64 encoded 32 byte key".into()),
+        None => return Err("[Interface] requires PrivateKey".into()),
+    }
+    if find(interface, "Address").is_none() {
+        return Err("[Interface] requires Address".into());
+    }
+    if peers.is_empty() {
+        return Err("config should have at least one [Peer]".into());
+    }
+    for (_, peer) in peers {
+        match find(peer, "PublicKey") {
+            Some(key) if is_key(&key) => {}
+            Some(_) => return Err("PublicKey is not a base64 encoded 32 byte key".into()),
+            None => return Err("[Peer] requires PublicKey".into()),
+        }
+        if let Some(key) = find(peer, "PresharedKey") {
+            if !is_key(&key) {
+                return Err("PresharedKey is not a base64 encoded 32 byte key".into());
+            }
+        }
+        match find(peer, "Endpoint") {
+            Some(endpoint) => match endpoint.rsplit_once(':') {
+                Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {}
+                _ => return Err(format!("Endpoint {} should be host:port", endpoint)),
+            },
+            None => return Err("[Peer] requires Endpoint".into()),
+        }
+        if find(peer, "AllowedIPs").is_none() {
+            return Err("[Peer] requires AllowedIPs".into());
+        }
+    }
+    Ok(())
+}
+
+/// Build the payload of the config `sections`, such as [parse] reads them.
+pub fn build(sections: &[Section]) -> Result<String, Error> {
+    validate(sections).map_err(|e| Error::new("Invalid WireGuard config", e))?;
+    Ok(sections
+        .iter()
+        .map(|(name, entries)| {
+            let mut s = format!("[{}]\n", name);
+            for (k, v) in entries {
+                s.push_str(&format!("{} = {}\n", k, v));
+            }
+            s
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+    const PUBLIC_KEY: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+
+    fn config() -> String {
+        format!(
+            "[Interface]\n# phone\nPrivateKey = {}\nAddress = 10.0.0.2/32\n\n[Peer]\nPublicKey = {}\nEndpoint = vpn.example.com:51820\nAllowedIPs = 0.0.0.0/0, ::/0 # all\n",
+            PRIVATE_KEY, PUBLIC_KEY
+        )
+    }
+
+    #[test]
+    fn roundtrip() {
+        let sections = parse(&config()).unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(
+            sections[1].1[2],
+            ("AllowedIPs".into(), "0.0.0.0/0, ::/0".into())
+        );
+        let payload = build(&sections).unwrap();
+        assert_eq!(
+            payload,
+            format!(
+                "[Interface]\nPrivateKey = {}\nAddress = 10.0.0.2/32\n\n[Peer]\nPublicKey = {}\nEndpoint = vpn.example.com:51820\nAllowedIPs = 0.0.0.0/0, ::/0\n",
+                PRIVATE_KEY, PUBLIC_KEY
+            )
+        );
+        assert_eq!(parse(&payload).unwrap(), sections);
+    }
+
+    #[test]
+    fn invalid() {
+        let msg = |config: &str| build(&parse(config).unwrap()).unwrap_err().msg;
+        let config = config();
+        assert_eq!(
+            msg(&config.replace("[Peer]", "[Server]")),
+            "unknown section [Server]"
+        );
+        assert_eq!(
+            msg(&config.replace(PRIVATE_KEY, "short=")),
+            "PrivateKey is not a base64 encoded 32 byte key"
+        );
+        assert_eq!(
+            msg(&config.replace(":51820", "")),
+            "Endpoint vpn.example.com should be host:port"
+        );
+        assert_eq!(
+            msg(&config.replace("AllowedIPs", "#")),
+            "[Peer] requires AllowedIPs"
+        );
+        assert_eq!(
+            parse("PrivateKey = x").unwrap_err(),
+            "line 1: entry outside of a section"
+        );
+        assert_eq!(
+            parse("[Interface]\nnonsense").unwrap_err(),
+            "line 2: expected `Key = Value`"
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! PDF417 codes, as found on id cards and driving licenses, read with rxing.
+
+use image::GrayImage;
+use quircs::{Data, Point};
+use rxing::common::HybridBinarizer;
+use rxing::multi::MultipleBarcodeReader;
+use rxing::pdf417::PDF417Reader;
+use rxing::{
+    BinaryBitmap, DecodeHints, Luma8LuminanceSource, RXingResult, RXingResultMetadataType,
+    RXingResultMetadataValue,
+};
+
+use crate::append::Segment;
+
+/// Corners, macro segment and data of a decoded symbol.
+pub type Symbol = ([Point; 4], Option<Segment>, Data);
+
+/// Find and decode all PDF417 codes of a grayscale image.
+///
+/// rxing looks for them upright, upside down and turned either way, and
+/// stops at the first of these orientations that has any.
+pub fn pdf417s(image: &GrayImage) -> Vec<Symbol> {
+    let Ok(source) = Luma8LuminanceSource::new(image.to_vec(), image.width(), image.height())
+    else {
+        return vec![];
+    };
+    let mut bitmap = BinaryBitmap::new(HybridBinarizer::new(source));
+    PDF417Reader
+        .decode_multiple_with_hints(&mut bitmap, &DecodeHints::default())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|result| symbol(result, image.width() as f32, image.height() as f32))
+        .collect()
+}
+
+fn symbol(result: &RXingResult, width: f32, height: f32) -> Option<Symbol> {
+    let metadata = result.getRXingResultMetadata();
+    let rotation = match metadata.get(&RXingResultMetadataType::ORIENTATION) {
+        Some(RXingResultMetadataValue::Orientation(rotation)) => *rotation,
+        _ => 0,
+    };
+    // The points are those of the image as rxing rotated it counterclockwise
+    // to find the symbol upright: top left, bottom left, top right and
+    // bottom right.
+    let points = result.getPoints();
+    if points.len() < 4 {
+        return None;
+    }
+    let corners = [0, 2, 3, 1].map(|i| {
+        let p = points[i];
+        let (x, y) = match rotation {
+            90 => (width - 1.0 - p.y, p.x),
+            180 => (width - 1.0 - p.x, height - 1.0 - p.y),
+            270 => (p.y, height - 1.0 - p.x),
+            _ => (p.x, p.y),
+        };
+        Point {
+            x: x.round() as i32,
+            y: y.round() as i32,
+        }
+    });
+    let segment = match metadata.get(&RXingResultMetadataType::PDF417_EXTRA_METADATA) {
+        Some(RXingResultMetadataValue::Pdf417ExtraMetadata(macro_pdf417)) => Some(Segment {
+            file_id: macro_pdf417.getFileId().to_string(),
+            index: Some(macro_pdf417.getSegmentIndex()),
+            count: usize::try_from(macro_pdf417.getSegmentCount()).ok(),
+            last: macro_pdf417.isLastSegment(),
+        }),
+        _ => None,
+    };
+    Some((
+        corners,
+        segment,
+        Data {
+            payload: result.getText().as_bytes().to_vec(),
+            ..Default::default()
+        },
+    ))
+}
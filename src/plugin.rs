@@ -0,0 +1,1068 @@
+//! The nushell plugin: signatures of its commands and their dispatch.
+
+use nu_plugin::{EvaluatedCall, LabeledError, Plugin};
+use nu_protocol::{Category, PluginExample, PluginSignature, Span, SyntaxShape, Type, Value};
+
+use crate::render::{payload, render, to_qr};
+use crate::{barcode, clipboard, decode, payloads};
+
+/// The plugin, serving the commands `from qr`, `to qr` and the others.
+#[derive(Default)]
+pub struct Qr;
+
+impl Qr {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Plugin for Qr {
+    fn signature(&self) -> Vec<PluginSignature> {
+        vec![
+            backend_flags(decode_flags(PluginSignature::build("from qr")))
+            .usage("decode input qr, aztec, data matrix or pdf417 code image")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Binary, Type::Binary),
+                (Type::Binary, Type::String),
+                (Type::Binary, Type::List(Box::new(Type::String))),
+                (Type::Binary, Type::List(Box::new(Type::Binary))),
+                (Type::Binary, Type::Table(vec![])),
+                (Type::String, Type::Binary),
+                (Type::String, Type::String),
+                (Type::String, Type::List(Box::new(Type::String))),
+                (Type::String, Type::List(Box::new(Type::Binary))),
+                (Type::String, Type::Table(vec![])),
+                (Type::Nothing, Type::Binary),
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::List(Box::new(Type::String))),
+                (Type::Nothing, Type::List(Box::new(Type::Binary))),
+                (Type::Nothing, Type::Table(vec![])),
+                (Type::List(Box::new(Type::String)), Type::Table(vec![])),
+                (Type::List(Box::new(Type::Binary)), Type::Table(vec![])),
+            ])
+            .optional("path", SyntaxShape::String, "image file or url to decode instead of the input")
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "convert input string to qr image".into(),
+                    example: "open --raw qrcode.png | from qr".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode an image file directly".into(),
+                    example: "from qr qrcode.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "download an image and decode it".into(),
+                    example: "from qr https://example.com/qr.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode the codes on every page of a pdf invoice".into(),
+                    example: "from qr invoice.pdf".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode every frame of a screen recording".into(),
+                    example: "from qr recording.gif".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode the images embedded in a web page".into(),
+                    example: "http get https://example.com | parse --regex 'src=\"(?<src>data:image[^\"]+)\"' | get src | from qr".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode all images of a directory into a table".into(),
+                    example: "glob *.png | from qr".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "join a message split over several codes with structured append".into(),
+                    example: "glob label-*.png | from qr | get payload".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode a list of images that were opened before".into(),
+                    example: "ls *.png | each { open --raw $in.name } | from qr".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode a washed-out scan".into(),
+                    example: "open --raw fax.png | from qr --contrast 60 --brightness -30".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "only decode the code in the top right corner of a scanned form".into(),
+                    example: "from qr form.png --crop [1800 0 600 600]".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode a code photographed at a steep angle, given its corners".into(),
+                    example: "from qr poster.jpg --quad [412 80 1630 310 1598 1220 380 1490]".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode an unevenly lit photo, trying every strategy".into(),
+                    example: "from qr receipt.jpg --try-harder".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read the aztec code of a boarding pass screenshot".into(),
+                    example: "from qr boarding-pass.png --detailed | select symbology payload".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read a driving license whose data is split over several pdf417 codes".into(),
+                    example: "from qr license-back.jpg".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode with the rqrr engine, which reads some images quircs can not".into(),
+                    example: "from qr qrcode.png --backend rqrr".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "only fall back to rqrr, and show which engine read every code".into(),
+                    example: "from qr qrcode.png --backends [quircs rqrr] --detailed | select payload engine".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "skip photos that take longer than two seconds to search".into(),
+                    example: "glob photos/*.jpg | from qr --try-harder --timeout 2sec".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "ignore specks and background patterns of a busy scanned page".into(),
+                    example: "from qr scan.png --min-size 40 --max-size 1200".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "get the payload as binary regardless of its content".into(),
+                    example: "open --raw qrcode.png | from qr --raw | save payload.bin".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "log binary payloads as hex".into(),
+                    example: "glob tags/*.png | from qr --bytes-as hex".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read the json of a shipping label as a record".into(),
+                    example: "from qr label.png --auto | get order_id".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read the credentials of a wifi code".into(),
+                    example: "from qr wifi.png --parse | select ssid password".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "audit the mfa enrollment codes of a folder without showing their keys".into(),
+                    example: "glob enroll/*.png | from qr --parse --redact | get payload | select issuer account algorithm".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "show the vaccinations of a covid certificate".into(),
+                    example: "from qr dcc.png --dcc | get certificate.v".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "check scanned codes against a manifest of sha256 sums".into(),
+                    example: "glob scans/*.png | from qr --checksum sha256 | join (open manifest.csv) sha256".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "decode a payload written in shift-jis".into(),
+                    example: "open --raw qrcode.png | from qr --encoding shift_jis".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "show version, error correction level, corners and bounding box of every code".into(),
+                    example: "open --raw qrcode.png | from qr --detailed".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "count the distinct codes of a glossy photo, where reflections show some twice".into(),
+                    example: "from qr shelf.jpg --unique --detailed | select payload count".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read a sheet of labels in the order they are printed".into(),
+                    example: "from qr labels.png --sort-by position".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "check that printed codes are upright and large enough to scan".into(),
+                    example: "from qr label.png --detailed | select rotation module_size".into(),
+                    result: None,
+                },
+            ]),
+            decode_flags(PluginSignature::build("from barcode"))
+            .usage("decode the one dimensional barcodes of an image: code128, code39, codabar, ean13, ean8, upca, upce, itf, databar, databar expanded")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Binary, Type::Binary),
+                (Type::Binary, Type::String),
+                (Type::Binary, Type::List(Box::new(Type::String))),
+                (Type::Binary, Type::List(Box::new(Type::Binary))),
+                (Type::Binary, Type::Table(vec![])),
+                (Type::String, Type::Binary),
+                (Type::String, Type::String),
+                (Type::String, Type::List(Box::new(Type::String))),
+                (Type::String, Type::List(Box::new(Type::Binary))),
+                (Type::String, Type::Table(vec![])),
+                (Type::Nothing, Type::Binary),
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::List(Box::new(Type::String))),
+                (Type::Nothing, Type::List(Box::new(Type::Binary))),
+                (Type::Nothing, Type::Table(vec![])),
+                (Type::List(Box::new(Type::String)), Type::Table(vec![])),
+                (Type::List(Box::new(Type::Binary)), Type::Table(vec![])),
+            ])
+            .optional("path", SyntaxShape::String, "image file or url to decode instead of the input")
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "decode the barcode of a product photo".into(),
+                    example: "from barcode product.jpg".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "show which kind of barcode every code of a scanned shipping label is".into(),
+                    example: "from barcode label.png --detailed | select symbology payload".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "read the application identifiers of a gs1-128 barcode".into(),
+                    example: "from barcode pallet.png --parse".into(),
+                    result: None,
+                },
+            ]),
+            backend_flags(decode_flags(PluginSignature::build("from code")))
+            .usage("decode every code of an image, whichever kind it is: qr, micro qr, data matrix, aztec, pdf417 and one dimensional barcodes")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Binary, Type::Binary),
+                (Type::Binary, Type::String),
+                (Type::Binary, Type::List(Box::new(Type::String))),
+                (Type::Binary, Type::List(Box::new(Type::Binary))),
+                (Type::Binary, Type::Table(vec![])),
+                (Type::String, Type::Binary),
+                (Type::String, Type::String),
+                (Type::String, Type::List(Box::new(Type::String))),
+                (Type::String, Type::List(Box::new(Type::Binary))),
+                (Type::String, Type::Table(vec![])),
+                (Type::Nothing, Type::Binary),
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::List(Box::new(Type::String))),
+                (Type::Nothing, Type::List(Box::new(Type::Binary))),
+                (Type::Nothing, Type::Table(vec![])),
+                (Type::List(Box::new(Type::String)), Type::Table(vec![])),
+                (Type::List(Box::new(Type::Binary)), Type::Table(vec![])),
+            ])
+            .optional("path", SyntaxShape::String, "image file or url to decode instead of the input")
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "list the codes of a shipping label with their symbology".into(),
+                    example: "from code label.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "keep only the data matrix codes of a photo of parts".into(),
+                    example: "from code parts.jpg | where symbology == datamatrix | get payload".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "only look for qr codes and the barcodes of products on a busy shelf photo".into(),
+                    example: "from code shelf.jpg --only [qr ean13 upca]".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "tell which kind of code every image of a directory has".into(),
+                    example: "glob *.png | from code | select filename symbology".into(),
+                    result: None,
+                },
+            ]),
+            backend_flags(decode_flags(render_flags(PluginSignature::build("qr clipboard"))))
+            .usage("decode the qr code of the image on the clipboard, or copy a qr code of the input to it")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::Binary),
+                (Type::Nothing, Type::List(Box::new(Type::String))),
+                (Type::Nothing, Type::List(Box::new(Type::Binary))),
+                (Type::Nothing, Type::Table(vec![])),
+                (Type::Binary, Type::Nothing),
+                (Type::String, Type::Nothing),
+                (Type::Record(vec![]), Type::Nothing),
+            ])
+            .named("format", SyntaxShape::String, "serialize structured input as: json(Default), nuon, yaml, toml", Some('f'))
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "decode a qr code that was screenshotted to the clipboard".into(),
+                    example: "qr clipboard".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "copy a qr code of a link, to paste it into a chat".into(),
+                    example: "\"https://example.com/join\" | qr clipboard".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr")))
+            .usage("convert input to png image of qr code")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Binary, Type::Binary),
+                (Type::String, Type::Binary),
+                (Type::Record(vec![]), Type::Binary),
+                (Type::Table(vec![]), Type::Binary),
+                (Type::List(Box::new(Type::Any)), Type::Binary),
+                (Type::Table(vec![]), Type::List(Box::new(Type::Binary))),
+                (Type::List(Box::new(Type::Any)), Type::List(Box::new(Type::Binary))),
+            ])
+            .named("format", SyntaxShape::String, "serialize structured input as: json(Default), nuon, yaml, toml", Some('f'))
+            .named("get", SyntaxShape::CellPath, "encode only the value at the given cell path of the input", Some('g'))
+            .switch("each", "generate one qr code per row of a table or item of a list", Some('e'))
+            .named("payload-column", SyntaxShape::String, "generate one qr code per row from the given column", Some('c'))
+            .named("symbology", SyntaxShape::String, "allowed: qr(Default), datamatrix, aztec, maxicode, hanxin", Some('y'))
+            .switch("gs1", "encode a GS1 element string, e.g. (01)09501101530003(17)270101, as GS1 data matrix", None)
+            .named("postal-code", SyntaxShape::String, "postal code of the structured carrier message of maxicode, up to 9 digits or 6 letters and digits", None)
+            .named("country", SyntaxShape::Int, "ISO 3166 numeric country code of the structured carrier message of maxicode", None)
+            .named("service-class", SyntaxShape::Int, "carrier service class of the structured carrier message of maxicode", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "convert string to qr code, default width is 600".into(),
+                    example: "\"hello!\" | to qr | save qr.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "convert string to qr code with given shape and width".into(),
+                    example: "\"hello!\" | to qr --shape circle --width 300 | save qr.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "convert a record to qr code of its nuon representation".into(),
+                    example: "{id: 42, tags: [a b]} | to qr --format nuon | save qr.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "encode a single field of structured input".into(),
+                    example: "open data.json | to qr --get users.0.invite_url | save invite.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate a qr code for every row of a table".into(),
+                    example: "open tickets.csv | to qr --payload-column url | enumerate | each { |it| $it.item | save $\"ticket-($it.index).png\" }".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate a GS1 data matrix of a product's gtin, batch and expiry".into(),
+                    example: "\"(01)09501101530003(10)AB-123(17)270101\" | to qr --symbology datamatrix --gs1 | save label.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate an aztec code, as on boarding passes and transit tickets".into(),
+                    example: "open ticket.txt | to qr --symbology aztec | save ticket.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate a maxicode of a parcel label, with its postal code, country and service class".into(),
+                    example: "\"1Z12345678\" | to qr --symbology maxicode --postal-code 152382802 --country 840 --service-class 1 | save parcel.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate a han xin code, as required on some chinese documents".into(),
+                    example: "\"发票号码 04400123\" | to qr --symbology hanxin | save invoice.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "add qr codes to a table, shown as a summary of each".into(),
+                    example: "[[name url]; [nushell https://www.nushell.sh]] | insert qr {|row| $row.url | to qr --summary }".into(),
+                    result: None,
+                }
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr wifi")))
+            .usage("generate a qr code for joining a WiFi network")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("ssid", SyntaxShape::String, "network name", None)
+            .named("password", SyntaxShape::String, "network password", Some('p'))
+            .named("security", SyntaxShape::String, "allowed: WPA(Default if password is given), WEP, nopass", Some('t'))
+            .switch("hidden", "the network does not broadcast its ssid", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a qr code for a WPA network".into(),
+                    example: "to qr wifi --ssid guest --password \"p@ss;word\" | save wifi.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr vcard")))
+            .usage("generate a vCard contact qr code from a record")
+            .extra_usage("columns: name(required), org, phone, email, address, url")
+            .category(Category::Strings)
+            .input_output_type(Type::Record(vec![]), Type::Binary)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a contact card".into(),
+                    example: "{name: \"Jane Doe\", org: ACME, phone: \"+1 555 0100\", email: jane@example.com} | to qr vcard | save jane.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr mecard")))
+            .usage("generate a MeCard contact qr code from a record")
+            .extra_usage("columns: name(required), org, phone, email, address, url")
+            .category(Category::Strings)
+            .input_output_type(Type::Record(vec![]), Type::Binary)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a compact contact card".into(),
+                    example: "{name: \"Jane Doe\", phone: \"+1 555 0100\"} | to qr mecard | save jane.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr totp")))
+            .usage("generate an otpauth qr code for enrolling authenticator apps")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("secret", SyntaxShape::String, "shared secret, base32 encoded", None)
+            .required_named("account", SyntaxShape::String, "account name, e.g. an email address", None)
+            .named("issuer", SyntaxShape::String, "provider or service name", None)
+            .named("digits", SyntaxShape::Int, "number of digits of a code: 6(Default), 7 or 8", None)
+            .named("period", SyntaxShape::Int, "seconds a code is valid, default is 30", None)
+            .named("algorithm", SyntaxShape::String, "allowed: SHA1(Default), SHA256, SHA512", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate an enrollment code".into(),
+                    example: "to qr totp --issuer ACME --account jane@example.com --secret JBSWY3DPEHPK3PXP | save totp.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr geo")))
+            .usage("generate a qr code for a geographic location")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("lat", SyntaxShape::Number, "latitude in degrees", None)
+            .required_named("lon", SyntaxShape::Number, "longitude in degrees", None)
+            .named("alt", SyntaxShape::Number, "altitude in meters", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a location code".into(),
+                    example: "to qr geo --lat 48.2010 --lon 16.3695 | save venue.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr mailto")))
+            .usage("generate a qr code that composes an email")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .named("to", SyntaxShape::String, "recipients, separated by commas", None)
+            .named("cc", SyntaxShape::String, "carbon copy recipients, separated by commas", None)
+            .named("bcc", SyntaxShape::String, "blind carbon copy recipients, separated by commas", None)
+            .named("subject", SyntaxShape::String, "subject line", None)
+            .named("body", SyntaxShape::String, "message text", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a support email code".into(),
+                    example: "to qr mailto --to support@example.com --subject \"Order #42\" | save mail.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr sms")))
+            .usage("generate a qr code that composes a text message")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("number", SyntaxShape::String, "recipient phone number", Some('n'))
+            .named("message", SyntaxShape::String, "prefilled message text", Some('m'))
+            .named("format", SyntaxShape::String, "allowed: SMSTO(Default), sms", Some('f'))
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a \"text us\" code".into(),
+                    example: "to qr sms --number \"+1 555 0100\" --message \"JOIN\" | save sms.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr tel")))
+            .usage("generate a qr code that calls a phone number")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("number", SyntaxShape::String, "phone number", Some('n'))
+            .named("country", SyntaxShape::String, "ISO 3166 country code of a national number, normalizes it to E.164", Some('c'))
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a \"call us\" code from a national number".into(),
+                    example: "to qr tel --number \"030 1234567\" --country DE | save call.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr vevent")))
+            .usage("generate a calendar event qr code from a record")
+            .extra_usage("columns: title(required), start(required), end, location, description")
+            .category(Category::Strings)
+            .input_output_type(Type::Record(vec![]), Type::Binary)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate an event invite".into(),
+                    example: "{title: Meetup, start: 2024-05-01T18:00:00+02:00, end: 2024-05-01T20:00:00+02:00, location: \"Main Hall\"} | to qr vevent | save invite.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr epc")))
+            .usage("generate an EPC (GiroCode) SEPA credit transfer qr code")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("name", SyntaxShape::String, "beneficiary name, up to 70 characters", None)
+            .required_named("iban", SyntaxShape::String, "beneficiary IBAN", None)
+            .named("bic", SyntaxShape::String, "beneficiary BIC, optional inside the EEA", None)
+            .named("amount", SyntaxShape::Number, "amount in EUR", None)
+            .named("purpose", SyntaxShape::String, "4 letter purpose code", None)
+            .named("reference", SyntaxShape::String, "structured RF creditor reference", None)
+            .named("text", SyntaxShape::String, "unstructured remittance information, up to 140 characters", None)
+            .named("info", SyntaxShape::String, "beneficiary to originator information, up to 70 characters", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a scan-to-pay code for an invoice".into(),
+                    example: "to qr epc --name \"ACME GmbH\" --iban DE89370400440532013000 --amount 12.5 --text \"Invoice 42\" | save pay.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr pix")))
+            .usage("generate a PIX (Brazil) payment qr code")
+            .extra_usage("fields can also be given as columns of an input record")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::Nothing, Type::Binary),
+                (Type::Record(vec![]), Type::Binary),
+            ])
+            .named("key", SyntaxShape::String, "PIX key of the receiver", None)
+            .named("name", SyntaxShape::String, "merchant name, up to 25 characters", None)
+            .named("city", SyntaxShape::String, "merchant city, up to 15 characters", None)
+            .named("amount", SyntaxShape::Number, "amount in BRL", None)
+            .named("txid", SyntaxShape::String, "transaction id, up to 25 letters and digits", None)
+            .named("description", SyntaxShape::String, "message shown to the payer", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a static PIX code".into(),
+                    example: "to qr pix --key fulano@example.com --name \"Fulano de Tal\" --city \"São Paulo\" --amount 10 | save pix.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "generate a PIX code from a record".into(),
+                    example: "{key: +5511999999999, name: Loja, city: Recife, txid: PEDIDO42} | to qr pix | save pix.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr upi")))
+            .usage("generate a UPI (India) payment qr code")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("address", SyntaxShape::String, "payee virtual payment address (pa)", None)
+            .named("name", SyntaxShape::String, "payee name (pn)", None)
+            .named("amount", SyntaxShape::Number, "amount (am)", None)
+            .named("note", SyntaxShape::String, "transaction note (tn)", None)
+            .named("currency", SyntaxShape::String, "currency code (cu), default is INR", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a payment code for a shop".into(),
+                    example: "to qr upi --address shop@okbank --name \"Corner Shop\" --amount 250 | save upi.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr crypto")))
+            .usage("generate a BIP-21 style cryptocurrency payment qr code")
+            .category(Category::Strings)
+            .input_output_type(Type::Nothing, Type::Binary)
+            .required_named("address", SyntaxShape::String, "receiving address", None)
+            .named("scheme", SyntaxShape::String, "uri scheme: bitcoin(Default), litecoin, dogecoin, or any other", None)
+            .named("amount", SyntaxShape::Number, "amount in the main unit of the currency", None)
+            .named("label", SyntaxShape::String, "name of the receiver", None)
+            .named("message", SyntaxShape::String, "description of the payment", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a donation code".into(),
+                    example: "to qr crypto --address bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq --amount 0.001 --label Donation | save donate.png".into(),
+                    result: None,
+                },
+            ]),
+            summary_flag(render_flags(PluginSignature::build("to qr wireguard")))
+            .usage("generate a qr code to import a WireGuard client config into the mobile apps")
+            .extra_usage("input is the config text, or a record with Interface and Peer records")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::String, Type::Binary),
+                (Type::Record(vec![]), Type::Binary),
+            ])
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "generate a qr code from a config file".into(),
+                    example: "open --raw wg0.conf | to qr wireguard | save wg0.png".into(),
+                    result: None,
+                },
+            ]),
+            PluginSignature::build("to barcode")
+            .usage("convert input to png image of a one dimensional barcode")
+            .category(Category::Strings)
+            .input_output_types(vec![
+                (Type::String, Type::Binary),
+                (Type::Int, Type::Binary),
+                (Type::String, Type::String),
+                (Type::Int, Type::String),
+            ])
+            .named("symbology", SyntaxShape::String, "allowed: code128(Default), code39, codabar, ean13, ean8, upca, upce (zero-suppressing upc-a numbers), databar (gtin-14), databar-expanded (gs1 element string), postnet, intelligent-mail (tracking and routing code), royal-mail, pharmacode (3 to 131070), pharmacode-two-track (4 to 64570080)", Some('y'))
+            .named("width", SyntaxShape::Int, "Target width, rounded down to whole pixels per bar (Default: 3 pixels per module)", Some('w'))
+            .named("height", SyntaxShape::Int, "Height of the bars, without the digits below those of ean and upc (Default: 100)", Some('v'))
+            .switch("mod43", "add the modulo 43 check character to code39", None)
+            .switch("svg", "return an svg document instead of a png image", None)
+            .plugin_examples(vec![
+                PluginExample {
+                    description: "convert a string to a code 128 barcode".into(),
+                    example: "\"PKG-0042\" | to barcode | save label.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "convert a string to a barcode as svg".into(),
+                    example: "\"PKG-0042\" | to barcode --svg | save label.svg".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "convert a product number to an ean-13 barcode, adding its check digit".into(),
+                    example: "\"590123412345\" | to barcode --symbology ean13 | save product.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "label loose fruit with its gtin, net weight and best before date".into(),
+                    example: "\"(01)09501101530003(3103)000250(15)261231\" | to barcode --symbology databar-expanded | save apples.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "print the intelligent mail barcode of an envelope, with its tracking code and routing zip code".into(),
+                    example: "\"01234567094987654321 01234567891\" | to barcode --symbology intelligent-mail | save envelope.png".into(),
+                    result: None,
+                },
+                PluginExample {
+                    description: "mark a folding box with the pharmacode of its product".into(),
+                    example: "1234 | to barcode --symbology pharmacode | save carton.png".into(),
+                    result: None,
+                },
+            ]),
+        ]
+    }
+
+    fn run(
+        &mut self,
+        name: &str,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        // Images of `--summary` come back as themselves, all commands read
+        // their binary.
+        let base;
+        let input = match input {
+            Value::CustomValue { val, span } => {
+                base = val.to_base_value(*span)?;
+                &base
+            }
+            _ => input,
+        };
+        let input_span = Some(input.span().unwrap_or(call.head));
+        match name {
+            "from qr" => decode::from_image(call, input, input_span, decode::Codes::Qr),
+            "from barcode" => decode::from_image(call, input, input_span, decode::Codes::Barcodes),
+            "from code" => decode::from_image(call, input, input_span, decode::Codes::All),
+            "qr clipboard" => match input {
+                Value::Nothing { .. } => clipboard::paste(call),
+                _ => clipboard::copy(render(call, &payload(call, input)?, input_span)?, call.head),
+            },
+            "to qr" => to_qr(call, input, input_span),
+            "to barcode" => barcode::to_barcode(call, input, input_span),
+            "to qr wifi" => render(call, wifi(call)?.as_bytes(), None),
+            "to qr vcard" => render(call, contact(call, input)?.vcard().as_bytes(), input_span),
+            "to qr geo" => render(call, geo(call)?.as_bytes(), None),
+            "to qr mailto" => render(call, mailto(call)?.as_bytes(), None),
+            "to qr sms" => render(call, sms(call)?.as_bytes(), None),
+            "to qr tel" => render(call, tel(call)?.as_bytes(), None),
+            "to qr vevent" => render(call, event(call, input)?.as_bytes(), input_span),
+            "to qr epc" => render(call, epc(call)?.as_bytes(), None),
+            "to qr pix" => render(call, pix(call, input)?.as_bytes(), None),
+            "to qr upi" => render(call, upi(call)?.as_bytes(), None),
+            "to qr crypto" => render(call, crypto(call)?.as_bytes(), None),
+            "to qr wireguard" => render(call, wireguard(call, input)?.as_bytes(), input_span),
+            "to qr totp" => render(call, totp(call)?.as_bytes(), None),
+            "to qr mecard" => render(call, contact(call, input)?.mecard().as_bytes(), input_span),
+            _ => Err(LabeledError {
+                label: "Plugin call with wrong name signature".into(),
+                msg: "Plugin command does not exist".into(),
+                span: Some(call.head),
+            }),
+        }
+    }
+}
+
+/// Add the flags shared by all commands producing a qr image.
+fn render_flags(signature: PluginSignature) -> PluginSignature {
+    signature
+        .named("shape", SyntaxShape::String, "allowed: Square(Default), Circle, RoundedSquare, Vertical, Horizontal, Diamond, or $env.NU_QR_SHAPE if set", Some('s'))
+        .named("width", SyntaxShape::Int, "Target width (Default: $env.NU_QR_WIDTH, or 600)", Some('w'))
+        .named("height", SyntaxShape::Int, "Target height (Default: $env.NU_QR_HEIGHT)", Some('v'))
+        .named("background", SyntaxShape::List(Box::new(SyntaxShape::Int)), "background coler", Some('b'))
+}
+
+/// Flag of the commands returning codes for showing them as a summary.
+fn summary_flag(signature: PluginSignature) -> PluginSignature {
+    signature
+        .switch("summary", "return an image value shown as its symbology, size and payload in tables, which nushell converts to binary on demand", None)
+}
+
+/// Flags for finding and decoding codes in images.
+fn decode_flags(signature: PluginSignature) -> PluginSignature {
+    signature
+        .switch("ignore-error", "ignore errors if some parts are decodable", Some('i'))
+        .named("only", SyntaxShape::List(Box::new(SyntaxShape::String)), "only look for these symbologies, which is faster and avoids false positives, e.g. [qr code128 ean13]", None)
+        .switch("join", "join multiple codes into one value instead of returning a list", Some('j'))
+        .named("sort-by", SyntaxShape::String, "order of the codes of an image: position(Default, top to bottom and left to right), payload", None)
+        .switch("unique", "return codes found more than once in an image only once, with a count column in tables", Some('u'))
+        .switch("detailed", "return a table with the payload and metadata of every code", Some('d'))
+        .switch("raw", "always return payloads as binary, even if they are valid utf-8", Some('r'))
+        .named("checksum", SyntaxShape::String, "add a column with a checksum of every payload to tables: sha256, crc32", None)
+        .switch("parse", "parse payloads of well-known formats into records: wifi, mecard, otpauth, geo, mailto, sms, tel, vevent, epc, swiss qr-bill, upi, pix, gs1, smart health card, url", Some('p'))
+        .switch("redact", "hide secrets of payloads read with --parse, such as otp keys and wifi passwords", None)
+        .switch("auto", "parse payloads that are json, nuon or urls into structured values, like open does", Some('a'))
+        .switch("dcc", "decode EU Digital COVID Certificates (HC1:) into their claims, without verifying the signature", None)
+        .named("bytes-as", SyntaxShape::String, "return payloads that are not text as a string of their bytes: hex, base64", None)
+        .named("encoding", SyntaxShape::String, "text encoding of the payloads, e.g. shift_jis, euc-kr, gbk, latin1 (Default: declared by the code, or utf-8)", None)
+        .switch("guess-encoding", "guess shift_jis, gbk or latin1 for payloads that are not utf-8", None)
+        .switch("invert", "look for light-on-dark codes only, by default they are tried when nothing else is found", None)
+        .switch("try-rotate", "if nothing is found, retry with the image rotated by multiples of 90 and 15 degrees", None)
+        .named("timeout", SyntaxShape::Duration, "give up on an image after this long, e.g. 5sec", None)
+        .named("min-size", SyntaxShape::Int, "ignore codes whose longest side is shorter than this many pixels", None)
+        .named("max-size", SyntaxShape::Int, "ignore codes whose longest side is longer than this many pixels", None)
+        .switch("try-harder", "if nothing is found, retry with the image binarized and scaled in other ways, which is slow", None)
+        .named("crop", SyntaxShape::List(Box::new(SyntaxShape::Int)), "only look for codes in the region [x y width height] of the image", None)
+        .named("quad", SyntaxShape::List(Box::new(SyntaxShape::Int)), "corners [x0 y0 x1 y1 x2 y2 x3 y3] of a skewed code, clockwise from its top left, to correct its perspective", None)
+        .named("max-dimension", SyntaxShape::Int, "downscale images whose longest side exceeds this many pixels before detection, 0 for no limit (Default: 2048)", None)
+        .named("brightness", SyntaxShape::Int, "brighten (positive) or darken (negative) the image before detection", None)
+        .named("contrast", SyntaxShape::Number, "increase (positive) or decrease (negative) the contrast in percent before detection", None)
+        .switch("sharpen", "sharpen the image before detection, helps with blurry photos", None)
+        .switch("denoise", "remove speckle noise before detection", None)
+}
+
+/// Flags choosing the engines that find and sample qr codes.
+fn backend_flags(signature: PluginSignature) -> PluginSignature {
+    signature
+        .named("backend", SyntaxShape::String, "engine tried first to locate and sample codes, before falling back to the others: quircs(Default), rqrr, rxing", None)
+        .named("backends", SyntaxShape::List(Box::new(SyntaxShape::String)), "engines to try in order until one finds a code (Default: [quircs rqrr rxing])", None)
+}
+
+/// Show why a payload can not be built at `span`.
+fn invalid(span: Span) -> impl Fn(payloads::Error) -> LabeledError {
+    move |e| LabeledError {
+        label: e.label,
+        msg: e.msg,
+        span: Some(span),
+    }
+}
+
+/// Read the flags of `to qr wifi` into its payload.
+fn wifi(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let wifi = payloads::wifi::Wifi {
+        ssid: call.get_flag("ssid")?.unwrap_or_default(),
+        password: call.get_flag("password")?,
+        security: call.get_flag("security")?,
+        hidden: call.has_flag("hidden"),
+    };
+    payloads::wifi::build(&wifi).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr geo` into its payload.
+fn geo(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let geo = payloads::geo::Geo {
+        lat: call.get_flag("lat")?.unwrap_or_default(),
+        lon: call.get_flag("lon")?.unwrap_or_default(),
+        alt: call.get_flag("alt")?,
+    };
+    payloads::geo::build(&geo).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr mailto` into its payload.
+fn mailto(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let mailto = payloads::mailto::Mailto {
+        to: call.get_flag("to")?,
+        cc: call.get_flag("cc")?,
+        bcc: call.get_flag("bcc")?,
+        subject: call.get_flag("subject")?,
+        body: call.get_flag("body")?,
+    };
+    payloads::mailto::build(&mailto).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr sms` into its payload.
+fn sms(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let sms = payloads::sms::Sms {
+        number: call.get_flag("number")?.unwrap_or_default(),
+        message: call.get_flag("message")?,
+        format: call.get_flag("format")?,
+    };
+    payloads::sms::build(&sms).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr tel` into its payload.
+fn tel(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let tel = payloads::tel::Tel {
+        number: call.get_flag("number")?.unwrap_or_default(),
+        country: call.get_flag("country")?,
+    };
+    payloads::tel::build(&tel).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr epc` into its payload.
+fn epc(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let epc = payloads::epc::Epc {
+        name: call.get_flag("name")?.unwrap_or_default(),
+        iban: call.get_flag("iban")?.unwrap_or_default(),
+        bic: call.get_flag("bic")?,
+        amount: call.get_flag("amount")?,
+        purpose: call.get_flag("purpose")?,
+        reference: call.get_flag("reference")?,
+        text: call.get_flag("text")?,
+        info: call.get_flag("info")?,
+    };
+    payloads::epc::build(&epc).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr pix`, or else the columns of its input record,
+/// into its payload.
+fn pix(call: &EvaluatedCall, input: &Value) -> Result<String, LabeledError> {
+    let field = |name: &str| -> Result<Option<String>, LabeledError> {
+        match call.get_flag::<String>(name)? {
+            Some(v) => Ok(Some(v)),
+            None => get_string(input, name),
+        }
+    };
+    let amount = match call.get_flag::<f64>("amount")? {
+        Some(v) => Some(v),
+        None => match get(input, "amount") {
+            None | Some(Value::Nothing { .. }) => None,
+            Some(v) => Some(v.as_float().or_else(|_| v.as_int().map(|i| i as f64))?),
+        },
+    };
+    let pix = payloads::pix::Pix {
+        key: field("key")?,
+        name: field("name")?,
+        city: field("city")?,
+        description: field("description")?,
+        txid: field("txid")?,
+        amount,
+    };
+    payloads::pix::build(&pix).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr upi` into its payload.
+fn upi(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let upi = payloads::upi::Upi {
+        address: call.get_flag("address")?.unwrap_or_default(),
+        name: call.get_flag("name")?,
+        amount: call.get_flag("amount")?,
+        note: call.get_flag("note")?,
+        currency: call.get_flag("currency")?,
+    };
+    payloads::upi::build(&upi).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr crypto` into its payload.
+fn crypto(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let crypto = payloads::crypto::Crypto {
+        address: call.get_flag("address")?.unwrap_or_default(),
+        scheme: call.get_flag("scheme")?,
+        amount: call.get_flag("amount")?,
+        label: call.get_flag("label")?,
+        message: call.get_flag("message")?,
+    };
+    payloads::crypto::build(&crypto).map_err(invalid(call.head))
+}
+
+/// Read the flags of `to qr totp` into its payload.
+fn totp(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    let totp = payloads::otpauth::Totp {
+        issuer: call.get_flag("issuer")?,
+        account: call.get_flag("account")?.unwrap_or_default(),
+        secret: call.get_flag("secret")?.unwrap_or_default(),
+        digits: call.get_flag("digits")?,
+        period: call.get_flag("period")?,
+        algorithm: call.get_flag("algorithm")?,
+    };
+    payloads::otpauth::build(&totp).map_err(invalid(call.head))
+}
+
+/// Read the record given to `to qr vcard` and `to qr mecard`.
+fn contact(
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<payloads::contact::Contact, LabeledError> {
+    expect_record(input, call.head)?;
+    let name = get_string(input, "name")?.ok_or_else(|| LabeledError {
+        label: "Missing name".into(),
+        msg: "contact record requires a `name` column".into(),
+        span: Some(input.span().unwrap_or(call.head)),
+    })?;
+    let address = match get(input, "address") {
+        Some(record @ Value::Record { .. }) => Some(payloads::contact::Address {
+            street: get_string(record, "street")?.unwrap_or_default(),
+            city: get_string(record, "city")?.unwrap_or_default(),
+            region: get_string(record, "region")?.unwrap_or_default(),
+            postal: get_string(record, "postal")?.unwrap_or_default(),
+            country: get_string(record, "country")?.unwrap_or_default(),
+        }),
+        _ => get_string(input, "address")?.map(|street| payloads::contact::Address {
+            street,
+            ..Default::default()
+        }),
+    };
+    Ok(payloads::contact::Contact {
+        name,
+        org: get_string(input, "org")?,
+        phones: get_strings(input, "phone")?,
+        emails: get_strings(input, "email")?,
+        address,
+        urls: get_strings(input, "url")?,
+    })
+}
+
+/// Read the record given to `to qr vevent` into its payload.
+fn event(call: &EvaluatedCall, input: &Value) -> Result<String, LabeledError> {
+    expect_record(input, call.head)?;
+    let span = input.span().unwrap_or(call.head);
+    let date = |name: &str| match get(input, name) {
+        None | Some(Value::Nothing { .. }) => Ok(None),
+        Some(Value::Date { val, .. }) => Ok(Some(*val)),
+        Some(v) => Err(LabeledError {
+            label: format!("Invalid {}", name),
+            msg: format!("expected a datetime, got {}", v.get_type()),
+            span: Some(v.span().unwrap_or(call.head)),
+        }),
+    };
+    let event = payloads::event::Event {
+        title: get_string(input, "title")?.ok_or_else(|| LabeledError {
+            label: "Missing title".into(),
+            msg: "event record requires a `title` column".into(),
+            span: Some(span),
+        })?,
+        start: date("start")?.ok_or_else(|| LabeledError {
+            label: "Missing start".into(),
+            msg: "event record requires a `start` column".into(),
+            span: Some(span),
+        })?,
+        end: date("end")?,
+        location: get_string(input, "location")?,
+        description: get_string(input, "description")?,
+    };
+    payloads::event::build(&event).map_err(invalid(span))
+}
+
+/// Read the config text or record given to `to qr wireguard` into its payload.
+fn wireguard(call: &EvaluatedCall, input: &Value) -> Result<String, LabeledError> {
+    let span = input.span().unwrap_or(call.head);
+    let sections = match input {
+        Value::Record { .. } => wireguard_sections(call, input)?,
+        _ => payloads::wireguard::parse(&input.as_string()?).map_err(|msg| LabeledError {
+            label: "Invalid WireGuard config".into(),
+            msg,
+            span: Some(span),
+        })?,
+    };
+    payloads::wireguard::build(&sections).map_err(invalid(span))
+}
+
+/// Convert a wireguard config record into sections, keeping the column order.
+fn wireguard_sections(
+    call: &EvaluatedCall,
+    input: &Value,
+) -> Result<Vec<payloads::wireguard::Section>, LabeledError> {
+    let entries = |value: &Value| -> Result<Vec<(String, String)>, LabeledError> {
+        let (cols, vals) = value.as_record()?;
+        cols.iter()
+            .zip(vals)
+            .filter(|(_, v)| !v.is_nothing())
+            .map(|(k, v)| {
+                let v = match v {
+                    Value::List { vals, .. } => vals
+                        .iter()
+                        .map(|v| v.as_string())
+                        .collect::<Result<Vec<_>, _>>()?
+                        .join(", "),
+                    v => v.as_string()?,
+                };
+                Ok((k.clone(), v))
+            })
+            .collect()
+    };
+    let (cols, vals) = input.as_record()?;
+    let mut sections = vec![];
+    for (name, value) in cols.iter().zip(vals) {
+        match value {
+            Value::List { vals, .. } => {
+                for v in vals {
+                    sections.push((name.clone(), entries(v)?));
+                }
+            }
+            Value::Record { .. } => sections.push((name.clone(), entries(value)?)),
+            v => {
+                return Err(LabeledError {
+                    label: "Invalid config".into(),
+                    msg: format!("section {} should be a record, got {}", name, v.get_type()),
+                    span: Some(v.span().unwrap_or(call.head)),
+                })
+            }
+        }
+    }
+    Ok(sections)
+}
+
+/// Fail unless `input` is a record.
+fn expect_record(input: &Value, head: Span) -> Result<(), LabeledError> {
+    match input {
+        Value::Record { .. } => Ok(()),
+        _ => Err(LabeledError {
+            label: "Invalid input".into(),
+            msg: format!("expected a record, got {}", input.get_type()),
+            span: Some(input.span().unwrap_or(head)),
+        }),
+    }
+}
+
+/// Look up the column `name` of a record, ignoring case.
+fn get<'a>(record: &'a Value, name: &str) -> Option<&'a Value> {
+    match record {
+        Value::Record { cols, vals, .. } => cols
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(name))
+            .map(|i| &vals[i]),
+        _ => None,
+    }
+}
+
+/// Read a text column of a record, treating `null` and empty strings as missing.
+fn get_string(record: &Value, name: &str) -> Result<Option<String>, LabeledError> {
+    match get(record, name) {
+        None | Some(Value::Nothing { .. }) => Ok(None),
+        Some(v) => Ok(Some(v.as_string()?).filter(|s| !s.is_empty())),
+    }
+}
+
+/// Read a column holding either a single string or a list of strings.
+fn get_strings(record: &Value, name: &str) -> Result<Vec<String>, LabeledError> {
+    match get(record, name) {
+        None | Some(Value::Nothing { .. }) => Ok(vec![]),
+        Some(Value::List { vals, .. }) => Ok(vals
+            .iter()
+            .map(|v| v.as_string())
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect()),
+        Some(v) => Ok(Some(v.as_string()?)
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect()),
+    }
+}
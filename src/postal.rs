@@ -0,0 +1,241 @@
+//! Postal barcodes for `to barcode`: POSTNET, the USPS Intelligent Mail
+//! barcode and the Royal Mail 4-state customer code.
+//!
+//! Their bars are all a module wide and a module apart, and tell their data
+//! by how far they reach up and down from the tracker in the middle. rxing
+//! reads none of them.
+
+use std::ops::Range;
+
+/// A bar of a postal barcode, or of two-track pharmacode, by the part of
+/// the bar height it covers.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bar {
+    Full,
+    Ascender,
+    Descender,
+    Tracker,
+    /// The short bar of POSTNET, standing on the bottom line.
+    Short,
+    /// The halves of two-track pharmacode.
+    Top,
+    Bottom,
+}
+
+impl Bar {
+    /// Rows of pixels the bar covers, for bars `height` pixels high.
+    pub fn rows(self, height: usize) -> Range<usize> {
+        let sixths = match self {
+            Bar::Full => 0..6,
+            Bar::Ascender => 0..4,
+            Bar::Descender => 2..6,
+            Bar::Tracker => 2..4,
+            Bar::Short => 4..6,
+            Bar::Top => 0..3,
+            Bar::Bottom => 3..6,
+        };
+        sixths.start * height / 6..sixths.end * height / 6
+    }
+}
+
+/// Tall bars of the POSTNET digits, of 5 bars from the left weighing 7, 4,
+/// 2, 1 and 0, but for 0 whose tall bars add up to 11.
+const POSTNET: [u8; 10] = [
+    0b11000, 0b00011, 0b00101, 0b00110, 0b01001, 0b01010, 0b01100, 0b10001, 0b10010, 0b10100,
+];
+
+/// Characters of the Royal Mail code, 6 rows of 6 columns.
+const ROYAL_MAIL: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Which 2 of 4 bars reach up (or down), for rows (or columns) 1 to 6.
+const ROYAL_MAIL_HALVES: [u8; 6] = [0b0011, 0b0101, 0b0110, 0b1001, 0b1010, 0b1100];
+
+/// Positions of the 13 bits of the 10 characters of an Intelligent Mail
+/// barcode, counted from 1: the descenders of the 65 bars and then their
+/// ascenders.
+#[rustfmt::skip]
+const IMB_BARS: [u8; 130] = [
+    67, 6, 78, 16, 86, 95, 34, 40, 45, 113, 117, 121, 62, 87, 18, 104, 41, 76, 57, 119, 115, 72, 97,
+    2, 127, 26, 105, 35, 122, 52, 114, 7, 24, 82, 68, 63, 94, 44, 77, 112, 70, 100, 39, 30, 107,
+    15, 125, 85, 10, 65, 54, 88, 20, 106, 46, 66, 8, 116, 29, 61, 99, 80, 90, 37, 123, 51, 25, 84,
+    129, 56, 4, 109, 96, 28, 36, 47, 11, 71, 33, 102, 21, 9, 17, 49, 124, 79, 64, 91, 42, 69, 53,
+    60, 14, 1, 27, 103, 126, 75, 89, 50, 120, 19, 32, 110, 92, 111, 130, 59, 31, 12, 81, 43, 55,
+    5, 74, 22, 101, 128, 58, 118, 48, 108, 38, 98, 93, 23, 83, 13, 73, 3,
+];
+
+/// The digits of `text`, which may be grouped with dashes or spaces.
+fn digits(text: &str) -> Result<Vec<u32>, String> {
+    text.chars()
+        .filter(|c| !matches!(c, '-' | ' '))
+        .map(|c| {
+            c.to_digit(10)
+                .ok_or_else(|| "should only contain digits".to_string())
+        })
+        .collect()
+}
+
+/// POSTNET bars of a ZIP code of 5 digits, ZIP+4 of 9, or delivery point of
+/// 11, with or without the check digit.
+pub fn postnet(text: &str) -> Result<Vec<Bar>, String> {
+    let mut digits = digits(text)?;
+    let check = |digits: &[u32]| (10 - digits.iter().sum::<u32>() % 10) % 10;
+    match digits.len() {
+        5 | 9 | 11 => digits.push(check(&digits)),
+        n @ (6 | 10 | 12) => {
+            let expected = check(&digits[..n - 1]);
+            if digits[n - 1] != expected {
+                return Err(format!("check digit should be {}", expected));
+            }
+        }
+        n => {
+            return Err(format!(
+                "should have 5, 9 or 11 digits, or one more with the check digit, got {}",
+                n
+            ))
+        }
+    }
+    let mut bars = vec![Bar::Full];
+    for d in digits {
+        bars.extend((0..5).rev().map(|i| match POSTNET[d as usize] >> i & 1 {
+            1 => Bar::Full,
+            _ => Bar::Short,
+        }));
+    }
+    bars.push(Bar::Full);
+    Ok(bars)
+}
+
+/// The frame check sequence of the 102 bits of an Intelligent Mail barcode,
+/// a CRC-11 from its most significant bit.
+fn imb_fcs(value: u128) -> u16 {
+    let mut fcs = 0x7ff;
+    for i in (0..102).rev() {
+        let bit = (value >> i & 1) as u16;
+        fcs = match (fcs >> 10 ^ bit) & 1 {
+            1 => fcs << 1 ^ 0xf35,
+            _ => fcs << 1,
+        } & 0x7ff;
+    }
+    fcs
+}
+
+/// The 13 bit characters with `n` bars of 13, in the order of the codewords
+/// they stand for: pairs of a pattern and its reverse, then the
+/// symmetrical patterns from the end.
+fn imb_characters(n: u32) -> Vec<u16> {
+    let mut pairs = vec![];
+    let mut symmetrical = vec![];
+    for c in 0..1u16 << 13 {
+        let reverse = c.reverse_bits() >> 3;
+        if c.count_ones() != n || reverse < c {
+            continue;
+        }
+        match reverse == c {
+            true => symmetrical.push(c),
+            false => pairs.extend([c, reverse]),
+        }
+    }
+    pairs.extend(symmetrical.iter().rev());
+    pairs
+}
+
+/// Intelligent Mail bars of a 20 digit tracking code followed by a routing
+/// ZIP code of 0, 5, 9 or 11 digits.
+pub fn intelligent_mail(text: &str) -> Result<Vec<Bar>, String> {
+    let digits = digits(text)?;
+    if !matches!(digits.len(), 20 | 25 | 29 | 31) {
+        return Err(format!(
+            "should have a 20 digit tracking code and a routing code of 0, 5, 9 or 11 digits, got {} digits",
+            digits.len()
+        ));
+    }
+    let (tracking, routing) = digits.split_at(20);
+    let routing_value = routing.iter().fold(0u128, |n, d| n * 10 + *d as u128);
+    let mut value = match routing.len() {
+        0 => 0,
+        5 => routing_value + 1,
+        9 => routing_value + 100_001,
+        _ => routing_value + 1_000_100_001,
+    };
+    if tracking[1] > 4 {
+        return Err("the second digit of the barcode id should be 0 to 4".into());
+    }
+    value = value * 10 + tracking[0] as u128;
+    value = value * 5 + tracking[1] as u128;
+    for d in &tracking[2..] {
+        value = value * 10 + *d as u128;
+    }
+    let fcs = imb_fcs(value);
+    let mut codewords = [0; 10];
+    codewords[9] = (value % 636) as usize * 2;
+    value /= 636;
+    for cw in codewords[1..9].iter_mut().rev() {
+        *cw = (value % 1365) as usize;
+        value /= 1365;
+    }
+    codewords[0] = value as usize + if fcs >> 10 & 1 == 1 { 659 } else { 0 };
+    let (five, two) = (imb_characters(5), imb_characters(2));
+    let characters: Vec<u16> = codewords
+        .iter()
+        .enumerate()
+        .map(|(i, &cw)| {
+            let c = five
+                .get(cw)
+                .copied()
+                .unwrap_or_else(|| two[cw - five.len()]);
+            match fcs >> i & 1 {
+                1 => !c & 0x1fff,
+                _ => c,
+            }
+        })
+        .collect();
+    let mut bits = [false; 130];
+    for (i, position) in IMB_BARS.iter().enumerate() {
+        bits[*position as usize - 1] = characters[i / 13] >> (i % 13) & 1 == 1;
+    }
+    Ok((0..65)
+        .map(|i| match (bits[65 + i], bits[i]) {
+            (true, true) => Bar::Full,
+            (true, false) => Bar::Ascender,
+            (false, true) => Bar::Descender,
+            (false, false) => Bar::Tracker,
+        })
+        .collect())
+}
+
+/// Royal Mail bars of a postcode and delivery point, with their check
+/// character.
+pub fn royal_mail(text: &str) -> Result<Vec<Bar>, String> {
+    let mut positions = vec![];
+    for c in text.to_uppercase().chars().filter(|c| *c != ' ') {
+        match ROYAL_MAIL.find(c) {
+            Some(i) => positions.push((i / 6, i % 6)),
+            None => {
+                return Err(format!(
+                    "{:?} cannot be encoded, the royal mail code only has letters and digits",
+                    c
+                ))
+            }
+        }
+    }
+    if positions.is_empty() {
+        return Err("should have a postcode".into());
+    }
+    // Rows and columns count from 1, and their sums modulo 6 from 1 to 6.
+    let sum = |f: fn(&(usize, usize)) -> usize| {
+        (positions.iter().map(|p| f(p) + 1).sum::<usize>() + 5) % 6
+    };
+    positions.push((sum(|p| p.0), sum(|p| p.1)));
+    let mut bars = vec![Bar::Ascender];
+    for (row, column) in positions {
+        let (up, down) = (ROYAL_MAIL_HALVES[row], ROYAL_MAIL_HALVES[column]);
+        bars.extend((0..4).rev().map(|i| match (up >> i & 1, down >> i & 1) {
+            (1, 1) => Bar::Full,
+            (1, _) => Bar::Ascender,
+            (_, 1) => Bar::Descender,
+            _ => Bar::Tracker,
+        }));
+    }
+    bars.push(Bar::Full);
+    Ok(bars)
+}
@@ -0,0 +1,604 @@
+//! Image adjustments applied before detection.
+
+use image::{imageops, GrayImage};
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{Span, Spanned, Value};
+use quircs::{Capstone, Point};
+
+/// Images larger than this are downscaled unless `--max-dimension` says otherwise.
+const DEFAULT_MAX_DIMENSION: u32 = 2048;
+
+/// Finder patterns combined into outlines, enough for three codes.
+const MAX_FINDER_PATTERNS: usize = 9;
+
+/// Adjustments requested by the flags of `from qr`.
+#[derive(Clone)]
+pub struct Preprocess {
+    /// Corners of a skewed code to rectify, clockwise from the top left.
+    quad: Option<Spanned<[(f64, f64); 4]>>,
+    /// Region to search in as `[x y width height]`.
+    crop: Option<Spanned<[u32; 4]>>,
+    /// Longest side of the image to search in, 0 for no limit.
+    max_dimension: u32,
+    /// Value added to every pixel, see [imageops::brighten].
+    brightness: Option<i32>,
+    /// Contrast change in percent, see [imageops::contrast].
+    contrast: Option<f32>,
+    /// Apply an unsharp mask.
+    sharpen: bool,
+    /// Apply a median filter.
+    denoise: bool,
+}
+
+impl Default for Preprocess {
+    /// No adjustments but downscaling large images, as without any flags.
+    fn default() -> Self {
+        Preprocess {
+            quad: None,
+            crop: None,
+            max_dimension: DEFAULT_MAX_DIMENSION,
+            brightness: None,
+            contrast: None,
+            sharpen: false,
+            denoise: false,
+        }
+    }
+}
+
+impl Preprocess {
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let max_dimension = match call.get_flag::<i64>("max-dimension")? {
+            Some(v) if v < 0 => {
+                return Err(LabeledError {
+                    label: "Invalid max dimension".into(),
+                    msg: "should be a positive number of pixels, or 0 for no limit".into(),
+                    span: Some(call.head),
+                })
+            }
+            Some(v) => v.min(u32::MAX as i64) as u32,
+            None => DEFAULT_MAX_DIMENSION,
+        };
+        let quad = match call.get_flag_value("quad") {
+            Some(value) => Some(quad(&value, call.head)?),
+            None => None,
+        };
+        if let (Some(_), Some(crop)) = (&quad, call.get_flag_value("crop")) {
+            return Err(LabeledError {
+                label: "Conflicting flags".into(),
+                msg: "--crop can not be combined with --quad".into(),
+                span: Some(crop.span().unwrap_or(call.head)),
+            });
+        }
+        Ok(Preprocess {
+            quad,
+            crop: match call.get_flag_value("crop") {
+                Some(value) => Some(crop_region(&value, call.head)?),
+                None => None,
+            },
+            max_dimension,
+            brightness: call.get_flag::<i64>("brightness")?.map(|v| v as i32),
+            contrast: call.get_flag::<f64>("contrast")?.map(|v| v as f32),
+            sharpen: call.has_flag("sharpen"),
+            denoise: call.has_flag("denoise"),
+        })
+    }
+
+    /// Prepare `image` for detection.
+    ///
+    /// Returns the image along with the way back to coordinates of the original.
+    pub fn apply(&self, mut image: GrayImage) -> Result<(GrayImage, Origin), LabeledError> {
+        let mut origin = Origin {
+            scale: 1.0,
+            x: 0,
+            y: 0,
+            perspective: None,
+        };
+        if let Some(Spanned { item: quad, span }) = self.quad {
+            let Some((rectified, perspective)) = rectify(&image, quad) else {
+                return Err(LabeledError {
+                    label: "Invalid quad".into(),
+                    msg: "three of the corners are on a line".into(),
+                    span: Some(span),
+                });
+            };
+            image = rectified;
+            origin.perspective = Some(perspective);
+        }
+        if let Some(Spanned {
+            item: [x, y, w, h],
+            span,
+        }) = self.crop
+        {
+            if x >= image.width() || y >= image.height() {
+                return Err(LabeledError {
+                    label: "Crop region is outside of the image".into(),
+                    msg: format!("image is {}x{}", image.width(), image.height()),
+                    span: Some(span),
+                });
+            }
+            image = imageops::crop_imm(&image, x, y, w, h).to_image();
+            (origin.x, origin.y) = (x, y);
+        }
+        let longest = image.width().max(image.height());
+        if self.max_dimension > 0 && longest > self.max_dimension {
+            let scale = longest as f64 / self.max_dimension as f64;
+            let (w, h) = (image.width() as f64 / scale, image.height() as f64 / scale);
+            origin.scale = scale;
+            image =
+                imageops::thumbnail(&image, (w.round() as u32).max(1), (h.round() as u32).max(1));
+        }
+        if self.denoise {
+            image = median3(&image);
+        }
+        if let Some(value) = self.brightness {
+            image = imageops::brighten(&image, value);
+        }
+        if let Some(value) = self.contrast {
+            image = imageops::contrast(&image, value);
+        }
+        if self.sharpen {
+            // A light mask: enough to restore module edges of blurry photos
+            // without amplifying sensor noise.
+            image = imageops::unsharpen(&image, 1.5, 4);
+        }
+        Ok((image, origin))
+    }
+}
+
+/// Maps coordinates of a prepared image back to the original image.
+pub struct Origin {
+    scale: f64,
+    x: u32,
+    y: u32,
+    perspective: Option<Perspective>,
+}
+
+impl Origin {
+    pub fn map(&self, p: &mut Point) {
+        p.x = (p.x as f64 * self.scale).round() as i32 + self.x as i32;
+        p.y = (p.y as f64 * self.scale).round() as i32 + self.y as i32;
+        if let Some(perspective) = &self.perspective {
+            perspective.unmap(p);
+        }
+    }
+}
+
+/// Parse the `[x y width height]` list given to `--crop`.
+fn crop_region(value: &Value, head: Span) -> Result<Spanned<[u32; 4]>, LabeledError> {
+    let span = value.span().unwrap_or(head);
+    let invalid = || LabeledError {
+        label: "Invalid crop region".into(),
+        msg: "should be a list of four non-negative integers: [x y width height]".into(),
+        span: Some(span),
+    };
+    let ints = value
+        .as_list()
+        .map_err(|_| invalid())?
+        .iter()
+        .map(|v| v.as_i64().ok().and_then(|v| u32::try_from(v).ok()))
+        .collect::<Option<Vec<u32>>>()
+        .ok_or_else(invalid)?;
+    match ints[..] {
+        [x, y, w, h] if w > 0 && h > 0 => Ok(Spanned {
+            item: [x, y, w, h],
+            span,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parse the `[x0 y0 x1 y1 x2 y2 x3 y3]` list given to `--quad`.
+fn quad(value: &Value, head: Span) -> Result<Spanned<[(f64, f64); 4]>, LabeledError> {
+    let span = value.span().unwrap_or(head);
+    let invalid = || LabeledError {
+        label: "Invalid quad".into(),
+        msg: "should be a list of eight integers: [x0 y0 x1 y1 x2 y2 x3 y3]".into(),
+        span: Some(span),
+    };
+    let ints = value
+        .as_list()
+        .map_err(|_| invalid())?
+        .iter()
+        .map(|v| v.as_i64().ok().map(|v| v as f64))
+        .collect::<Option<Vec<f64>>>()
+        .ok_or_else(invalid)?;
+    match ints[..] {
+        [x0, y0, x1, y1, x2, y2, x3, y3] => Ok(Spanned {
+            item: [(x0, y0), (x1, y1), (x2, y2), (x3, y3)],
+            span,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
+/// Replace every pixel by the median of its 3x3 neighbourhood, which removes
+/// speckles while keeping the edges between modules sharp.
+fn median3(image: &GrayImage) -> GrayImage {
+    let (w, h) = image.dimensions();
+    GrayImage::from_fn(w, h, |x, y| {
+        let mut v = [0u8; 9];
+        for (i, (dx, dy)) in (0..9).map(|i| (i % 3, i / 3)).enumerate() {
+            let px = (x + dx).saturating_sub(1).min(w - 1);
+            let py = (y + dy).saturating_sub(1).min(h - 1);
+            v[i] = image.get_pixel(px, py).0[0];
+        }
+        v.sort_unstable();
+        image::Luma([v[4]])
+    })
+}
+
+/// How `--try-harder` turns an image black and white, instead of leaving it
+/// to the global threshold of quirc.
+#[derive(Clone, Copy)]
+pub enum Binarization {
+    /// Compare every pixel to the mean of its neighbourhood, for uneven lighting.
+    Adaptive,
+    /// A fixed global threshold.
+    Threshold(u8),
+}
+
+pub fn binarize(image: &GrayImage, binarization: Binarization) -> GrayImage {
+    let (w, h) = image.dimensions();
+    let radius = match binarization {
+        Binarization::Threshold(t) => {
+            return GrayImage::from_fn(w, h, |x, y| {
+                image::Luma([if image.get_pixel(x, y).0[0] < t {
+                    0
+                } else {
+                    255
+                }])
+            })
+        }
+        // A window of an eighth of the image, wide enough to span some modules.
+        Binarization::Adaptive => (w.max(h) / 16).max(7),
+    };
+    // Summed area table, for the mean of any window in constant time.
+    let stride = w as usize + 1;
+    let mut sum = vec![0u64; stride * (h as usize + 1)];
+    for y in 0..h as usize {
+        let mut row = 0;
+        for x in 0..w as usize {
+            row += image.get_pixel(x as u32, y as u32).0[0] as u64;
+            sum[(y + 1) * stride + x + 1] = sum[y * stride + x + 1] + row;
+        }
+    }
+    GrayImage::from_fn(w, h, |x, y| {
+        let (x0, y0) = (
+            x.saturating_sub(radius) as usize,
+            y.saturating_sub(radius) as usize,
+        );
+        let (x1, y1) = (
+            (x + radius + 1).min(w) as usize,
+            (y + radius + 1).min(h) as usize,
+        );
+        let total = sum[y1 * stride + x1] + sum[y0 * stride + x0]
+            - sum[y0 * stride + x1]
+            - sum[y1 * stride + x0];
+        let mean = total / ((x1 - x0) * (y1 - y0)) as u64;
+        // A pixel has to be clearly darker than its surroundings, so that
+        // flat regions stay white instead of turning into noise.
+        image::Luma([if (image.get_pixel(x, y).0[0] as u64) + 8 < mean {
+            0
+        } else {
+            255
+        }])
+    })
+}
+
+/// Resize `image` by `factor`.
+pub fn scale(image: &GrayImage, factor: f64) -> GrayImage {
+    let (w, h) = (
+        ((image.width() as f64 * factor).round() as u32).max(1),
+        ((image.height() as f64 * factor).round() as u32).max(1),
+    );
+    match factor < 1.0 {
+        true => imageops::thumbnail(image, w, h),
+        false => imageops::resize(image, w, h, imageops::FilterType::Triangle),
+    }
+}
+
+/// Surround `image` with a margin of its background color, a quiet zone for
+/// codes that were cropped right at their edge.
+///
+/// The background is taken to be the light color of the border, as the
+/// border of such an image is the edge of a dark-on-light code. Returns the
+/// padded image and the width of the margin.
+pub fn pad(image: &GrayImage) -> (GrayImage, u32) {
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        return (image.clone(), 0);
+    }
+    let mut border: Vec<u8> = (0..w)
+        .flat_map(|x| [(x, 0), (x, h - 1)])
+        .chain((0..h).flat_map(|y| [(0, y), (w - 1, y)]))
+        .map(|(x, y)| image.get_pixel(x, y)[0])
+        .collect();
+    border.sort_unstable();
+    // A little below the lightest, which may be noise.
+    let background = border[border.len() * 9 / 10];
+    // Four modules of a version 1 code filling the image.
+    let margin = (w.min(h) / 5).max(8);
+    let mut padded =
+        GrayImage::from_pixel(w + 2 * margin, h + 2 * margin, image::Luma([background]));
+    imageops::replace(&mut padded, image, margin as i64, margin as i64);
+    (padded, margin)
+}
+
+/// Read the EXIF orientation of an encoded image, 1 (upright) if there is none.
+pub fn orientation(bytes: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut std::io::Cursor::new(bytes))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
+        .unwrap_or(1)
+}
+
+/// Turn an image stored with EXIF `orientation` upright.
+pub fn orient(image: GrayImage, orientation: u32) -> GrayImage {
+    match orientation {
+        2 => imageops::flip_horizontal(&image),
+        3 => imageops::rotate180(&image),
+        4 => imageops::flip_vertical(&image),
+        5 => imageops::flip_horizontal(&imageops::rotate90(&image)),
+        6 => imageops::rotate90(&image),
+        7 => imageops::flip_horizontal(&imageops::rotate270(&image)),
+        8 => imageops::rotate270(&image),
+        _ => image,
+    }
+}
+
+/// Rotate `image` clockwise by `degrees` around its center, onto a white
+/// canvas that is large enough to hold all of it.
+pub fn rotate(image: &GrayImage, degrees: f64) -> (GrayImage, Rotation) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (w, h) = (image.width() as f64, image.height() as f64);
+    let (nw, nh) = (
+        (w * cos.abs() + h * sin.abs()).round(),
+        (w * sin.abs() + h * cos.abs()).round(),
+    );
+    let rotation = Rotation {
+        sin,
+        cos,
+        center: (w / 2.0, h / 2.0),
+        rotated_center: (nw / 2.0, nh / 2.0),
+    };
+    let rotated = GrayImage::from_fn(nw as u32, nh as u32, |x, y| {
+        let (sx, sy) = rotation.source(x as f64 + 0.5, y as f64 + 0.5);
+        image::Luma([bilinear(image, sx - 0.5, sy - 0.5)])
+    });
+    (rotated, rotation)
+}
+
+/// Maps coordinates of a rotated image back to the image before rotation.
+pub struct Rotation {
+    sin: f64,
+    cos: f64,
+    center: (f64, f64),
+    rotated_center: (f64, f64),
+}
+
+impl Rotation {
+    fn source(&self, x: f64, y: f64) -> (f64, f64) {
+        let (dx, dy) = (x - self.rotated_center.0, y - self.rotated_center.1);
+        (
+            dx * self.cos + dy * self.sin + self.center.0,
+            -dx * self.sin + dy * self.cos + self.center.1,
+        )
+    }
+
+    pub fn unmap(&self, p: &mut Point) {
+        let (x, y) = self.source(p.x as f64, p.y as f64);
+        (p.x, p.y) = (x.round() as i32, y.round() as i32);
+    }
+}
+
+/// Sample `image` at a fractional position, white outside of it.
+fn bilinear(image: &GrayImage, x: f64, y: f64) -> u8 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let at = |x: f64, y: f64| -> f64 {
+        if x < 0.0 || y < 0.0 || x >= image.width() as f64 || y >= image.height() as f64 {
+            255.0
+        } else {
+            image.get_pixel(x as u32, y as u32).0[0] as f64
+        }
+    };
+    let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1.0, y0) * fx;
+    let bottom = at(x0, y0 + 1.0) * (1.0 - fx) + at(x0 + 1.0, y0 + 1.0) * fx;
+    (top * (1.0 - fy) + bottom * fy).round() as u8
+}
+
+/// A projective transformation of the plane.
+pub struct Perspective([f64; 9]);
+
+impl Perspective {
+    /// The transformation taking every corner of `from` to the same corner of
+    /// `to`, unless three of them are on a line.
+    fn between(from: [(f64, f64); 4], to: [(f64, f64); 4]) -> Option<Self> {
+        let mut m = [[0.0; 9]; 8];
+        for (i, ((u, v), (x, y))) in from.into_iter().zip(to).enumerate() {
+            m[2 * i] = [u, v, 1.0, 0.0, 0.0, 0.0, -u * x, -v * x, x];
+            m[2 * i + 1] = [0.0, 0.0, 0.0, u, v, 1.0, -u * y, -v * y, y];
+        }
+        // Gauss-Jordan elimination with partial pivoting.
+        for col in 0..8 {
+            let pivot = (col..8).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+            if m[pivot][col].abs() < 1e-9 {
+                return None;
+            }
+            m.swap(col, pivot);
+            let row = m[col];
+            for (i, other) in m.iter_mut().enumerate() {
+                if i != col {
+                    let f = other[col] / row[col];
+                    other.iter_mut().zip(row).for_each(|(a, b)| *a -= f * b);
+                }
+            }
+        }
+        let mut h = [1.0; 9];
+        for (i, row) in m.iter().enumerate() {
+            h[i] = row[8] / row[i];
+        }
+        Some(Perspective(h))
+    }
+
+    /// The transformation of a finder pattern, from its 7x7 modules to the image.
+    fn of_capstone(capstone: &Capstone) -> Self {
+        let c = capstone.c;
+        Perspective([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7], 1.0])
+    }
+
+    /// The transformation undoing this one.
+    fn inverse(&self) -> Self {
+        let [a, b, c, d, e, f, g, h, i] = self.0;
+        // The adjugate, which is the inverse up to a scale factor.
+        Perspective([
+            e * i - f * h,
+            c * h - b * i,
+            b * f - c * e,
+            f * g - d * i,
+            a * i - c * g,
+            c * d - a * f,
+            d * h - e * g,
+            b * g - a * h,
+            a * e - b * d,
+        ])
+    }
+
+    fn source(&self, x: f64, y: f64) -> (f64, f64) {
+        let h = &self.0;
+        let w = h[6] * x + h[7] * y + h[8];
+        (
+            (h[0] * x + h[1] * y + h[2]) / w,
+            (h[3] * x + h[4] * y + h[5]) / w,
+        )
+    }
+
+    /// Map coordinates of a rectified image back to the image before rectification.
+    pub fn unmap(&self, p: &mut Point) {
+        let (x, y) = self.source(p.x as f64, p.y as f64);
+        (p.x, p.y) = (x.round() as i32, y.round() as i32);
+    }
+}
+
+/// Warp the quadrilateral `quad` of `image`, clockwise from the top left,
+/// onto an upright square with a margin around it for the quiet zone.
+///
+/// Returns `None` if the quadrilateral is degenerate.
+pub fn rectify(image: &GrayImage, quad: [(f64, f64); 4]) -> Option<(GrayImage, Perspective)> {
+    let distance = |(x0, y0): (f64, f64), (x1, y1): (f64, f64)| (x1 - x0).hypot(y1 - y0);
+    // The longest edge keeps the most detail, bounded so that a bogus quad
+    // can not allocate more than the image itself.
+    let side = (0..4)
+        .map(|i| distance(quad[i], quad[(i + 1) % 4]))
+        .fold(0.0, f64::max)
+        .min(image.width().max(image.height()) as f64)
+        .round();
+    if side < 1.0 {
+        return None;
+    }
+    let (a, b) = ((side / 8.0).ceil(), (side / 8.0).ceil() + side);
+    let perspective = Perspective::between([(a, a), (b, a), (b, b), (a, b)], quad)?;
+    let size = (a + b) as u32;
+    let rectified = GrayImage::from_fn(size, size, |x, y| {
+        let (sx, sy) = perspective.source(x as f64 + 0.5, y as f64 + 0.5);
+        image::Luma([bilinear(image, sx - 0.5, sy - 0.5)])
+    });
+    Some((rectified, perspective))
+}
+
+/// Outlines of qr codes located by their finder patterns, clockwise from the
+/// top left.
+///
+/// quirc fits a grid through the finder patterns of a code, which goes wrong
+/// under steep perspective. The outline instead follows the edges of the
+/// finder patterns, as straight lines stay straight in any perspective.
+pub fn outlines(image: &GrayImage) -> Vec<[(f64, f64); 4]> {
+    let mut quirc = quircs::Quirc::default();
+    quirc.identify(image.width() as usize, image.height() as usize, image);
+    let capstones = &quirc.capstones[..quirc.capstones.len().min(MAX_FINDER_PATTERNS)];
+    let mut v = Vec::new();
+    for (i, a) in capstones.iter().enumerate() {
+        for (j, b) in capstones.iter().enumerate().skip(i + 1) {
+            for c in &capstones[j + 1..] {
+                v.extend(outline([a, b, c]));
+            }
+        }
+    }
+    v
+}
+
+/// Outline of the code with the finder patterns `capstones`, if they are
+/// arranged like the corners of one.
+fn outline(capstones: [&Capstone; 3]) -> Option<[(f64, f64); 4]> {
+    let center = |c: &Capstone| (c.center.x as f64, c.center.y as f64);
+    // Direction of `other` in the modules of finder pattern `c`, relative to
+    // its center. The transformation of a finder pattern is only accurate
+    // near it, so the direction is measured a pattern's width away.
+    let offset = |c: &Capstone, other: &Capstone| {
+        let ((x0, y0), (x1, y1)) = (center(c), center(other));
+        let width = (c.corners[2].x as f64 - c.corners[0].x as f64)
+            .hypot(c.corners[2].y as f64 - c.corners[0].y as f64);
+        let k = (width / (x1 - x0).hypot(y1 - y0)).min(1.0);
+        let (u, v) = Perspective::of_capstone(c)
+            .inverse()
+            .source(x0 + (x1 - x0) * k, y0 + (y1 - y0) * k);
+        (u - 3.5, v - 3.5)
+    };
+    // The other two patterns of the top left one are each in line with one of
+    // its axes, like quirc groups them.
+    let in_line = |(u, v): (f64, f64)| u.abs() < 0.2 * v.abs() || v.abs() < 0.2 * u.abs();
+    let i = (0..3).find(|&i| {
+        let (a, b) = (
+            offset(capstones[i], capstones[(i + 1) % 3]),
+            offset(capstones[i], capstones[(i + 2) % 3]),
+        );
+        in_line(a) && in_line(b) && (a.0.abs() < a.1.abs()) != (b.0.abs() < b.1.abs())
+    })?;
+    let (tl, mut tr, mut bl) = (capstones[i], capstones[(i + 1) % 3], capstones[(i + 2) % 3]);
+    let (o, a, b) = (center(tl), center(tr), center(bl));
+    if (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0) < 0.0 {
+        (tr, bl) = (bl, tr);
+    }
+    // The outer edges of finder pattern `c`: the one facing away from
+    // `along`, which is in line with it, and the perpendicular one facing
+    // away from `across`.
+    let edges = |c: &Capstone, along: &Capstone, across: &Capstone| {
+        let ((u, v), (u2, v2)) = (offset(c, along), offset(c, across));
+        let perspective = Perspective::of_capstone(c);
+        let away = |d: f64| if d > 0.0 { 0.0 } else { 7.0 };
+        let column = |u| (perspective.source(u, 0.0), perspective.source(u, 7.0));
+        let row = |v| (perspective.source(0.0, v), perspective.source(7.0, v));
+        match u.abs() > v.abs() {
+            true => (column(away(u)), row(away(v2))),
+            false => (row(away(v)), column(away(u2))),
+        }
+    };
+    let (left, top) = edges(tl, tr, bl);
+    let (right, top_of_right) = edges(tr, tl, bl);
+    let (bottom, left_of_bottom) = edges(bl, tl, tr);
+    Some([
+        intersection(left, top)?,
+        intersection(right, top_of_right)?,
+        intersection(right, bottom)?,
+        intersection(bottom, left_of_bottom)?,
+    ])
+}
+
+/// Point where the lines through `a` and `b` cross, unless they are parallel.
+fn intersection(a: ((f64, f64), (f64, f64)), b: ((f64, f64), (f64, f64))) -> Option<(f64, f64)> {
+    let (((x1, y1), (x2, y2)), ((x3, y3), (x4, y4))) = (a, b);
+    let d = (x1 - x2) * (y3 - y4) - (y1 - y2) * (x3 - x4);
+    if d.abs() < 1e-9 {
+        return None;
+    }
+    let (p, q) = (x1 * y2 - y1 * x2, x3 * y4 - y3 * x4);
+    Some((
+        (p * (x3 - x4) - (x1 - x2) * q) / d,
+        (p * (y3 - y4) - (y1 - y2) * q) / d,
+    ))
+}
@@ -0,0 +1,84 @@
+//! Codes made by `to qr --summary`, which tables show as a summary instead
+//! of the bytes of the image.
+
+use std::io::Cursor;
+
+use image::io::Reader;
+use nu_protocol::{CustomValue, ShellError, Span, Value};
+use serde::{Deserialize, Serialize};
+
+/// Characters of the payload shown in the summary.
+const PREVIEW_LENGTH: usize = 32;
+
+/// A png image of a code, shown as its symbology, size and payload. Nushell
+/// turns it into the binary of the image when it needs a base value, and the
+/// commands of this plugin read it as such.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QrImage {
+    symbology: String,
+    width: u32,
+    height: u32,
+    /// The start of the payload, or its size if it is not text.
+    preview: String,
+    png: Vec<u8>,
+}
+
+impl QrImage {
+    /// Wrap the `png` of a code of `symbology` encoding `data`.
+    pub fn new(symbology: &str, data: &[u8], png: Vec<u8>) -> Self {
+        let (width, height) = Reader::new(Cursor::new(&png))
+            .with_guessed_format()
+            .ok()
+            .and_then(|reader| reader.into_dimensions().ok())
+            .unwrap_or_default();
+        let preview = match std::str::from_utf8(data) {
+            Ok(text) if text.chars().count() > PREVIEW_LENGTH => {
+                format!("{}…", text.chars().take(PREVIEW_LENGTH).collect::<String>())
+            }
+            Ok(text) => text.to_string(),
+            Err(_) => format!("{} bytes", data.len()),
+        };
+        QrImage {
+            symbology: symbology.into(),
+            width,
+            height,
+            preview,
+            png,
+        }
+    }
+
+    pub fn into_value(self, span: Span) -> Value {
+        Value::CustomValue {
+            val: Box::new(self),
+            span,
+        }
+    }
+}
+
+#[typetag::serde]
+impl CustomValue for QrImage {
+    fn clone_value(&self, span: Span) -> Value {
+        self.clone().into_value(span)
+    }
+
+    fn value_string(&self) -> String {
+        format!(
+            "{} {}x{} png: {}",
+            self.symbology,
+            self.width,
+            self.height,
+            self.preview.escape_debug()
+        )
+    }
+
+    fn to_base_value(&self, span: Span) -> Result<Value, ShellError> {
+        Ok(Value::Binary {
+            val: self.png.clone(),
+            span,
+        })
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
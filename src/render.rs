@@ -0,0 +1,231 @@
+//! Rendering of the input of `to qr` and its payload commands as images.
+
+use nu_plugin::{EvaluatedCall, LabeledError};
+use nu_protocol::{ast::CellPath, Span, Value};
+
+use crate::defaults;
+use crate::encode::{self, Carrier, Options, Shape};
+use crate::matrix::Symbology;
+use crate::qr_image::QrImage;
+use crate::serialize::{serialize, Format};
+
+/// Render `data` as a png qr code, using the shape/size flags of `call`, or
+/// as a [QrImage] with `--summary`.
+pub fn render(
+    call: &EvaluatedCall,
+    data: &[u8],
+    data_span: Option<Span>,
+) -> Result<Value, LabeledError> {
+    render_as(call, Symbology::Qr, data, data_span)
+}
+
+/// Render `data` as a png of `symbology`, which only `to qr` reads from its
+/// flags: the payload commands have flags of their own, like `--country`.
+fn render_as(
+    call: &EvaluatedCall,
+    symbology: Symbology,
+    data: &[u8],
+    data_span: Option<Span>,
+) -> Result<Value, LabeledError> {
+    let image = png(call, symbology, data, data_span)?;
+    match (image, call.has_flag("summary")) {
+        (Value::Binary { val, span }, true) => {
+            Ok(QrImage::new(symbology.name(), data, val).into_value(span))
+        }
+        (image, _) => Ok(image),
+    }
+}
+
+/// Render `data` as a png of `symbology`, using the shape/size flags of `call`.
+fn png(
+    call: &EvaluatedCall,
+    symbology: Symbology,
+    data: &[u8],
+    data_span: Option<Span>,
+) -> Result<Value, LabeledError> {
+    let options = options(call, symbology)?;
+    match encode::render(data, &options) {
+        Ok(buf) => Ok(Value::Binary {
+            val: buf,
+            span: call.head,
+        }),
+        Err(msg) => Err(LabeledError {
+            label: match symbology {
+                Symbology::Qr => "failed to generate qr code".into(),
+                _ => "failed to generate code".into(),
+            },
+            msg,
+            span: data_span,
+        }),
+    }
+}
+
+/// Read how to draw a code of `symbology` from the flags of `call`, or their
+/// `NU_QR_*` defaults.
+fn options(call: &EvaluatedCall, symbology: Symbology) -> Result<Options, LabeledError> {
+    // Only qr codes have a shape, so a default for them does not fail the others.
+    let shape_name: Option<String> = match symbology {
+        Symbology::Qr => defaults::flag(call, "shape")?,
+        _ => None,
+    };
+    let shape =
+        match shape_name.map(|x| x.to_uppercase()).as_deref() {
+            Some("SQUARE") => Shape::Square,
+            Some("CIRCLE") => Shape::Circle,
+            Some("ROUNDEDSQUARE") => Shape::RoundedSquare,
+            Some("VERTICAL") => Shape::Vertical,
+            Some("HORIZONTAL") => Shape::Horizontal,
+            Some("DIAMOND") => Shape::Diamond,
+            None => Shape::Square,
+            _ => return Err(LabeledError {
+                label: "Unknown shape parameter".into(),
+                msg:
+                    "should be one of Square, Circle, RoundedSquare, Vertical, Horizontal, Diamond"
+                        .into(),
+                span: Some(call.head),
+            }),
+        };
+    /*
+    let (r,g,b,a) = match call.get_flag_value("background") {
+        Some(Value::List { vals, .. }) => {
+            match vals.len() {
+                3 => {
+                    let v : Vec<usize> = vals.into_iter().map(|x| x.as_int()).collect();
+                    (v[0], v[1], v[2], 0)
+                },
+                4 => {
+                    let v : Vec<usize> = vals.into_iter().map(|x| x.as_int()).collect();
+                    (v[0], v[1], v[2], v[3])
+                },
+                _ => {
+                    return Err(LabeledError { label: "incorrect background".into(), msg: "Sholud be a list of [r g b] or [r g b a]".into(), span: Some(call.head) })
+                }
+            }
+        },
+        Some(_) => {
+            return Err(LabeledError { label: "incorrect background".into(), msg: "Sholud be a list of [r g b] or [r g b a]".into(), span: Some(call.head) })
+        },
+        None => {
+            (255.255,255,0)
+        }
+    };
+    */
+    let size = |flag: &str| match defaults::flag::<usize>(call, flag)? {
+        Some(size) => u32::try_from(size).map(Some).map_err(|_| LabeledError {
+            label: "Invalid width/height: too large".into(),
+            msg: format!("width/height should be smaller than {}", u32::MAX),
+            span: Some(call.head),
+        }),
+        None => Ok(None),
+    };
+    let carrier = match call.get_flag::<String>("postal-code")? {
+        Some(postal_code) => Some(Carrier {
+            postal_code,
+            country: carrier_number(call, "country")?,
+            service_class: carrier_number(call, "service-class")?,
+        }),
+        None => None,
+    };
+    Ok(Options {
+        symbology: symbology.name().into(),
+        shape,
+        width: size("width")?,
+        height: size("height")?,
+        gs1: call.has_flag("gs1"),
+        carrier,
+    })
+}
+
+/// Read a number of the structured carrier message of maxicode.
+fn carrier_number(call: &EvaluatedCall, flag: &str) -> Result<u32, LabeledError> {
+    match call.get_flag::<i64>(flag)?.map(u32::try_from) {
+        Some(Ok(n)) if n <= 999 => Ok(n),
+        _ => Err(LabeledError {
+            label: "Invalid structured carrier message".into(),
+            msg: format!("--{} should be a number from 0 to 999", flag),
+            span: call
+                .get_flag_value(flag)
+                .and_then(|v| v.span().ok())
+                .or(Some(call.head)),
+        }),
+    }
+}
+
+/// Encode the input of `to qr`, either as a whole or one code per row.
+pub fn to_qr(
+    call: &EvaluatedCall,
+    input: &Value,
+    input_span: Option<Span>,
+) -> Result<Value, LabeledError> {
+    let (input, input_span) = match call.get_flag::<CellPath>("get")? {
+        Some(path) => {
+            let value = input
+                .clone()
+                .follow_cell_path(&path.members, false)
+                .map_err(|e| LabeledError {
+                    label: "Unable to get cell path".into(),
+                    msg: e.to_string(),
+                    span: Some(call.head),
+                })?;
+            let span = Some(value.span().unwrap_or(call.head));
+            (value, span)
+        }
+        None => (input.clone(), input_span),
+    };
+    let input = &input;
+    let symbology = Symbology::from_call(call)?;
+    let column: Option<String> = call.get_flag("payload-column")?;
+    if !call.has_flag("each") && column.is_none() {
+        return render_as(call, symbology, &payload(call, input)?, input_span);
+    }
+    // nu-plugin 0.83 only returns custom values on their own, not in lists.
+    if call.has_flag("summary") {
+        return Err(LabeledError {
+            label: "Conflicting flags".into(),
+            msg: "--summary returns a single image, use each { to qr --summary } instead of --each or --payload-column".into(),
+            span: Some(call.head),
+        });
+    }
+    let images = input
+        .as_list()?
+        .iter()
+        .map(|row| {
+            let row_span = Some(row.span().unwrap_or(call.head));
+            match &column {
+                Some(column) => match row.get_data_by_key(column) {
+                    Some(value) => render_as(call, symbology, &payload(call, &value)?, row_span),
+                    None => Err(LabeledError {
+                        label: "Missing payload column".into(),
+                        msg: format!("row does not have a column named {}", column),
+                        span: row_span,
+                    }),
+                },
+                None => render_as(call, symbology, &payload(call, row)?, row_span),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Value::List {
+        vals: images,
+        span: call.head,
+    })
+}
+
+/// Get the bytes to encode from the input of `to qr`, serializing structured values.
+pub fn payload(call: &EvaluatedCall, input: &Value) -> Result<Vec<u8>, LabeledError> {
+    let format = match call.get_flag::<String>("format")? {
+        Some(name) => Some(Format::from_name(&name, call.head)?),
+        None => None,
+    };
+    match (input, format) {
+        (Value::Binary { val, .. }, None) => Ok(val.clone()),
+        (Value::String { val, .. }, None) => Ok(val.as_bytes().to_vec()),
+        (Value::Binary { span, .. }, Some(_)) => Err(LabeledError {
+            label: "Unable to serialize input".into(),
+            msg: "binary input is encoded as is, remove --format".into(),
+            span: Some(*span),
+        }),
+        (_, format) => {
+            Ok(serialize(input, format.unwrap_or(Format::Json), call.head)?.into_bytes())
+        }
+    }
+}
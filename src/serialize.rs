@@ -0,0 +1,516 @@
+//! Serialization of structured pipeline input into text payloads, and
+//! parsing of payloads back into structured values.
+
+use nu_plugin::LabeledError;
+use nu_protocol::{Span, Value};
+
+use crate::payloads;
+
+/// Text formats structured input can be encoded as.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Json,
+    Nuon,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub fn from_name(name: &str, span: Span) -> Result<Self, LabeledError> {
+        match name.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "nuon" => Ok(Format::Nuon),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(LabeledError {
+                label: "Unknown format parameter".into(),
+                msg: "should be one of json, nuon, yaml, toml".into(),
+                span: Some(span),
+            }),
+        }
+    }
+}
+
+/// Serialize `value` in `format`.
+pub fn serialize(value: &Value, format: Format, head: Span) -> Result<String, LabeledError> {
+    let failed = |e: String| LabeledError {
+        label: "Unable to serialize input".into(),
+        msg: e,
+        span: Some(value.span().unwrap_or(head)),
+    };
+    match format {
+        Format::Nuon => to_nuon(value).map_err(failed),
+        Format::Json => serde_json::to_string(&to_json(value).map_err(failed)?)
+            .map_err(|e| failed(e.to_string())),
+        Format::Yaml => serde_yaml::to_string(&to_json(value).map_err(failed)?)
+            .map_err(|e| failed(e.to_string())),
+        Format::Toml => match to_json(value).map_err(failed)? {
+            v @ serde_json::Value::Object(_) => {
+                toml::to_string(&v).map_err(|e| failed(e.to_string()))
+            }
+            _ => Err(failed("toml can only encode a record".into())),
+        },
+    }
+}
+
+/// Convert to the json data model, the same way `to json` does.
+fn to_json(value: &Value) -> Result<serde_json::Value, String> {
+    use serde_json::Value as Json;
+    Ok(match value {
+        Value::Nothing { .. } => Json::Null,
+        Value::Bool { val, .. } => Json::Bool(*val),
+        Value::Int { val, .. } | Value::Filesize { val, .. } | Value::Duration { val, .. } => {
+            Json::from(*val)
+        }
+        Value::Float { val, .. } => serde_json::Number::from_f64(*val)
+            .map(Json::Number)
+            .ok_or_else(|| format!("{} can not be represented", val))?,
+        Value::String { val, .. } => Json::String(val.clone()),
+        Value::Date { val, .. } => Json::String(val.to_rfc3339()),
+        Value::Binary { val, .. } => Json::Array(val.iter().map(|b| Json::from(*b)).collect()),
+        Value::CellPath { val, .. } => Json::String(val.into_string()),
+        Value::List { vals, .. } => {
+            Json::Array(vals.iter().map(to_json).collect::<Result<_, _>>()?)
+        }
+        Value::Record { cols, vals, .. } => Json::Object(
+            cols.iter()
+                .cloned()
+                .zip(vals.iter().map(to_json))
+                .map(|(k, v)| v.map(|v| (k, v)))
+                .collect::<Result<_, _>>()?,
+        ),
+        v => return Err(format!("{} can not be serialized", v.get_type())),
+    })
+}
+
+/// Quote a string with nuon (json compatible) escapes.
+fn nuon_string(s: &str) -> String {
+    serde_json::Value::String(s.to_string()).to_string()
+}
+
+/// Record keys stay bare unless they contain characters the parser would split on.
+fn nuon_key(s: &str) -> String {
+    if !s.is_empty()
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    {
+        s.to_string()
+    } else {
+        nuon_string(s)
+    }
+}
+
+fn to_nuon(value: &Value) -> Result<String, String> {
+    Ok(match value {
+        Value::Nothing { .. } => "null".into(),
+        Value::Bool { val, .. } => val.to_string(),
+        Value::Int { val, .. } => val.to_string(),
+        Value::Float { val, .. } if val.is_nan() => "NaN".into(),
+        Value::Float { val, .. } if val.is_infinite() => {
+            if *val > 0.0 {
+                "inf".into()
+            } else {
+                "-inf".into()
+            }
+        }
+        Value::Float { val, .. } if val.fract() == 0.0 && val.abs() < 1e16 => format!("{:.1}", val),
+        Value::Float { val, .. } => val.to_string(),
+        Value::Filesize { val, .. } => format!("{}b", val),
+        Value::Duration { val, .. } => format!("{}ns", val),
+        Value::Date { val, .. } => val.to_rfc3339(),
+        Value::String { val, .. } => nuon_string(val),
+        Value::Binary { val, .. } => format!(
+            "0x[{}]",
+            val.iter().map(|b| format!("{:02X}", b)).collect::<String>()
+        ),
+        Value::CellPath { val, .. } => format!("$.{}", val.into_string()),
+        Value::List { vals, .. } => format!(
+            "[{}]",
+            vals.iter()
+                .map(to_nuon)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        ),
+        Value::Record { cols, vals, .. } => format!(
+            "{{{}}}",
+            cols.iter()
+                .zip(vals)
+                .map(|(k, v)| to_nuon(v).map(|v| format!("{}: {}", nuon_key(k), v)))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")
+        ),
+        v => return Err(format!("{} can not be serialized", v.get_type())),
+    })
+}
+
+/// Parse `text` into a structured value if it looks like json, nuon or a url,
+/// like `open` picks a parser for a file.
+pub fn sniff(text: &str, span: Span) -> Option<Value> {
+    let text = text.trim();
+    if text.starts_with(['{', '[']) {
+        return match serde_json::from_str(text) {
+            Ok(json) => Some(from_json(json, span)),
+            Err(_) => from_nuon(text, span).ok(),
+        };
+    }
+    url_record(text, span)
+}
+
+/// Convert from the json data model, the same way `from json` does.
+pub fn from_json(json: serde_json::Value, span: Span) -> Value {
+    use serde_json::Value as Json;
+    match json {
+        Json::Null => Value::Nothing { span },
+        Json::Bool(val) => Value::Bool { val, span },
+        Json::Number(n) => match n.as_i64() {
+            Some(val) => Value::Int { val, span },
+            None => Value::Float {
+                val: n.as_f64().unwrap_or(f64::NAN),
+                span,
+            },
+        },
+        Json::String(val) => Value::String { val, span },
+        Json::Array(vals) => Value::List {
+            vals: vals.into_iter().map(|v| from_json(v, span)).collect(),
+            span,
+        },
+        Json::Object(map) => {
+            let (cols, vals) = map
+                .into_iter()
+                .map(|(k, v)| (k, from_json(v, span)))
+                .unzip();
+            Value::Record { cols, vals, span }
+        }
+    }
+}
+
+/// The components of a url with a host, in the columns of `url parse`.
+fn url_record(text: &str, span: Span) -> Option<Value> {
+    if !text.contains("://") || text.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = url::Url::parse(text).ok()?;
+    if !url.has_host() {
+        return None;
+    }
+    let string = |val: &str| Value::String {
+        val: val.into(),
+        span,
+    };
+    Some(Value::Record {
+        cols: [
+            "scheme", "username", "password", "host", "port", "path", "query", "fragment", "params",
+        ]
+        .map(String::from)
+        .to_vec(),
+        vals: vec![
+            string(url.scheme()),
+            string(url.username()),
+            string(url.password().unwrap_or_default()),
+            string(url.host_str().unwrap_or_default()),
+            string(&url.port().map(|p| p.to_string()).unwrap_or_default()),
+            string(url.path()),
+            string(url.query().unwrap_or_default()),
+            string(url.fragment().unwrap_or_default()),
+            payloads::url::query_record(&url, span),
+        ],
+        span,
+    })
+}
+
+/// Parse nuon written by `to nuon`, or by hand as far as plain data goes.
+fn from_nuon(text: &str, span: Span) -> Result<Value, String> {
+    let mut parser = Nuon { text, pos: 0, span };
+    let value = parser.value()?;
+    parser.skip_space();
+    match parser.peek() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{}' after the value", c)),
+    }
+}
+
+struct Nuon<'a> {
+    text: &'a str,
+    pos: usize,
+    span: Span,
+}
+
+impl<'a> Nuon<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skip whitespace, comments and the commas that may separate items.
+    fn skip_space(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+            self.pos += rest.len() - trimmed.len();
+            if !trimmed.starts_with('#') {
+                break;
+            }
+            self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_space();
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.pos += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+            None => Err(format!("expected '{}', found the end", expected)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_space();
+        match self.peek() {
+            None => Err("unexpected end".into()),
+            Some('{') => self.record(),
+            Some('[') => self.list(),
+            Some(quote @ ('"' | '\'' | '`')) => Ok(Value::String {
+                val: self.string(quote)?,
+                span: self.span,
+            }),
+            Some(_) if self.rest().starts_with("0x[") => self.binary(),
+            Some(_) => {
+                let token = self.bare(&[',', '[', ']', '{', '}']);
+                bare_value(token, self.span)
+            }
+        }
+    }
+
+    /// A word up to whitespace or one of `stop`.
+    fn bare(&mut self, stop: &[char]) -> &'a str {
+        let rest = self.rest();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || stop.contains(&c))
+            .unwrap_or(rest.len());
+        self.pos += end;
+        &rest[..end]
+    }
+
+    /// A string in `quote`s, with json escapes between double quotes.
+    fn string(&mut self, quote: char) -> Result<String, String> {
+        let rest = self.rest();
+        let mut escaped = false;
+        let end = rest[1..]
+            .char_indices()
+            .find(|&(_, c)| {
+                let end = !escaped && c == quote;
+                escaped = quote == '"' && !escaped && c == '\\';
+                end
+            })
+            .map(|(i, _)| i + 2)
+            .ok_or("unterminated string")?;
+        self.pos += end;
+        match quote {
+            '"' => serde_json::from_str(&rest[..end]).map_err(|e| e.to_string()),
+            _ => Ok(rest[1..end - 1].into()),
+        }
+    }
+
+    fn record(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let (mut cols, mut vals) = (vec![], vec![]);
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                None => return Err("unterminated record".into()),
+                Some(quote @ ('"' | '\'' | '`')) => cols.push(self.string(quote)?),
+                Some(_) => cols.push(self.bare(&[':', ',', '}']).into()),
+            }
+            self.expect(':')?;
+            vals.push(self.value()?);
+        }
+        Ok(Value::Record {
+            cols,
+            vals,
+            span: self.span,
+        })
+    }
+
+    /// A list, or a table written as `[[column ...]; [value ...] ...]`.
+    fn list(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut vals = vec![];
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                None => return Err("unterminated list".into()),
+                Some(';') if vals.len() == 1 => return self.table(vals.remove(0)),
+                Some(_) => vals.push(self.value()?),
+            }
+        }
+        Ok(Value::List {
+            vals,
+            span: self.span,
+        })
+    }
+
+    fn table(&mut self, header: Value) -> Result<Value, String> {
+        self.pos += 1;
+        let Value::List { vals: names, .. } = header else {
+            return Err("expected a list of columns before ';'".into());
+        };
+        let cols = names
+            .into_iter()
+            .map(|name| match name {
+                Value::String { val, .. } => Ok(val),
+                _ => Err("columns of a table should be strings".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rows = vec![];
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(']') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('[') => match self.list()? {
+                    Value::List { vals, .. } if vals.len() == cols.len() => {
+                        rows.push(Value::Record {
+                            cols: cols.clone(),
+                            vals,
+                            span: self.span,
+                        })
+                    }
+                    _ => return Err("row does not match the columns of the table".into()),
+                },
+                Some(c) => return Err(format!("expected a row of the table, found '{}'", c)),
+                None => return Err("unterminated table".into()),
+            }
+        }
+        Ok(Value::List {
+            vals: rows,
+            span: self.span,
+        })
+    }
+
+    /// Binary written as `0x[0A FF]`.
+    fn binary(&mut self) -> Result<Value, String> {
+        self.pos += 3;
+        let rest = self.rest();
+        let end = rest.find(']').ok_or("unterminated binary")?;
+        let digits: Vec<u8> = rest[..end]
+            .bytes()
+            .filter(|b| !b.is_ascii_whitespace() && *b != b',')
+            .collect();
+        self.pos += end + 1;
+        if !digits.len().is_multiple_of(2) {
+            return Err("binary should have an even number of hex digits".into());
+        }
+        let val = digits
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| "binary should consist of hex digits".to_string())
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Value::Binary {
+            val,
+            span: self.span,
+        })
+    }
+}
+
+/// Nanoseconds of the duration units of nu.
+const DURATION_UNITS: [(&str, f64); 9] = [
+    ("ns", 1.0),
+    ("us", 1e3),
+    ("µs", 1e3),
+    ("ms", 1e6),
+    ("sec", 1e9),
+    ("min", 60e9),
+    ("hr", 3600e9),
+    ("day", 86400e9),
+    ("wk", 604800e9),
+];
+
+/// Bytes of the filesize units of nu.
+const FILESIZE_UNITS: [(&str, f64); 13] = [
+    ("b", 1.0),
+    ("kb", 1e3),
+    ("mb", 1e6),
+    ("gb", 1e9),
+    ("tb", 1e12),
+    ("pb", 1e15),
+    ("eb", 1e18),
+    ("kib", 1024.0),
+    ("mib", 1048576.0),
+    ("gib", 1073741824.0),
+    ("tib", 1099511627776.0),
+    ("pib", 1125899906842624.0),
+    ("eib", 1152921504606846976.0),
+];
+
+/// The value of a bare word: a number, duration, filesize, date or else a string.
+fn bare_value(token: &str, span: Span) -> Result<Value, String> {
+    let numeric = token.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c));
+    Ok(match token {
+        "" => return Err("expected a value".into()),
+        "null" => Value::Nothing { span },
+        "true" => Value::Bool { val: true, span },
+        "false" => Value::Bool { val: false, span },
+        "NaN" => Value::Float {
+            val: f64::NAN,
+            span,
+        },
+        "inf" => Value::Float {
+            val: f64::INFINITY,
+            span,
+        },
+        "-inf" => Value::Float {
+            val: f64::NEG_INFINITY,
+            span,
+        },
+        _ if numeric && token.parse::<i64>().is_ok() => Value::Int {
+            val: token.parse().unwrap_or_default(),
+            span,
+        },
+        _ if numeric && token.parse::<f64>().is_ok() => Value::Float {
+            val: token.parse().unwrap_or_default(),
+            span,
+        },
+        _ => {
+            let with_unit = |units: &[(&str, f64)]| {
+                units.iter().find_map(|(unit, factor)| {
+                    let number = token.strip_suffix(unit)?;
+                    let number: f64 = number.parse().ok().filter(|_| numeric)?;
+                    Some((number * factor).round() as i64)
+                })
+            };
+            if let Some(val) = with_unit(&DURATION_UNITS) {
+                Value::Duration { val, span }
+            } else if let Some(val) = with_unit(&FILESIZE_UNITS) {
+                Value::Filesize { val, span }
+            } else if let Ok(val) = chrono::DateTime::parse_from_rfc3339(token) {
+                Value::Date { val, span }
+            } else {
+                Value::String {
+                    val: token.into(),
+                    span,
+                }
+            }
+        }
+    })
+}
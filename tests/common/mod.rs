@@ -0,0 +1,44 @@
+//! Codes shared by the round trip and golden image tests.
+
+use nu_plugin_qr::encode::Shape;
+
+/// Every shape of the modules of qr codes, with the name of its fixtures.
+pub const SHAPES: [(&str, Shape); 6] = [
+    ("square", Shape::Square),
+    ("circle", Shape::Circle),
+    ("rounded-square", Shape::RoundedSquare),
+    ("vertical", Shape::Vertical),
+    ("horizontal", Shape::Horizontal),
+    ("diamond", Shape::Diamond),
+];
+
+/// Two dimensional symbologies other than qr and text they encode.
+pub const MATRIX_CODES: [(&str, &str); 4] = [
+    ("datamatrix", "Grüße 123"),
+    ("aztec", "発票 04400123"),
+    ("maxicode", "Hello 123"),
+    ("hanxin", "发票号码 04400123"),
+];
+
+/// Every barcode symbology, text it encodes and the payload `from barcode`
+/// reads, with its check digit, unless it can not read the symbology.
+pub const BARCODES: [(&str, &str, Option<&str>); 14] = [
+    ("code128", "Nushell-42", Some("Nushell-42")),
+    ("code39", "NU42", Some("NU42")),
+    ("codabar", "A40156B", Some("40156")),
+    ("ean13", "590123412345", Some("5901234123457")),
+    ("ean8", "9638507", Some("96385074")),
+    ("upca", "03600029145", Some("036000291452")),
+    ("upce", "0123456", Some("01234565")),
+    ("databar", "0950110153000", Some("]e00109501101530003")),
+    (
+        "databarexpanded",
+        "(01)09501101530003(17)270101",
+        Some("]e0010950110153000317270101"),
+    ),
+    ("postnet", "55555-1237", None),
+    ("intelligentmail", "01234567094987654321 01234567891", None),
+    ("royalmail", "LU17 8XE 2B", None),
+    ("pharmacode", "1234", None),
+    ("pharmacodetwotrack", "1234", None),
+];
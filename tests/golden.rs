@@ -0,0 +1,71 @@
+//! Codes compare equal to the images in `tests/fixtures`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the fixtures of changed (or new) codes
+//! instead, and look at them before committing.
+
+mod common;
+
+use std::path::PathBuf;
+
+use common::{BARCODES, MATRIX_CODES, SHAPES};
+use nu_plugin_qr::encode;
+
+/// Compare the `actual` contents of the fixture `name` to the file, or
+/// write them with `UPDATE_GOLDEN`. Images are compared by their pixels,
+/// which stay the same when another png encoder compresses them otherwise.
+///
+/// Returns a message if they differ.
+fn compare(name: &str, actual: &[u8]) -> Option<String> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, actual).unwrap();
+        return None;
+    }
+    let Ok(expected) = std::fs::read(&path) else {
+        return Some(format!("{} is missing, run with UPDATE_GOLDEN=1", name));
+    };
+    let same = match name.ends_with(".png") {
+        true => {
+            let pixels = |png: &[u8]| image::load_from_memory(png).map(|i| i.to_luma8());
+            matches!((pixels(&expected), pixels(actual)), (Ok(e), Ok(a)) if e == a)
+        }
+        false => expected == actual,
+    };
+    (!same).then(|| format!("{} differs", name))
+}
+
+/// Fail with every message of `differences`.
+fn assert_same(differences: impl Iterator<Item = Option<String>>) {
+    let differences: Vec<_> = differences.flatten().collect();
+    assert!(differences.is_empty(), "{}", differences.join("\n"));
+}
+
+#[test]
+fn qr_shapes() {
+    assert_same(SHAPES.iter().map(|(name, shape)| {
+        let png = encode::qr(b"https://www.nushell.sh", *shape, 200).unwrap();
+        compare(&format!("qr-{}.png", name), &png)
+    }));
+}
+
+#[test]
+fn matrix_codes() {
+    assert_same(MATRIX_CODES.iter().map(|(symbology, text)| {
+        let png = encode::matrix(symbology, text.as_bytes(), 200).unwrap();
+        compare(&format!("{}.png", symbology), &png)
+    }));
+}
+
+#[test]
+fn barcodes() {
+    assert_same(BARCODES.iter().flat_map(|(symbology, text, _)| {
+        let png = encode::barcode(symbology, text, 2, 50).unwrap();
+        let svg = encode::barcode_svg(symbology, text, 2, 50).unwrap();
+        [
+            compare(&format!("{}.png", symbology), &png),
+            compare(&format!("{}.svg", symbology), svg.as_bytes()),
+        ]
+    }));
+}
@@ -0,0 +1,359 @@
+//! Han Xin codes read back by a decoder written apart from the encoder,
+//! after ISO/IEC 20830, as rxing can not read them. The placement of the
+//! alignment patterns is checked against the total codewords of each
+//! version in the capacity table of the standard.
+
+use encoding_rs::GB18030;
+use nu_plugin_qr::encode;
+
+/// Total codewords of versions 1 to 10, of the capacity table.
+const TOTAL: [usize; 10] = [25, 37, 50, 54, 69, 84, 100, 117, 136, 155];
+
+/// Error correction blocks of level L2 by version, as their count, data
+/// codewords and error correction codewords.
+const L2: [&[(usize, usize, usize)]; 10] = [
+    &[(1, 17, 8)],
+    &[(1, 25, 12)],
+    &[(1, 34, 16)],
+    &[(1, 38, 16)],
+    &[(1, 49, 20)],
+    &[(1, 58, 26)],
+    &[(1, 70, 30)],
+    &[(1, 40, 18), (1, 41, 18)],
+    &[(2, 48, 20)],
+    &[(1, 52, 22), (1, 57, 24)],
+];
+
+/// Width of the first block between alignment lines, and of the others
+/// plus one, from version 4 on.
+const FIRST_BLOCK: [i64; 7] = [14, 16, 16, 17, 18, 19, 20];
+const OTHER_BLOCKS: [i64; 7] = [15, 15, 17, 18, 19, 20, 21];
+
+/// Whether the Reed-Solomon syndromes of `codewords` in GF(`size`) with
+/// primitive polynomial `poly` vanish for `ec` error correction codewords.
+fn check(codewords: &[u8], poly: usize, size: usize, ec: usize) -> bool {
+    let mut exp = vec![0; 2 * size];
+    let mut log = vec![0; size];
+    let mut x = 1;
+    for (i, e) in exp.iter_mut().enumerate().take(size - 1) {
+        *e = x;
+        log[x] = i;
+        x <<= 1;
+        if x >= size {
+            x ^= poly;
+        }
+    }
+    for i in size - 1..2 * size {
+        exp[i] = exp[i - (size - 1)];
+    }
+    let mul = |a: usize, b: usize| match a == 0 || b == 0 {
+        true => 0,
+        false => exp[log[a] + log[b]],
+    };
+    (1..=ec).all(|j| {
+        codewords
+            .iter()
+            .fold(0, |s, &c| mul(s, exp[j]) ^ c as usize)
+            == 0
+    })
+}
+
+/// Modules of a version `version` symbol taken by the finder patterns,
+/// function information and alignment patterns.
+fn function_modules(version: usize) -> Vec<Vec<bool>> {
+    let size = 21 + 2 * version as i64;
+    let corner = |c: i64| c < 9 || c >= size - 9;
+    let mut grid: Vec<Vec<bool>> = (0..size)
+        .map(|y| (0..size).map(|x| corner(x) && corner(y)).collect())
+        .collect();
+    if version < 4 {
+        return grid;
+    }
+    let mut set = |x: i64, y: i64| {
+        if (0..size).contains(&x) && (0..size).contains(&y) {
+            grid[y as usize][x as usize] = true;
+        }
+    };
+    let band = |i: i64| match i {
+        0 => FIRST_BLOCK[version - 4],
+        _ => OTHER_BLOCKS[version - 4] - 1,
+    };
+    // Alignment lines run left and down from every other block corner,
+    // staggered between rows, with their light edge.
+    let (mut y, mut row, mut starts) = (0, 0, true);
+    while y < size {
+        let h = band(row);
+        let (mut x, mut column, mut on) = (size - 1, 0, starts);
+        starts = !starts;
+        while x >= 0 {
+            let w = band(column);
+            if on && !(y == 0 && x == size - 1) {
+                for i in 0..=w {
+                    set(x - i, y);
+                    set(x - i - 1, y + 1);
+                }
+                for i in 1..h {
+                    set(x, y + i);
+                    set(x - 1, y + i + 1);
+                }
+            }
+            on = !on;
+            column += 1;
+            x -= w;
+        }
+        row += 1;
+        y += h;
+    }
+    // Assistant patterns where the lines meet the edges.
+    let mut assist = |x: i64, y: i64| {
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                set(x + dx, y + dy);
+            }
+        }
+    };
+    let (mut y, mut row) = (0, 0);
+    while y < size {
+        assist(if row % 2 == 0 { 0 } else { size - 1 }, y);
+        y += band(row);
+        row += 1;
+    }
+    let (mut x, mut column) = (size - 1, 0);
+    while x >= 0 {
+        assist(x, if column % 2 == 0 { size - 1 } else { 0 });
+        x -= band(column);
+        column += 1;
+    }
+    grid
+}
+
+/// Dark modules of a png symbol with a quiet zone of three modules.
+fn modules(png: &[u8]) -> Vec<Vec<bool>> {
+    let image = image::load_from_memory(png).unwrap().to_luma8();
+    let dark = |x: u32, y: u32| image.get_pixel(x, y).0[0] < 128;
+    let first = (0..image.height())
+        .filter_map(|y| (0..image.width()).find(|&x| dark(x, y)))
+        .min()
+        .unwrap();
+    let scale = first / 3;
+    let size = image.width() / scale - 6;
+    let at = |c: u32| (3 + c) * scale + scale / 2;
+    (0..size)
+        .map(|y| (0..size).map(|x| dark(at(x), at(y))).collect())
+        .collect()
+}
+
+/// Whether the module at 1-based row `i` and column `j` is inverted by `mask`.
+fn masked(mask: usize, i: usize, j: usize) -> bool {
+    match mask {
+        0 => false,
+        1 => (i + j).is_multiple_of(2),
+        2 => ((i + j) % 3 + j % 3).is_multiple_of(2),
+        _ => (i % j + j % i + i % 3 + j % 3).is_multiple_of(2),
+    }
+}
+
+/// Reads `len` bits at a time, most significant first.
+struct Bits {
+    bits: Vec<bool>,
+    at: usize,
+}
+
+impl Bits {
+    fn take(&mut self, len: usize) -> usize {
+        let value = self.bits[self.at..self.at + len]
+            .iter()
+            .fold(0, |v, &b| v << 1 | b as usize);
+        self.at += len;
+        value
+    }
+}
+
+/// Version and text of the symbol in `png`.
+fn decode(png: &[u8]) -> (usize, String) {
+    let m = modules(png);
+    let last = m.len() - 1;
+    let tl: Vec<bool> = (0..8)
+        .map(|i| m[8][i])
+        .chain((0..9).map(|i| m[8 - i][8]))
+        .collect();
+    let tr: Vec<bool> = (0..8)
+        .map(|i| m[i][last - 8])
+        .chain((0..9).map(|i| m[8][last - 8 + i]))
+        .collect();
+    let bl: Vec<bool> = (0..8)
+        .map(|i| m[last - i][8])
+        .chain((0..9).map(|i| m[last - 8][8 - i]))
+        .collect();
+    let br: Vec<bool> = (0..8)
+        .map(|i| m[last - 8][last - i])
+        .chain((0..9).map(|i| m[last - 8 + i][last - 8]))
+        .collect();
+    assert!(
+        tl == br && tr == bl,
+        "copies of the function information differ"
+    );
+    let info: Vec<bool> = tl.into_iter().chain(tr).collect();
+    assert!((28..34).all(|i| info[i] == (i % 2 == 1)));
+    let nibbles: Vec<u8> = info[..28]
+        .chunks(4)
+        .map(|c| c.iter().fold(0, |v, &b| v << 1 | b as u8))
+        .collect();
+    assert!(check(&nibbles, 0x13, 16, 4), "function information");
+    let info = (nibbles[0] as usize) << 8 | (nibbles[1] as usize) << 4 | nibbles[2] as usize;
+    let (version, level, mask) = ((info >> 4) - 20, (info >> 2 & 3) + 1, info & 3);
+    assert_eq!((m.len(), level), (21 + 2 * version, 2));
+
+    let grid = function_modules(version);
+    let bits: Vec<bool> = (0..m.len())
+        .flat_map(|y| (0..m.len()).map(move |x| (x, y)))
+        .filter(|&(x, y)| !grid[y][x])
+        .map(|(x, y)| m[y][x] ^ masked(mask, y + 1, x + 1))
+        .collect();
+    let codewords: Vec<u8> = bits
+        .chunks_exact(8)
+        .map(|c| c.iter().fold(0, |v, &b| v << 1 | b as u8))
+        .collect();
+    assert_eq!(codewords.len(), TOTAL[version - 1]);
+    // Codewords are interleaved with a step of 13.
+    let mut stream = vec![0; codewords.len()];
+    let mut next = codewords.iter();
+    for start in 0..13 {
+        for at in (start..codewords.len()).step_by(13) {
+            stream[at] = *next.next().unwrap();
+        }
+    }
+    let mut data = vec![];
+    let mut blocks = stream.as_slice();
+    for &(count, d, ec) in L2[version - 1] {
+        for _ in 0..count {
+            let (block, rest) = blocks.split_at(d + ec);
+            assert!(check(block, 0x163, 256, ec), "data error correction");
+            data.extend_from_slice(&block[..d]);
+            blocks = rest;
+        }
+    }
+    assert!(blocks.is_empty());
+
+    let mut bits = Bits {
+        bits: data
+            .iter()
+            .flat_map(|cw| (0..8).rev().map(move |i| cw >> i & 1 == 1))
+            .collect(),
+        at: 0,
+    };
+    let text = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+    let symbols: Vec<u8> = (0..28)
+        .chain(32..48)
+        .chain(58..65)
+        .chain(91..97)
+        .chain(123..128)
+        .collect();
+    let mut out: Vec<u8> = vec![];
+    while bits.at + 4 <= bits.bits.len() {
+        match bits.take(4) {
+            0 => break,
+            1 => {
+                let mut group = String::new();
+                loop {
+                    let v = bits.take(10);
+                    if v >= 1021 {
+                        out.truncate(out.len() - 3);
+                        out.extend_from_slice(&group.as_bytes()[3 - (v - 1020)..]);
+                        break;
+                    }
+                    group = format!("{:03}", v);
+                    out.extend_from_slice(group.as_bytes());
+                }
+            }
+            2 => {
+                let mut table: &[u8] = text;
+                loop {
+                    match bits.take(6) {
+                        63 => break,
+                        62 if table == text => table = &symbols,
+                        62 => table = text,
+                        v => out.push(table[v]),
+                    }
+                }
+            }
+            3 => {
+                let count = bits.take(13);
+                out.extend((0..count).map(|_| bits.take(8) as u8));
+            }
+            mode @ (4 | 5) => loop {
+                let v = bits.take(12);
+                let (first, second) = match (mode, v) {
+                    (_, 0xfff) => break,
+                    (5, v) => (0xd8 + v / 94, 0xa1 + v % 94),
+                    (_, v) if v >= 0xfca => (0xa8, 0xa1 + v - 0xfca),
+                    (_, v) if v >= 0xeb0 => (0xa1 + (v - 0xeb0) / 94, 0xa1 + (v - 0xeb0) % 94),
+                    (_, v) => (0xb0 + v / 94, 0xa1 + v % 94),
+                };
+                out.extend([first as u8, second as u8]);
+            },
+            6 => loop {
+                let v = bits.take(15);
+                if v == 0x7fff {
+                    break;
+                }
+                let second = v % 0xbe;
+                let second = if second <= 0x3e {
+                    second + 0x40
+                } else {
+                    second + 0x41
+                };
+                out.extend([(0x81 + v / 0xbe) as u8, second as u8]);
+            },
+            7 => {
+                let v = bits.take(21);
+                out.extend([
+                    (0x81 + v / 0x3138) as u8,
+                    (0x30 + v % 0x3138 / 0x4ec) as u8,
+                    (0x81 + v % 0x4ec / 0xa) as u8,
+                    (0x30 + v % 0xa) as u8,
+                ]);
+            }
+            mode => panic!("unknown mode {}", mode),
+        }
+    }
+    let (text, _, errors) = GB18030.decode(&out);
+    assert!(!errors, "not GB 18030: {:?}", out);
+    (version, text.into_owned())
+}
+
+#[test]
+fn capacity() {
+    for version in 1..=10 {
+        let grid = function_modules(version);
+        let free = grid.iter().flatten().filter(|function| !**function).count();
+        assert_eq!(free / 8, TOTAL[version - 1], "version {}", version);
+        let blocks: usize = L2[version - 1].iter().map(|(n, d, ec)| n * (d + ec)).sum();
+        assert_eq!(blocks, TOTAL[version - 1], "version {}", version);
+    }
+}
+
+#[test]
+fn round_trip() {
+    let texts = [
+        "hello",
+        "发票号码 04400123",
+        "12345678901234567890",
+        "ABC123def-+ /xyz",
+        "\u{1d}GS\u{1e}RS",
+        "€ 𠀀 ©",
+        "丂丄 ㄅ",
+        "中华人民共和国 Han Xin 汉信码 2026-10-15 12345678901234",
+        &"a".repeat(40),
+        &"a".repeat(100),
+    ];
+    let mut versions = vec![];
+    for text in texts {
+        let png = encode::matrix("hanxin", text.as_bytes(), 400).unwrap();
+        let (version, decoded) = decode(&png);
+        assert_eq!(decoded, text);
+        versions.push(version);
+    }
+    // Some of them need alignment patterns.
+    assert!(versions.iter().any(|&v| v >= 4), "{:?}", versions);
+}
@@ -0,0 +1,149 @@
+//! Commands run through the plugin as nushell calls them.
+
+use std::io::Cursor;
+
+use image::{imageops, GrayImage, ImageOutputFormat, Luma};
+use nu_plugin::{EvaluatedCall, LabeledError, Plugin};
+use nu_plugin_qr::decode::{self, Codes};
+use nu_plugin_qr::encode::{self, Shape};
+use nu_plugin_qr::plugin::Qr;
+use nu_protocol::{Span, Spanned, Value};
+
+/// Run `name` with the named flags, `None` for switches.
+fn run(name: &str, flags: &[(&str, Option<Value>)], input: Value) -> Result<Value, LabeledError> {
+    let span = Span::test_data();
+    let call = EvaluatedCall {
+        head: span,
+        positional: vec![],
+        named: flags
+            .iter()
+            .map(|(flag, value)| {
+                let flag = Spanned {
+                    item: flag.to_string(),
+                    span,
+                };
+                (flag, value.clone())
+            })
+            .collect(),
+    };
+    Qr::new().run(name, &call, &input)
+}
+
+fn string(s: &str) -> Option<Value> {
+    Some(Value::string(s, Span::test_data()))
+}
+
+#[test]
+fn tel_country_is_not_a_maxicode_flag() {
+    let flags = [("number", string("030 1234567")), ("country", string("DE"))];
+    let image = run("to qr tel", &flags, Value::nothing(Span::test_data())).unwrap();
+    let found = decode::scan(image.as_binary().unwrap(), Codes::Qr).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].payload, b"tel:+49301234567");
+}
+
+#[test]
+fn to_qr_country_needs_maxicode() {
+    let flags = [("country", Some(Value::int(840, Span::test_data())))];
+    let err = run(
+        "to qr",
+        &flags,
+        Value::string("1Z12345678", Span::test_data()),
+    )
+    .unwrap_err();
+    assert!(err.msg.contains("--symbology maxicode"), "{}", err.msg);
+}
+
+#[test]
+fn maxicode_carrier_numbers_have_3_digits() {
+    let span = Span::test_data();
+    let carrier = |country: i64| {
+        let flags = [
+            ("symbology", string("maxicode")),
+            ("postal-code", string("152382802")),
+            ("country", Some(Value::int(country, span))),
+            ("service-class", Some(Value::int(1, span))),
+        ];
+        run("to qr", &flags, Value::string("1Z12345678", span))
+    };
+    assert!(carrier(840).is_ok());
+    let err = carrier(1000).unwrap_err();
+    assert_eq!(err.label, "Invalid structured carrier message");
+    assert!(err.msg.contains("--country"), "{}", err.msg);
+}
+
+#[test]
+fn from_qr_checksum_of_one_image() {
+    let png = encode::qr(b"hello", Shape::Square, 200).unwrap();
+    let input = Value::binary(png, Span::test_data());
+    let table = run("from qr", &[("checksum", string("crc32"))], input.clone()).unwrap();
+    let row = &table.as_list().unwrap()[0];
+    let column = |name| row.get_data_by_key(name).unwrap().as_string().unwrap();
+    assert_eq!(column("payload"), "hello");
+    assert_eq!(column("crc32"), "3610a686");
+    let flags = [("checksum", string("crc32")), ("join", None)];
+    assert!(run("from qr", &flags, input).is_err());
+}
+
+#[test]
+fn from_qr_timeout_keeps_found_codes() {
+    // The data matrix is found in the first pass, before the qr backends.
+    let mut page = GrayImage::from_pixel(440, 220, Luma([255]));
+    let codes = [
+        encode::matrix("datamatrix", b"DM-1", 160).unwrap(),
+        encode::qr(b"QR-1", Shape::Square, 160).unwrap(),
+    ];
+    for (png, x) in codes.iter().zip([20, 260]) {
+        let code = image::load_from_memory(png).unwrap().to_luma8();
+        imageops::overlay(&mut page, &code, x, 30);
+    }
+    let mut png = vec![];
+    page.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let input = Value::binary(png, Span::test_data());
+    // A single code is returned as its payload rather than a list.
+    let payloads = |found: Value| -> Vec<String> {
+        let mut payloads: Vec<_> = match found.as_list() {
+            Ok(list) => list.iter().map(|p| p.as_string().unwrap()).collect(),
+            Err(_) => vec![found.as_string().unwrap()],
+        };
+        payloads.sort();
+        payloads
+    };
+    assert_eq!(
+        payloads(run("from qr", &[], input.clone()).unwrap()),
+        ["DM-1", "QR-1"]
+    );
+
+    let timeout = (
+        "timeout",
+        Some(Value::Duration {
+            val: 1,
+            span: Span::test_data(),
+        }),
+    );
+    let err = run("from qr", std::slice::from_ref(&timeout), input.clone()).unwrap_err();
+    assert_eq!(err.label, "Timed out");
+    let table = run("from qr", &[timeout, ("ignore-error", None)], input).unwrap();
+    assert_eq!(payloads(table), ["DM-1"]);
+}
+
+#[test]
+fn crypto_amount_in_satoshis() {
+    let address = string("1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2");
+    let amount = |n: f64| {
+        let flags = [
+            ("address", address.clone()),
+            ("amount", Some(Value::float(n, Span::test_data()))),
+        ];
+        run("to qr crypto", &flags, Value::nothing(Span::test_data()))
+    };
+    let image = amount(0.001).unwrap();
+    let found = decode::scan(image.as_binary().unwrap(), Codes::Qr).unwrap();
+    assert_eq!(
+        found[0].payload,
+        b"bitcoin:1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2?amount=0.001"
+    );
+    assert!(amount(0.000000001).is_err());
+    assert!(amount(0.123456789).is_err());
+}
@@ -0,0 +1,277 @@
+//! Codes encoded at every size are decoded back to their payload.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::{BARCODES, MATRIX_CODES, SHAPES};
+use image::{imageops, GrayImage, ImageOutputFormat, Luma};
+use nu_plugin_qr::decode::{self, Codes};
+use nu_plugin_qr::encode::{self, Shape};
+use nu_plugin_qr::payloads::wifi::{self, Wifi};
+
+/// Payloads of the codes `decode::scan` finds in `png`.
+fn payloads(png: &[u8], codes: Codes) -> Vec<(&'static str, Vec<u8>)> {
+    decode::scan(png, codes)
+        .unwrap()
+        .into_iter()
+        .map(|found| (found.symbology, found.payload))
+        .collect()
+}
+
+#[test]
+fn qr_shapes() {
+    // The other shapes break up the finder patterns, which are then only
+    // found at some sizes; they are compared to golden images instead.
+    let readable = [Shape::Square, Shape::Circle];
+    for (name, shape) in SHAPES.iter().filter(|(_, s)| readable.contains(s)) {
+        for width in [120, 300, 600] {
+            let png = encode::qr(b"https://www.nushell.sh", *shape, width).unwrap();
+            assert_eq!(
+                payloads(&png, Codes::Qr),
+                [("qr", b"https://www.nushell.sh".to_vec())],
+                "{} qr code {} pixels wide",
+                name,
+                width
+            );
+        }
+    }
+}
+
+#[test]
+fn qr_binary() {
+    let data = [0, 159, 146, 150, 255];
+    let png = encode::matrix("qr", &data, 200).unwrap();
+    assert_eq!(payloads(&png, Codes::Qr), [("qr", data.to_vec())]);
+}
+
+#[test]
+fn matrix_codes() {
+    // Neither maxicode nor han xin are read by `from qr`.
+    for (symbology, text) in MATRIX_CODES
+        .iter()
+        .filter(|(s, _)| ["datamatrix", "aztec"].contains(s))
+    {
+        for width in [100, 300, 600] {
+            let png = encode::matrix(symbology, text.as_bytes(), width).unwrap();
+            assert_eq!(
+                payloads(&png, Codes::Qr),
+                [(*symbology, text.as_bytes().to_vec())],
+                "{} {} pixels wide",
+                symbology,
+                width
+            );
+        }
+    }
+}
+
+#[test]
+fn rendered_payload() {
+    let wifi = wifi::build(&Wifi {
+        ssid: "home;net".into(),
+        password: Some("secret".into()),
+        ..Default::default()
+    })
+    .unwrap();
+    let options = encode::Options {
+        symbology: "datamatrix".into(),
+        height: Some(240),
+        ..Default::default()
+    };
+    let png = encode::render(wifi.as_bytes(), &options).unwrap();
+    let image = image::load_from_memory(&png).unwrap();
+    assert!(image.height() <= 240, "{} pixels high", image.height());
+    assert_eq!(
+        payloads(&png, Codes::Qr),
+        [("datamatrix", wifi.into_bytes())]
+    );
+}
+
+#[test]
+fn qr_beside_other_codes() {
+    // quircs and rqrr find the aztec code but no qr code at all in this
+    // page, as the rounded modules of a small code break up its finder
+    // patterns; rxing still has to look for qr codes after them.
+    let qr = encode::qr(b"QR-1", Shape::RoundedSquare, 80).unwrap();
+    let aztec = encode::matrix("aztec", b"AZTEC-1", 200).unwrap();
+    let mut page = GrayImage::from_pixel(520, 240, Luma([255]));
+    for (png, x, y) in [(aztec, 20, 20), (qr, 320, 60)] {
+        let code = image::load_from_memory(&png).unwrap().to_luma8();
+        imageops::overlay(&mut page, &code, x, y);
+    }
+    let mut png = vec![];
+    page.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let mut found = payloads(&png, Codes::All);
+    found.sort();
+    assert_eq!(
+        found,
+        [("aztec", b"AZTEC-1".to_vec()), ("qr", b"QR-1".to_vec())]
+    );
+}
+
+/// A micro qr code of version M2-L with mask 0, encoding up to 10 digits,
+/// with `scale` pixels per module and a quiet zone of 2 modules.
+///
+/// Neither fast_qr nor rxing encode micro qr codes.
+fn micro_qr(digits: &str, scale: u32) -> GrayImage {
+    const SIZE: u32 = 13;
+    fn push(bits: &mut Vec<bool>, n: u32, len: usize) {
+        bits.extend((0..len).rev().map(|i| n >> i & 1 == 1));
+    }
+    fn mul(a: u8, b: u8) -> u8 {
+        let (mut a, mut b, mut product) = (a as u16, b, 0);
+        while b > 0 {
+            if b & 1 == 1 {
+                product ^= a;
+            }
+            a <<= 1;
+            if a & 0x100 != 0 {
+                a ^= 0x11d;
+            }
+            b >>= 1;
+        }
+        product as u8
+    }
+
+    // Numeric mode is a single 0 bit in M2, followed by a 4 bit count.
+    let mut bits = vec![false];
+    push(&mut bits, digits.len() as u32, 4);
+    for group in digits.as_bytes().chunks(3) {
+        let n = std::str::from_utf8(group).unwrap().parse().unwrap();
+        push(&mut bits, n, [4, 7, 10][group.len() - 1]);
+    }
+    let terminator = (40 - bits.len()).min(5);
+    let padding = (8 - (bits.len() + terminator) % 8) % 8;
+    push(&mut bits, 0, terminator + padding);
+    let mut data: Vec<u8> = bits
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |n, b| n << 1 | *b as u8))
+        .collect();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if data.len() == 5 {
+            break;
+        }
+        data.push(pad);
+    }
+
+    // 5 Reed-Solomon codewords, with the generator of the roots 1 to α⁴.
+    let mut generator = vec![1];
+    let mut alpha = 1;
+    for _ in 0..5 {
+        let mut next = generator.clone();
+        next.push(0);
+        for (i, c) in generator.iter().enumerate() {
+            next[i + 1] ^= mul(*c, alpha);
+        }
+        generator = next;
+        alpha = mul(alpha, 2);
+    }
+    let mut codewords = data.clone();
+    codewords.extend([0; 5]);
+    for i in 0..data.len() {
+        let c = codewords[i];
+        for (j, g) in generator.iter().enumerate() {
+            codewords[i + j] ^= mul(*g, c);
+        }
+    }
+    codewords[..5].copy_from_slice(&data);
+
+    let mut dark = [[false; SIZE as usize]; SIZE as usize];
+    for (y, row) in dark.iter_mut().enumerate().take(7) {
+        for (x, module) in row.iter_mut().enumerate().take(7) {
+            *module = (x as i32 - 3).abs().max((y as i32 - 3).abs()) != 2;
+        }
+    }
+    for i in (8..SIZE as usize).step_by(2) {
+        dark[0][i] = true;
+        dark[i][0] = true;
+    }
+    // Symbol number 1 (M2-L) and mask 0, with a BCH(15, 5) code.
+    let info = 1 << 2;
+    let mut bch = info << 10;
+    for i in (10..15).rev() {
+        if bch >> i & 1 == 1 {
+            bch ^= 0x537 << (i - 10);
+        }
+    }
+    let format = (info << 10 | bch) ^ 0x4445;
+    let cells = (1..9).map(|x| (x, 8)).chain((1..8).rev().map(|y| (8, y)));
+    for (i, (x, y)) in cells.enumerate() {
+        dark[y][x] = format >> (14 - i) & 1 == 1;
+    }
+    // Pairs of columns from the right, zigzagging up and down.
+    let mut bits = codewords
+        .iter()
+        .flat_map(|c| (0..8).rev().map(move |i| c >> i & 1 == 1));
+    let size = SIZE as usize;
+    for (pair, x) in (2..size).rev().step_by(2).enumerate() {
+        for row in 0..size {
+            let y = if pair % 2 == 0 { size - 1 - row } else { row };
+            for x in [x, x - 1] {
+                if (x < 9 && y < 9) || x == 0 || y == 0 {
+                    continue;
+                }
+                dark[y][x] = bits.next().unwrap() ^ (y % 2 == 0);
+            }
+        }
+    }
+
+    let side = (SIZE + 4) * scale;
+    GrayImage::from_fn(side, side, |x, y| {
+        let (x, y) = ((x / scale).wrapping_sub(2), (y / scale).wrapping_sub(2));
+        match x < SIZE && y < SIZE && dark[y as usize][x as usize] {
+            true => Luma([0]),
+            false => Luma([255]),
+        }
+    })
+}
+
+#[test]
+fn qr_beside_micro_qr() {
+    // A qr code found by the first backend must not keep the micro qr code
+    // from being looked for.
+    let qr = encode::qr(b"QR-1", Shape::Square, 200).unwrap();
+    let qr = image::load_from_memory(&qr).unwrap().to_luma8();
+    let mut page = GrayImage::from_pixel(420, 240, Luma([255]));
+    imageops::overlay(&mut page, &qr, 20, 20);
+    imageops::overlay(&mut page, &micro_qr("12345678", 8), 260, 50);
+    let mut png = vec![];
+    page.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .unwrap();
+    let mut found = payloads(&png, Codes::Qr);
+    found.sort();
+    assert_eq!(
+        found,
+        [("mqr", b"12345678".to_vec()), ("qr", b"QR-1".to_vec())]
+    );
+}
+
+#[test]
+fn barcodes() {
+    for (symbology, text, read) in BARCODES {
+        for scale in [1, 2, 3] {
+            let png = encode::barcode(symbology, text, scale, 60).unwrap();
+            let expected: Vec<_> = read
+                .map(|payload| (symbology, payload.as_bytes().to_vec()))
+                .into_iter()
+                .collect();
+            assert_eq!(
+                payloads(&png, Codes::Barcodes),
+                expected,
+                "{} at {} pixels per module",
+                symbology,
+                scale
+            );
+        }
+    }
+}
+
+#[test]
+fn invalid_input() {
+    assert!(encode::matrix("qr-code", b"x", 100).is_err());
+    assert!(encode::matrix("aztec", &[0xff], 100).is_err());
+    assert!(encode::barcode("ean13", "12", 2, 50).is_err());
+    assert!(encode::barcode("code128", "x", 0, 50).is_err());
+    assert!(decode::scan(b"not an image", Codes::All).is_err());
+}